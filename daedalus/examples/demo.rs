@@ -55,6 +55,21 @@ fn process_character(gs: &mut State, c: char) {
         '1' => {
             gs.mapbuilder.generate(FloorGenAlg::Basic);
         }
+        '2' => {
+            gs.mapbuilder.generate(FloorGenAlg::Bsp(BspSettings::new()));
+        }
+        '3' => {
+            gs.mapbuilder
+                .generate(FloorGenAlg::Caves(CaveSettings::new()));
+        }
+        '4' => {
+            gs.mapbuilder
+                .generate(FloorGenAlg::DrunkardsWalk(DrunkardSettings::new()));
+        }
+        '5' => {
+            gs.mapbuilder
+                .generate(FloorGenAlg::Maze(MazeSettings::new()));
+        }
         '0' => {
             generate_rooms_debug(gs);
         }
@@ -110,6 +125,10 @@ fn draw_panel(ctx: &mut BTerm) {
     ctx.print(52, 8, "d: toggle debug");
 
     ctx.print(52, 15, "1: generate basic map");
+    ctx.print(52, 16, "2: generate bsp map");
+    ctx.print(52, 17, "3: generate cave map");
+    ctx.print(52, 18, "4: generate drunkards map");
+    ctx.print(52, 19, "5: generate maze");
 }
 
 fn draw_debug(mapgen: &mut MapGenerator2D, ctx: &mut BTerm) {
@@ -188,6 +207,9 @@ fn draw_tile(pt: Point, kind: &str, ctx: &mut BTerm) {
         "water" => ('~', RGBA::named(LIGHT_BLUE), RGBA::named(BLUE)),
         "lava" => ('~', RGBA::named(ORANGE), RGBA::named(YELLOW)),
         "chasm" => (' ', RGBA::named(BLACK), RGBA::named(DARK_BLUE)),
+        "door" => ('+', RGBA::named(BROWN1), RGBA::named(BLACK)),
+        "open_door" => ('\'', RGBA::named(BROWN1), RGBA::named(BLACK)),
+        "locked_door" => ('+', RGBA::named(GOLD), RGBA::named(BLACK)),
         _ => ('?', RGBA::named(RED), RGBA::named(RED)),
     };
 
@@ -201,7 +223,7 @@ fn main() -> BError {
         .with_advanced_input(true)
         .build()?;
 
-    let mut mapbuilder = MapGenerator2D::new(50, 50);
+    let mut mapbuilder = MapGenerator2D::new(50, 50).with_doors(DoorSettings::new());
 
     let gs: State = State {
         mapbuilder,
@@ -1,6 +1,8 @@
 use std::fmt;
 
+/// Errors that can occur while generating maps
 pub enum BuilderError {
+    /// A map couldn't be built, and why
     BuildError(String),
 }
 
@@ -1,47 +1,149 @@
+use bracket_geometry::prelude::line2d_bresenham;
 use bracket_pathfinding::prelude::{Algorithm2D, Point};
 use labyrinth_map::prelude::*;
-use std::{collections::HashSet, iter::Map};
+use std::{
+    collections::{HashSet, VecDeque},
+    iter::Map,
+};
 
-use rand::Rng;
+use rand::{Rng, RngExt};
 
+use crate::errors::BuilderError;
 use crate::map_generators::MapGenerator2D;
 use labyrinth_map::prelude::MoveType;
 
 pub mod rooms;
 use rooms::*;
 
+pub mod bsp;
+pub mod caves;
+pub mod doors;
+pub mod drunkards;
+pub mod locks;
+pub mod mazes;
+pub mod prefabs;
+pub mod wfc;
+
 mod shapes;
 
-fn is_fully_connected(map: &mut Labyrinth2D) -> bool {
-    // TODO: Make this work for different kinds of move types?
-    let movtype = MoveType::Walk;
+/// Digs corridors between the separate regions of the map, until every tile
+/// that can be entered with the given [`MoveTypes`](MoveType) is reachable
+/// from every other one.
+///
+/// Each pass joins the closest region to the largest one with an L-shaped
+/// corridor of floor tiles, so the tunnels stay as short as possible. The
+/// regions are worked out once, and the closest one is found with a breadth
+/// first search from everything joined so far, so a pass takes time in
+/// proportion to the size of the map.
+pub fn connect_regions<R: Rng>(
+    mapgen: &mut MapGenerator2D,
+    move_types: &[MoveType],
+    rng: &mut R,
+) -> Result<(), BuilderError> {
+    if !Tile::floor().can_enter(move_types) {
+        return Err(BuilderError::BuildError(format!(
+            "Corridors of floor tiles can't connect regions for {:?}",
+            move_types
+        )));
+    }
 
-    // get a set of walkable tiles
-    let mut walkable: Vec<Point> = map
-        .iter()
-        .enumerate()
-        .filter(|(_, tile)| tile.can_enter(&[movtype.clone()]))
-        .map(|(i, _)| map.index_to_point2d(i))
-        .collect();
+    let components = mapgen.map().connected_components(move_types);
+    let main = match components.largest() {
+        Some(main) if components.count() > 1 => main,
+        _ => return Ok(()),
+    };
+
+    let mut joined = vec![false; components.count()];
+    joined[main] = true;
+    let mut reached = components.regions()[main].clone();
+
+    while let Some((start, end)) =
+        nearest_unjoined_tile(&components, &joined, &reached, mapgen.map().dimensions())
+    {
+        let corner = if rng.random_bool(0.5) {
+            Point::new(end.x, start.y)
+        } else {
+            Point::new(start.x, end.y)
+        };
 
-    // see that every point is accessible from a point
+        let map = mapgen.map_mut();
+        for pt in line2d_bresenham(start, corner)
+            .into_iter()
+            .chain(line2d_bresenham(corner, end))
+        {
+            if !map.can_enter(pt, move_types) {
+                map.set_tile_at(pt, Tile::floor());
+            }
+            // the corridor joins every region it runs through
+            if let Some(region) = components.region_of(pt) {
+                if !joined[region] {
+                    joined[region] = true;
+                    reached.extend_from_slice(&components.regions()[region]);
+                }
+            }
+            reached.push(pt);
+        }
+    }
+    Ok(())
+}
 
-    // get the last walkable point
-    if let Some(pt) = walkable.pop() {
-        // try to find a path to every other point from that one.
+/// Spreads out from all the `reached` tiles at once, until it meets a tile of
+/// a region that isn't joined yet. Returns that tile and the reached tile it
+/// was found from, which are as close together as any such pair.
+fn nearest_unjoined_tile(
+    components: &ConnectedComponents,
+    joined: &[bool],
+    reached: &[Point],
+    dimensions: Point,
+) -> Option<(Point, Point)> {
+    let index = |pt: Point| (pt.y * dimensions.x + pt.x) as usize;
+    let mut origins: Vec<Option<Point>> = vec![None; (dimensions.x * dimensions.y) as usize];
+    let mut queue = VecDeque::new();
+    for &pt in reached {
+        if origins[index(pt)].is_none() {
+            origins[index(pt)] = Some(pt);
+            queue.push_back(pt);
+        }
+    }
 
-        if walkable
-            .iter()
-            .any(|&end| !map.find_path(pt, end, [movtype.clone()]).success)
+    while let Some(pt) = queue.pop_front() {
+        let origin = origins[index(pt)]?;
+        if components
+            .region_of(pt)
+            .is_some_and(|region| !joined[region])
         {
-            return false;
+            return Some((pt, origin));
+        }
+        for delta in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+            let next = pt + Point::from_tuple(delta);
+            if next.x >= 0
+                && next.y >= 0
+                && next.x < dimensions.x
+                && next.y < dimensions.y
+                && origins[index(next)].is_none()
+            {
+                origins[index(next)] = Some(origin);
+                queue.push_back(next);
+            }
         }
-    } else {
-        // Trivial case: if there are no walkable tiles at all
-        return false;
     }
+    None
+}
 
-    true
+/// Fills in every region of the map but the largest one with walls, so that
+/// nothing is left that can't be reached with the given
+/// [`MoveTypes`](MoveType).
+pub fn cull_unreachable(map: &mut Labyrinth2D, move_types: &[MoveType]) {
+    let components = map.connected_components(move_types);
+    if let Some(largest) = components.largest() {
+        for (i, region) in components.regions().iter().enumerate() {
+            if i != largest {
+                for &pt in region {
+                    map.set_tile_at(pt, Tile::wall());
+                }
+            }
+        }
+    }
 }
 
 fn apply_room_to_map<T: Room + ?Sized>(map: &mut Labyrinth2D, room: &T) {
@@ -69,6 +171,14 @@ fn room_in_bounds<T: Room>(mapgen: &mut MapGenerator2D, room: &T) -> bool {
     room.walls().iter().all(|&pt| mapgen.map().in_bounds(pt))
 }
 
+/// Collects a set of points into a Vec with a fixed order, so that picking
+/// from it with a seeded rng is reproducible (HashSet order is not).
+fn sorted_points(points: &HashSet<Point>) -> Vec<Point> {
+    let mut points: Vec<Point> = points.iter().cloned().collect();
+    points.sort_by_key(|pt| (pt.y, pt.x));
+    points
+}
+
 fn fit_room<T: RoomCollisions, U: Rng>(
     mapgen: &mut MapGenerator2D,
     rooms: &CompoundRoom,
@@ -77,18 +187,15 @@ fn fit_room<T: RoomCollisions, U: Rng>(
 ) -> Option<(T, Point)> {
     let attempts = 10;
 
-    // TODO: Make this seedable
-    let mut rng = rand::thread_rng();
-
     // get attachment points of new room
-    let attach_points = newroom.entries();
+    let attach_points = sorted_points(&newroom.entries());
 
     // get attachment points (walls) of current compound room
-    let walls = rooms.walls();
+    let walls = sorted_points(&rooms.walls());
 
     // select an attachment point of new room
-    let idx = rng.gen_range(0..attach_points.len());
-    let attach_point_new = attach_points.iter().nth(idx).unwrap().clone();
+    let idx = rng.random_range(0..attach_points.len());
+    let attach_point_new = attach_points[idx];
     // println!("{:?}", newroom.floor());
 
     //bring the room to (0, 0) for correct transformations
@@ -96,8 +203,8 @@ fn fit_room<T: RoomCollisions, U: Rng>(
 
     // find a valid place to attach
     for _ in 0..attempts {
-        let idx = rng.gen_range(0..walls.len());
-        let attach_point_old = walls.iter().nth(idx).unwrap().clone();
+        let idx = rng.random_range(0..walls.len());
+        let attach_point_old = walls[idx];
 
         for _ in 0..5 {
             // TODO: randomize the transform here?
@@ -121,7 +228,7 @@ fn fit_room<T: RoomCollisions, U: Rng>(
     None
 }
 
-pub fn build_rooms_and_corridors(mapgen: &mut MapGenerator2D) {
+pub fn build_rooms_and_corridors<R: Rng>(mapgen: &mut MapGenerator2D, rng: &mut R) {
     // generate n rooms
     let n = 20;
 
@@ -133,7 +240,6 @@ pub fn build_rooms_and_corridors(mapgen: &mut MapGenerator2D) {
 
     // mapgen.add_room(firstroom);
 
-    let mut rng = rand::thread_rng();
     while rooms.count() <= n {
         // while true { // version where it tries to fill the room
         // generate a rectangle room or a corridor
@@ -143,13 +249,13 @@ pub fn build_rooms_and_corridors(mapgen: &mut MapGenerator2D) {
         //     CompoundRoom::from_room(RectRoom::new(3, 3))
         // };
 
-        let w = rng.gen_range(3..11);
-        let h = rng.gen_range(3..7);
+        let w = rng.random_range(3..11);
+        let h = rng.random_range(3..7);
 
         let newroom = CompoundRoom::from_room(RectRoom::new(w, h));
 
         // try to attach each room to the map
-        if let Some((newroom, connection)) = fit_room(mapgen, &rooms, newroom, &mut rng) {
+        if let Some((newroom, connection)) = fit_room(mapgen, &rooms, newroom, rng) {
             rooms.attach_room(newroom, connection);
         } else {
             break;
@@ -162,6 +268,4 @@ pub fn build_rooms_and_corridors(mapgen: &mut MapGenerator2D) {
     mapgen.update_rooms();
 
     // println!("{:?}", mapgen.rooms());
-
-    // TODO: check that the map is fully connected
 }
@@ -0,0 +1,355 @@
+//! Binary space partition floor generation.
+//!
+//! The map is recursively split into smaller and smaller leaves, a
+//! [`RectRoom`] is placed in each leaf, and the rooms on either side of every
+//! split are joined with [`Hall`] corridors.
+
+use bracket_geometry::prelude::*;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::map_generators::MapGenerator2D;
+
+use super::rooms::*;
+use super::sorted_points;
+
+/// Settings for the [`FloorGenAlg::Bsp`](crate::prelude::FloorGenAlg::Bsp)
+/// algorithm.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let settings = BspSettings::new()
+///     .with_min_leaf_size(10)
+///     .with_split_ratio(0.4)
+///     .with_max_depth(4);
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50);
+/// assert!(mapgen.generate(FloorGenAlg::Bsp(settings)).is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BspSettings {
+    min_leaf_size: i32,
+    split_ratio: f32,
+    max_depth: u32,
+}
+
+impl Default for BspSettings {
+    fn default() -> Self {
+        BspSettings {
+            min_leaf_size: 8,
+            split_ratio: 0.35,
+            max_depth: 6,
+        }
+    }
+}
+
+impl BspSettings {
+    /// Creates a new BspSettings struct with the default settings
+    pub fn new() -> BspSettings {
+        Default::default()
+    }
+
+    /// Sets the minimum width and height of a leaf. Leaves are never split
+    /// into pieces smaller than this. Clamped to at least 5, the smallest leaf
+    /// that fits a room with walls.
+    pub fn with_min_leaf_size(mut self, min_leaf_size: i32) -> BspSettings {
+        self.min_leaf_size = min_leaf_size.max(5);
+        self
+    }
+
+    /// Sets how uneven a split can be, as the smallest fraction of a leaf
+    /// either side of a split gets. Clamped to between 0.1 and 0.5, where 0.5
+    /// always splits leaves in half.
+    pub fn with_split_ratio(mut self, split_ratio: f32) -> BspSettings {
+        self.split_ratio = split_ratio.clamp(0.1, 0.5);
+        self
+    }
+
+    /// Sets the maximum number of times the map is split
+    pub fn with_max_depth(mut self, max_depth: u32) -> BspSettings {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Getter for the minimum leaf size
+    pub fn min_leaf_size(&self) -> i32 {
+        self.min_leaf_size
+    }
+
+    /// Getter for the split ratio
+    pub fn split_ratio(&self) -> f32 {
+        self.split_ratio
+    }
+
+    /// Getter for the maximum split depth
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+}
+
+/// Rooms and corridors placed while partitioning
+struct Partition {
+    rooms: Vec<RectRoom>,
+    halls: Vec<Hall>,
+}
+
+/// Tries to split a leaf in two, returning the two halves.
+fn split_leaf<R: Rng>(leaf: Rect, settings: &BspSettings, rng: &mut R) -> Option<(Rect, Rect)> {
+    let (w, h) = (leaf.width(), leaf.height());
+    let min = settings.min_leaf_size;
+
+    let can_split_x = w >= 2 * min;
+    let can_split_y = h >= 2 * min;
+
+    // prefer splitting across the longer side, so leaves stay squarish
+    let split_x = match (can_split_x, can_split_y) {
+        (false, false) => return None,
+        (true, false) => true,
+        (false, true) => false,
+        (true, true) => {
+            if w as f32 > 1.25 * h as f32 {
+                true
+            } else if h as f32 > 1.25 * w as f32 {
+                false
+            } else {
+                rng.random_bool(0.5)
+            }
+        }
+    };
+
+    let length = if split_x { w } else { h };
+    let lowest = ((length as f32 * settings.split_ratio) as i32).max(min);
+    let highest = length - lowest;
+    if lowest > highest {
+        return None;
+    }
+    let cut = rng.random_range(lowest..=highest);
+
+    Some(if split_x {
+        (
+            Rect::with_size(leaf.x1, leaf.y1, cut, h),
+            Rect::with_size(leaf.x1 + cut, leaf.y1, w - cut, h),
+        )
+    } else {
+        (
+            Rect::with_size(leaf.x1, leaf.y1, w, cut),
+            Rect::with_size(leaf.x1, leaf.y1 + cut, w, h - cut),
+        )
+    })
+}
+
+/// Places a room inside a leaf, leaving space for its walls.
+fn room_in_leaf<R: Rng>(leaf: Rect, rng: &mut R) -> RectRoom {
+    let (max_w, max_h) = (leaf.width() - 2, leaf.height() - 2);
+
+    let w = rng.random_range((max_w / 2).max(3).min(max_w)..=max_w);
+    let h = rng.random_range((max_h / 2).max(3).min(max_h)..=max_h);
+
+    let mut room = RectRoom::new(w, h);
+    room.shift(Point::new(
+        leaf.x1 + 1 + rng.random_range(0..=max_w - w),
+        leaf.y1 + 1 + rng.random_range(0..=max_h - h),
+    ));
+    room
+}
+
+/// L-shaped corridor between two points
+fn corridor<R: Rng>(start: Point, end: Point, rng: &mut R) -> Vec<Hall> {
+    let mut halls = vec![];
+
+    let corner = if rng.random_bool(0.5) {
+        Point::new(end.x, start.y)
+    } else {
+        Point::new(start.x, end.y)
+    };
+
+    for (from, to) in [(start, corner), (corner, end)] {
+        let mut hall = if from.y == to.y {
+            Hall::new_horizontal(to.x - from.x, 1)
+        } else {
+            Hall::new_vertical(to.y - from.y, 1)
+        };
+        hall.shift(from);
+        halls.push(hall);
+    }
+
+    halls
+}
+
+/// Recursively partitions a leaf, returning the indices of the rooms placed
+/// inside of it.
+fn partition<R: Rng>(
+    leaf: Rect,
+    depth: u32,
+    settings: &BspSettings,
+    rng: &mut R,
+    placed: &mut Partition,
+) -> Vec<usize> {
+    let halves = if depth < settings.max_depth {
+        split_leaf(leaf, settings, rng)
+    } else {
+        None
+    };
+
+    match halves {
+        None => {
+            placed.rooms.push(room_in_leaf(leaf, rng));
+            vec![placed.rooms.len() - 1]
+        }
+        Some((left, right)) => {
+            let left = partition(left, depth + 1, settings, rng, placed);
+            let right = partition(right, depth + 1, settings, rng, placed);
+
+            // join the closest pair of rooms across the split
+            let (a, b) = left
+                .iter()
+                .flat_map(|&a| right.iter().map(move |&b| (a, b)))
+                .min_by_key(|&(a, b)| {
+                    let (ca, cb) = (placed.rooms[a].center(), placed.rooms[b].center());
+                    (ca.x - cb.x).abs() + (ca.y - cb.y).abs()
+                })
+                .unwrap();
+
+            let (start, end) = (placed.rooms[a].center(), placed.rooms[b].center());
+            placed.halls.extend(corridor(start, end, rng));
+
+            left.into_iter().chain(right).collect()
+        }
+    }
+}
+
+/// Builds a map by binary space partitioning, placing the rooms and corridors
+/// in the [`CompoundRoom`] of the [`MapGenerator2D`].
+pub fn build_bsp<R: Rng>(mapgen: &mut MapGenerator2D, settings: &BspSettings, rng: &mut R) {
+    let dims = *mapgen.dimensions();
+
+    // keep the outer edge of the map solid
+    let root = Rect::with_size(1, 1, dims.x - 2, dims.y - 2);
+    if root.width() < 5 || root.height() < 5 {
+        return;
+    }
+
+    let mut placed = Partition {
+        rooms: vec![],
+        halls: vec![],
+    };
+    partition(root, 0, settings, rng, &mut placed);
+
+    let mut rooms = CompoundRoom::new();
+
+    // corridors connect to the rooms where they cut through walls, and to
+    // each other where their floors meet. Halls come after the rooms.
+    let n = placed.rooms.len();
+    for (h, hall) in placed.halls.iter().enumerate() {
+        let floor = sorted_points(&hall.floor());
+        for (r, room) in placed.rooms.iter().enumerate() {
+            let walls = room.walls();
+            for &pt in floor.iter().filter(|pt| walls.contains(pt)) {
+                rooms.connections.insert(pt);
+                rooms.link(r, n + h, pt);
+            }
+        }
+
+        for (other, earlier) in placed.halls[..h].iter().enumerate() {
+            let earlier = earlier.floor();
+            if let Some(&pt) = floor.iter().find(|pt| earlier.contains(pt)) {
+                rooms.link(n + other, n + h, pt);
+            }
+        }
+    }
+
+    for room in placed.rooms {
+        rooms.rooms_mut().push(Box::new(room));
+    }
+    for hall in placed.halls {
+        rooms.rooms_mut().push(Box::new(hall));
+    }
+
+    mapgen.add_compound_room(rooms);
+    mapgen.update_rooms();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+    use bracket_pathfinding::prelude::*;
+
+    #[test]
+    fn settings_are_clamped() {
+        let settings = BspSettings::new()
+            .with_min_leaf_size(1)
+            .with_split_ratio(0.9);
+
+        assert_eq!(settings.min_leaf_size(), 5);
+        assert_eq!(settings.split_ratio(), 0.5);
+    }
+
+    #[test]
+    fn no_splits_makes_one_room() {
+        let mut mapgen = MapGenerator2D::new(30, 20).with_seed(3);
+        let settings = BspSettings::new().with_max_depth(0);
+
+        mapgen.generate(FloorGenAlg::Bsp(settings)).unwrap();
+
+        assert_eq!(mapgen.rooms().count(), 1);
+        assert!(mapgen.connections().is_empty());
+    }
+
+    #[test]
+    fn rooms_stay_inside_the_map() {
+        let mut mapgen = MapGenerator2D::new(80, 50).with_seed(11);
+        let map = mapgen
+            .generate(FloorGenAlg::Bsp(BspSettings::new()))
+            .unwrap()
+            .into_map();
+
+        assert!(mapgen.rooms().count() > 2);
+        assert!(mapgen
+            .rooms()
+            .all_points()
+            .iter()
+            .all(|&pt| map.in_bounds(pt)));
+
+        // the outer edge stays solid
+        for x in 0..80 {
+            assert_eq!(map.tile_kind(Point::new(x, 0)), "wall");
+            assert_eq!(map.tile_kind(Point::new(x, 49)), "wall");
+        }
+    }
+
+    #[test]
+    fn leaf_count_is_limited_by_depth() {
+        let mut mapgen = MapGenerator2D::new(120, 120).with_seed(5);
+        let settings = BspSettings::new().with_max_depth(2).with_min_leaf_size(5);
+
+        mapgen.generate(FloorGenAlg::Bsp(settings)).unwrap();
+
+        // 4 leaves, each with a room, plus 2 halls for each of the 3 splits
+        assert_eq!(mapgen.rooms().count(), 4 + 3 * 2);
+    }
+
+    #[test]
+    fn bsp_map_is_connected() {
+        for seed in 0..5 {
+            let mut mapgen = MapGenerator2D::new(60, 40).with_seed(seed);
+            let map = mapgen
+                .generate(FloorGenAlg::Bsp(BspSettings::new()))
+                .unwrap()
+                .into_map();
+
+            let floors: Vec<Point> = (0..map.size())
+                .map(|idx| map.index_to_point2d(idx))
+                .filter(|&pt| map.can_enter(pt, &[MoveType::Walk]))
+                .collect();
+
+            let dmap = map.dijkstra_map(&floors[..1], [MoveType::Walk]);
+            assert!(floors
+                .iter()
+                .all(|&pt| dmap.map[map.point2d_to_index(pt)] < f32::MAX));
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Cellular automata cave generation.
+//!
+//! The map is seeded with random wall/floor noise, which is then smoothed
+//! over a number of passes into organic looking caves. Afterwards, only the
+//! largest cave is kept, or all caves are connected with tunnels.
+
+use bracket_geometry::prelude::*;
+use labyrinth_map::prelude::*;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::map_generators::MapGenerator2D;
+
+use super::{connect_regions, cull_unreachable};
+
+/// What to do with the separate caves left after smoothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaveRegions {
+    /// Only keep the largest cave, filling in the rest
+    KeepLargest,
+
+    /// Keep every cave, digging tunnels between them
+    ConnectAll,
+}
+
+/// Settings for the [`FloorGenAlg::Caves`](crate::prelude::FloorGenAlg::Caves)
+/// algorithm.
+///
+/// Walls follow a birth/survival rule over their 8 neighbors: a floor tile
+/// becomes a wall if at least `birth_limit` of its neighbors are walls, and a
+/// wall stays a wall if at least `survival_limit` of its neighbors are walls.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let settings = CaveSettings::new()
+///     .with_fill_probability(0.5)
+///     .with_iterations(4)
+///     .with_regions(CaveRegions::ConnectAll);
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50);
+/// assert!(mapgen.generate(FloorGenAlg::Caves(settings)).is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CaveSettings {
+    fill_probability: f64,
+    iterations: u32,
+    birth_limit: u32,
+    survival_limit: u32,
+    regions: CaveRegions,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        CaveSettings {
+            fill_probability: 0.45,
+            iterations: 5,
+            birth_limit: 5,
+            survival_limit: 4,
+            regions: CaveRegions::KeepLargest,
+        }
+    }
+}
+
+impl CaveSettings {
+    /// Creates a new CaveSettings struct with the default settings
+    pub fn new() -> CaveSettings {
+        Default::default()
+    }
+
+    /// Sets the chance of each tile starting out as a wall
+    pub fn with_fill_probability(mut self, fill_probability: f64) -> CaveSettings {
+        self.fill_probability = fill_probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the number of smoothing passes
+    pub fn with_iterations(mut self, iterations: u32) -> CaveSettings {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets the number of wall neighbors needed for a floor to become a wall
+    pub fn with_birth_limit(mut self, birth_limit: u32) -> CaveSettings {
+        self.birth_limit = birth_limit;
+        self
+    }
+
+    /// Sets the number of wall neighbors needed for a wall to stay a wall
+    pub fn with_survival_limit(mut self, survival_limit: u32) -> CaveSettings {
+        self.survival_limit = survival_limit;
+        self
+    }
+
+    /// Sets what to do with separate caves
+    pub fn with_regions(mut self, regions: CaveRegions) -> CaveSettings {
+        self.regions = regions;
+        self
+    }
+
+    /// Getter for the initial wall probability
+    pub fn fill_probability(&self) -> f64 {
+        self.fill_probability
+    }
+
+    /// Getter for the number of smoothing passes
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Getter for the birth limit
+    pub fn birth_limit(&self) -> u32 {
+        self.birth_limit
+    }
+
+    /// Getter for the survival limit
+    pub fn survival_limit(&self) -> u32 {
+        self.survival_limit
+    }
+
+    /// Getter for the region handling
+    pub fn regions(&self) -> CaveRegions {
+        self.regions
+    }
+}
+
+/// Grid of walls (true) and floors (false) the automaton runs on
+struct CaveGrid {
+    cells: Vec<bool>,
+    width: i32,
+    height: i32,
+}
+
+impl CaveGrid {
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        // everything off the map counts as wall, so caves close at the edges
+        x < 0 || y < 0 || x >= self.width || y >= self.height || self.cells[self.idx(x, y)]
+    }
+
+    fn idx(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn on_edge(&self, x: i32, y: i32) -> bool {
+        x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1
+    }
+
+    fn wall_neighbors(&self, x: i32, y: i32) -> u32 {
+        let mut count = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) && self.is_wall(x + dx, y + dy) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn smooth(&mut self, settings: &CaveSettings) {
+        let mut next = self.cells.clone();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let walls = self.wall_neighbors(x, y);
+                next[self.idx(x, y)] = self.on_edge(x, y)
+                    || if self.is_wall(x, y) {
+                        walls >= settings.survival_limit
+                    } else {
+                        walls >= settings.birth_limit
+                    };
+            }
+        }
+
+        self.cells = next;
+    }
+}
+
+/// Builds a cave map with a cellular automaton, directly on the
+/// [`Labyrinth2D`] of the [`MapGenerator2D`].
+pub fn build_caves<R: Rng>(mapgen: &mut MapGenerator2D, settings: &CaveSettings, rng: &mut R) {
+    let dims = *mapgen.dimensions();
+
+    let mut grid = CaveGrid {
+        cells: vec![true; (dims.x * dims.y) as usize],
+        width: dims.x,
+        height: dims.y,
+    };
+
+    // random noise to start
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let idx = grid.idx(x, y);
+            grid.cells[idx] = grid.on_edge(x, y) || rng.random_bool(settings.fill_probability);
+        }
+    }
+
+    for _ in 0..settings.iterations {
+        grid.smooth(settings);
+    }
+
+    let map = mapgen.map_mut();
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if !grid.is_wall(x, y) {
+                map.set_tile_at(Point::new(x, y), Tile::floor());
+            }
+        }
+    }
+
+    match settings.regions {
+        CaveRegions::KeepLargest => cull_unreachable(map, &[MoveType::Walk]),
+        CaveRegions::ConnectAll => {
+            // floor tiles can always be walked on, so this can't fail
+            let _ = connect_regions(mapgen, &[MoveType::Walk], rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+    use bracket_pathfinding::prelude::*;
+
+    fn floors(map: &Labyrinth2D) -> Vec<Point> {
+        (0..map.size())
+            .map(|idx| map.index_to_point2d(idx))
+            .filter(|&pt| map.can_enter(pt, &[MoveType::Walk]))
+            .collect()
+    }
+
+    fn is_connected(map: &Labyrinth2D) -> bool {
+        let floors = floors(map);
+        let dmap = map.dijkstra_map(&floors[..1], [MoveType::Walk]);
+        floors
+            .iter()
+            .all(|&pt| dmap.map[map.point2d_to_index(pt)] < f32::MAX)
+    }
+
+    #[test]
+    fn edges_are_walls() {
+        let mut mapgen = MapGenerator2D::new(40, 30).with_seed(9);
+        let map = mapgen
+            .generate(FloorGenAlg::Caves(CaveSettings::new()))
+            .unwrap()
+            .into_map();
+
+        for x in 0..40 {
+            assert_eq!(map.tile_kind(Point::new(x, 0)), "wall");
+            assert_eq!(map.tile_kind(Point::new(x, 29)), "wall");
+        }
+        for y in 0..30 {
+            assert_eq!(map.tile_kind(Point::new(0, y)), "wall");
+            assert_eq!(map.tile_kind(Point::new(39, y)), "wall");
+        }
+    }
+
+    #[test]
+    fn caves_are_connected() {
+        for regions in [CaveRegions::KeepLargest, CaveRegions::ConnectAll] {
+            for seed in 0..3 {
+                let mut mapgen = MapGenerator2D::new(60, 40).with_seed(seed);
+                let settings = CaveSettings::new().with_regions(regions);
+                let map = mapgen
+                    .generate(FloorGenAlg::Caves(settings))
+                    .unwrap()
+                    .into_map();
+
+                assert!(!floors(&map).is_empty());
+                assert!(is_connected(&map));
+            }
+        }
+    }
+
+    #[test]
+    fn connecting_keeps_more_floor() {
+        let mut mapgen = MapGenerator2D::new(60, 40);
+
+        let settings = CaveSettings::new().with_fill_probability(0.5);
+        let largest = mapgen
+            .generate_seeded(FloorGenAlg::Caves(settings.clone()), 4)
+            .unwrap()
+            .into_map();
+        let connected = mapgen
+            .generate_seeded(
+                FloorGenAlg::Caves(settings.with_regions(CaveRegions::ConnectAll)),
+                4,
+            )
+            .unwrap()
+            .into_map();
+
+        assert!(floors(&connected).len() >= floors(&largest).len());
+    }
+
+    #[test]
+    fn smoothing_fills_isolated_floor() {
+        let mut grid = CaveGrid {
+            cells: vec![true; 25],
+            width: 5,
+            height: 5,
+        };
+        grid.cells[12] = false;
+
+        grid.smooth(&CaveSettings::new());
+
+        assert!(grid.cells.iter().all(|&wall| wall));
+    }
+}
@@ -0,0 +1,147 @@
+//! Door placement at the connections between rooms.
+
+use bracket_pathfinding::prelude::Algorithm2D;
+use labyrinth_map::prelude::*;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::map_generators::MapGenerator2D;
+
+use super::sorted_points;
+
+/// Settings for the doors a [`MapGenerator2D`] places where rooms connect.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let doors = DoorSettings::new()
+///     .with_probability(1.0)
+///     .with_locked(0.25, MoveType::custom("red_key"));
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50).with_doors(doors);
+/// let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+///
+/// for &pt in level.connections() {
+///     assert!(level.map().door_state(pt).is_some());
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DoorSettings {
+    probability: f64,
+    locked_probability: f64,
+    key: MoveType,
+}
+
+impl Default for DoorSettings {
+    fn default() -> Self {
+        DoorSettings {
+            probability: 0.5,
+            locked_probability: 0.0,
+            key: MoveType::custom("key"),
+        }
+    }
+}
+
+impl DoorSettings {
+    /// Creates a new DoorSettings struct with the default settings
+    pub fn new() -> DoorSettings {
+        Default::default()
+    }
+
+    /// Sets the chance of a door being placed at each connection
+    pub fn with_probability(mut self, probability: f64) -> DoorSettings {
+        self.probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the chance of a placed door being locked, and the custom
+    /// [`MoveType`] that is the key to the locked doors
+    pub fn with_locked(mut self, probability: f64, key: MoveType) -> DoorSettings {
+        self.locked_probability = probability.clamp(0.0, 1.0);
+        self.key = key;
+        self
+    }
+
+    /// Getter for the chance of a door at each connection
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Getter for the chance of a door being locked
+    pub fn locked_probability(&self) -> f64 {
+        self.locked_probability
+    }
+
+    /// Getter for the key to the locked doors
+    pub fn key(&self) -> &MoveType {
+        &self.key
+    }
+}
+
+/// Places closed or locked doors at the connections between rooms, on the
+/// ones that are floor.
+pub fn place_doors<R: Rng>(mapgen: &mut MapGenerator2D, settings: &DoorSettings, rng: &mut R) {
+    for pt in sorted_points(mapgen.connections()) {
+        let map = mapgen.map_mut();
+        if !map.in_bounds(pt) || *map.tile_kind(pt) != "floor" {
+            continue;
+        }
+
+        if rng.random_bool(settings.probability) {
+            let door = if rng.random_bool(settings.locked_probability) {
+                Tile::locked_door(settings.key.clone())
+            } else {
+                Tile::door()
+            };
+            map.set_tile_at(pt, door);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::FloorGenAlg;
+
+    #[test]
+    fn no_doors_by_default() {
+        let mut mapgen = MapGenerator2D::new(60, 40).with_seed(3);
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+
+        assert!(!level.connections().is_empty());
+        assert!(level
+            .connections()
+            .iter()
+            .all(|&pt| level.map().door_state(pt).is_none()));
+    }
+
+    #[test]
+    fn doors_keep_the_map_connected() {
+        let settings = DoorSettings::new()
+            .with_probability(1.0)
+            .with_locked(0.5, MoveType::custom("red_key"));
+        let mut mapgen = MapGenerator2D::new(60, 40)
+            .with_seed(3)
+            .with_doors(settings);
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+        let map = level.map();
+
+        let states: Vec<_> = level
+            .connections()
+            .iter()
+            .filter_map(|&pt| map.door_state(pt))
+            .collect();
+        assert_eq!(states.len(), level.connections().len());
+        assert!(states.contains(&DoorState::Locked));
+
+        let movers = [
+            MoveType::Walk,
+            MoveType::opener(),
+            MoveType::custom("red_key"),
+        ];
+        assert!(map.is_fully_connected(movers));
+        assert!(!map.is_fully_connected([MoveType::Walk]));
+    }
+}
@@ -0,0 +1,398 @@
+//! Drunkard's walk generation.
+//!
+//! Walkers stumble around a solid map in random directions, digging floor
+//! wherever they step, until enough of the map is floor or they run out of
+//! steps. The result is winding tunnels and mines, rather than rooms.
+
+use bracket_geometry::prelude::*;
+use labyrinth_map::prelude::*;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::map_generators::MapGenerator2D;
+
+/// Where the walkers of a drunkard's walk start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrunkardSpawn {
+    /// Every walker starts in the centre of the map
+    Centre,
+
+    /// The first walker starts in the centre, and the rest on a random tile
+    /// that has already been dug
+    RandomFloor,
+
+    /// Every walker starts on a random tile just inside the edge of the map.
+    /// The tunnels of separate walkers might not meet, and are joined by the
+    /// connectivity repair of the [`MapGenerator2D`].
+    Edge,
+}
+
+/// Mirroring applied to everything the walkers dig.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrunkardSymmetry {
+    /// No mirroring
+    None,
+
+    /// Mirrored left to right
+    Horizontal,
+
+    /// Mirrored top to bottom
+    Vertical,
+
+    /// Mirrored both ways, into four quarters
+    Both,
+}
+
+/// Settings for the
+/// [`FloorGenAlg::DrunkardsWalk`](crate::prelude::FloorGenAlg::DrunkardsWalk)
+/// algorithm.
+///
+/// Walkers are sent out one at a time, each taking up to `steps` steps, until
+/// `floor_percent` of the map is floor or all the walkers are spent. Floor
+/// that was already on the map counts towards it, so a walk on top of another
+/// algorithm only digs what is missing.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let settings = DrunkardSettings::new()
+///     .with_walkers(20)
+///     .with_steps(300)
+///     .with_floor_percent(0.35)
+///     .with_spawn(DrunkardSpawn::RandomFloor)
+///     .with_brush(2)
+///     .with_symmetry(DrunkardSymmetry::Horizontal);
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50);
+/// assert!(mapgen.generate(FloorGenAlg::DrunkardsWalk(settings)).is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DrunkardSettings {
+    walkers: usize,
+    steps: usize,
+    floor_percent: f64,
+    spawn: DrunkardSpawn,
+    brush: i32,
+    symmetry: DrunkardSymmetry,
+}
+
+impl Default for DrunkardSettings {
+    fn default() -> Self {
+        DrunkardSettings {
+            walkers: 50,
+            steps: 400,
+            floor_percent: 0.4,
+            spawn: DrunkardSpawn::Centre,
+            brush: 1,
+            symmetry: DrunkardSymmetry::None,
+        }
+    }
+}
+
+impl DrunkardSettings {
+    /// Creates a new DrunkardSettings struct with the default settings
+    pub fn new() -> DrunkardSettings {
+        Default::default()
+    }
+
+    /// Sets the most walkers to send out
+    pub fn with_walkers(mut self, walkers: usize) -> DrunkardSettings {
+        self.walkers = walkers.max(1);
+        self
+    }
+
+    /// Sets the number of steps each walker takes
+    pub fn with_steps(mut self, steps: usize) -> DrunkardSettings {
+        self.steps = steps;
+        self
+    }
+
+    /// Sets the share of the map to dig out, from 0 to 1
+    pub fn with_floor_percent(mut self, floor_percent: f64) -> DrunkardSettings {
+        self.floor_percent = floor_percent.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets where the walkers start
+    pub fn with_spawn(mut self, spawn: DrunkardSpawn) -> DrunkardSettings {
+        self.spawn = spawn;
+        self
+    }
+
+    /// Sets the width of the square the walkers dig at every step
+    pub fn with_brush(mut self, brush: i32) -> DrunkardSettings {
+        self.brush = brush.max(1);
+        self
+    }
+
+    /// Sets the mirroring of the dug tunnels
+    pub fn with_symmetry(mut self, symmetry: DrunkardSymmetry) -> DrunkardSettings {
+        self.symmetry = symmetry;
+        self
+    }
+
+    /// Getter for the most walkers to send out
+    pub fn walkers(&self) -> usize {
+        self.walkers
+    }
+
+    /// Getter for the steps of each walker
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Getter for the share of the map to dig out
+    pub fn floor_percent(&self) -> f64 {
+        self.floor_percent
+    }
+
+    /// Getter for where the walkers start
+    pub fn spawn(&self) -> DrunkardSpawn {
+        self.spawn
+    }
+
+    /// Getter for the brush width
+    pub fn brush(&self) -> i32 {
+        self.brush
+    }
+
+    /// Getter for the mirroring
+    pub fn symmetry(&self) -> DrunkardSymmetry {
+        self.symmetry
+    }
+}
+
+/// Digs out the floor under a walker, keeping track of every new floor tile
+struct Digger<'a> {
+    map: &'a mut Labyrinth2D,
+    dims: Point,
+    settings: &'a DrunkardSettings,
+    dug: Vec<Point>,
+    floor: usize,
+}
+
+impl Digger<'_> {
+    /// Whether a point is inside the border of walls around the map
+    fn inside(&self, pt: Point) -> bool {
+        pt.x > 0 && pt.y > 0 && pt.x < self.dims.x - 1 && pt.y < self.dims.y - 1
+    }
+
+    /// Digs the brush around a point, and its mirror images
+    fn dig(&mut self, pt: Point) {
+        let offset = (self.settings.brush - 1) / 2;
+
+        for dy in 0..self.settings.brush {
+            for dx in 0..self.settings.brush {
+                let pt = pt + Point::new(dx - offset, dy - offset);
+                let (flip_x, flip_y) = (self.dims.x - 1 - pt.x, self.dims.y - 1 - pt.y);
+
+                let mirrored = match self.settings.symmetry {
+                    DrunkardSymmetry::None => vec![pt],
+                    DrunkardSymmetry::Horizontal => vec![pt, Point::new(flip_x, pt.y)],
+                    DrunkardSymmetry::Vertical => vec![pt, Point::new(pt.x, flip_y)],
+                    DrunkardSymmetry::Both => vec![
+                        pt,
+                        Point::new(flip_x, pt.y),
+                        Point::new(pt.x, flip_y),
+                        Point::new(flip_x, flip_y),
+                    ],
+                };
+
+                for pt in mirrored {
+                    if self.inside(pt) && *self.map.tile_kind(pt) != "floor" {
+                        self.map.set_tile_at(pt, Tile::floor());
+                        self.dug.push(pt);
+                        self.floor += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Digs tunnels with drunkard's walks, directly on the [`Labyrinth2D`] of the
+/// [`MapGenerator2D`]. The edges of the map are left as walls.
+pub fn build_drunkards_walk<R: Rng>(
+    mapgen: &mut MapGenerator2D,
+    settings: &DrunkardSettings,
+    rng: &mut R,
+) {
+    let dims = *mapgen.dimensions();
+    if dims.x < 3 || dims.y < 3 {
+        return;
+    }
+
+    let target = (settings.floor_percent * ((dims.x - 2) * (dims.y - 2)) as f64) as usize;
+    let centre = dims / 2;
+
+    let mut digger = Digger {
+        map: mapgen.map_mut(),
+        dims,
+        settings,
+        dug: vec![],
+        floor: 0,
+    };
+    digger.floor = (1..dims.y - 1)
+        .flat_map(|y| (1..dims.x - 1).map(move |x| Point::new(x, y)))
+        .filter(|&pt| *digger.map.tile_kind(pt) == "floor")
+        .count();
+
+    for walker in 0..settings.walkers {
+        if digger.floor >= target {
+            break;
+        }
+
+        let mut pos = match settings.spawn {
+            DrunkardSpawn::Centre => centre,
+            DrunkardSpawn::RandomFloor if walker == 0 || digger.dug.is_empty() => centre,
+            DrunkardSpawn::RandomFloor => digger.dug[rng.random_range(0..digger.dug.len())],
+            DrunkardSpawn::Edge => match rng.random_range(0..4) {
+                0 => Point::new(rng.random_range(1..dims.x - 1), 1),
+                1 => Point::new(rng.random_range(1..dims.x - 1), dims.y - 2),
+                2 => Point::new(1, rng.random_range(1..dims.y - 1)),
+                _ => Point::new(dims.x - 2, rng.random_range(1..dims.y - 1)),
+            },
+        };
+        digger.dig(pos);
+
+        for _ in 0..settings.steps {
+            if digger.floor >= target {
+                break;
+            }
+
+            let next = pos
+                + match rng.random_range(0..4) {
+                    0 => Point::new(0, -1),
+                    1 => Point::new(0, 1),
+                    2 => Point::new(-1, 0),
+                    _ => Point::new(1, 0),
+                };
+
+            // walkers stumble into the border and stay put
+            if digger.inside(next) {
+                pos = next;
+                digger.dig(pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+    use bracket_pathfinding::prelude::*;
+    use rand::SeedableRng;
+
+    fn floor_count(map: &Labyrinth2D) -> usize {
+        (0..map.size())
+            .filter(|&idx| map.can_enter(map.index_to_point2d(idx), &[MoveType::Walk]))
+            .count()
+    }
+
+    #[test]
+    fn digs_to_the_target_and_keeps_walls() {
+        let settings = DrunkardSettings::new().with_floor_percent(0.3);
+        let mut mapgen = MapGenerator2D::new(40, 30).with_seed(5);
+        let map = mapgen
+            .generate(FloorGenAlg::DrunkardsWalk(settings))
+            .unwrap()
+            .into_map();
+
+        assert!(floor_count(&map) >= (0.3 * (38 * 28) as f64) as usize);
+        assert!(map.is_fully_connected([MoveType::Walk]));
+
+        for x in 0..40 {
+            assert_eq!(map.tile_kind(Point::new(x, 0)), "wall");
+            assert_eq!(map.tile_kind(Point::new(x, 29)), "wall");
+        }
+        for y in 0..30 {
+            assert_eq!(map.tile_kind(Point::new(0, y)), "wall");
+            assert_eq!(map.tile_kind(Point::new(39, y)), "wall");
+        }
+    }
+
+    #[test]
+    fn existing_floor_counts_to_the_target() {
+        let settings = DrunkardSettings::new().with_floor_percent(0.5);
+        let mut rng = MapRng::seed_from_u64(3);
+
+        // an open map is already all floor
+        let mut mapgen = MapGenerator2D::new(40, 30);
+        mapgen.walled_map();
+        let open = mapgen.map().clone();
+        build_drunkards_walk(&mut mapgen, &settings, &mut rng);
+        assert_eq!(mapgen.map(), &open);
+
+        // and a map that is partly floor is only dug up to the target
+        mapgen.flush_map();
+        for x in 1..39 {
+            for y in 1..8 {
+                mapgen
+                    .map_mut()
+                    .set_tile_at(Point::new(x, y), Tile::floor());
+            }
+        }
+        build_drunkards_walk(&mut mapgen, &settings, &mut rng);
+        let target = (0.5 * (38 * 28) as f64) as usize;
+        assert!(floor_count(mapgen.map()) >= target);
+        assert!(floor_count(mapgen.map()) < target + 10);
+    }
+
+    #[test]
+    fn step_budget_limits_digging() {
+        let settings = DrunkardSettings::new()
+            .with_walkers(1)
+            .with_steps(10)
+            .with_floor_percent(1.0);
+        let mut mapgen = MapGenerator2D::new(40, 30).with_seed(5);
+        let map = mapgen
+            .generate(FloorGenAlg::DrunkardsWalk(settings))
+            .unwrap()
+            .into_map();
+
+        assert!(floor_count(&map) <= 11);
+        assert!(map.can_enter(Point::new(20, 15), &[MoveType::Walk]));
+    }
+
+    #[test]
+    fn spawns_all_connect() {
+        for spawn in [
+            DrunkardSpawn::Centre,
+            DrunkardSpawn::RandomFloor,
+            DrunkardSpawn::Edge,
+        ] {
+            let settings = DrunkardSettings::new().with_spawn(spawn).with_brush(2);
+            let mut mapgen = MapGenerator2D::new(40, 30).with_seed(8);
+            let map = mapgen
+                .generate(FloorGenAlg::DrunkardsWalk(settings))
+                .unwrap()
+                .into_map();
+
+            assert!(floor_count(&map) > 0);
+            assert!(map.is_fully_connected([MoveType::Walk]));
+        }
+    }
+
+    #[test]
+    fn symmetry_mirrors_the_tunnels() {
+        let settings = DrunkardSettings::new().with_symmetry(DrunkardSymmetry::Both);
+        let mut mapgen = MapGenerator2D::new(40, 30)
+            .with_seed(3)
+            .without_connectivity_repair();
+        let map = mapgen
+            .generate(FloorGenAlg::DrunkardsWalk(settings))
+            .unwrap()
+            .into_map();
+
+        for y in 0..30 {
+            for x in 0..40 {
+                let kind = map.tile_kind(Point::new(x, y));
+                assert_eq!(kind, map.tile_kind(Point::new(39 - x, y)));
+                assert_eq!(kind, map.tile_kind(Point::new(x, 29 - y)));
+            }
+        }
+    }
+}
@@ -0,0 +1,523 @@
+//! Lock-and-key puzzles over the room graph of a level.
+//!
+//! Locked doors are placed on the critical path from the start room to the
+//! exit room, and the key to each one is put in a room that can be reached
+//! with the keys to the locks before it. Keys are kept off the critical path
+//! when possible, so that players have to explore side branches.
+
+use std::collections::VecDeque;
+
+use bracket_pathfinding::prelude::{Algorithm2D, Point};
+use labyrinth_map::prelude::*;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::level::GeneratedLevel;
+
+/// Names of the first keys, after which keys are numbered
+const KEY_COLOURS: [&str; 6] = ["red", "blue", "green", "yellow", "purple", "orange"];
+
+/// Settings for the lock-and-key puzzle of a
+/// [`MapGenerator2D`](crate::prelude::MapGenerator2D).
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50)
+///     .with_seed(2)
+///     .with_locks(LockSettings::new().with_locks(2));
+/// let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+///
+/// let puzzle = level.lock_puzzle().unwrap();
+/// for lock in puzzle.locks() {
+///     assert_eq!(level.map().door_state(lock.door()), Some(DoorState::Locked));
+/// }
+/// assert!(puzzle.is_solvable(&level));
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockSettings {
+    locks: usize,
+}
+
+impl Default for LockSettings {
+    fn default() -> Self {
+        LockSettings { locks: 2 }
+    }
+}
+
+impl LockSettings {
+    /// Creates a new LockSettings struct with the default settings
+    pub fn new() -> LockSettings {
+        Default::default()
+    }
+
+    /// Sets the most locked doors to place. Fewer are placed if the critical
+    /// path doesn't have enough connections that can't be walked around.
+    pub fn with_locks(mut self, locks: usize) -> LockSettings {
+        self.locks = locks;
+        self
+    }
+
+    /// Getter for the most locked doors to place
+    pub fn locks(&self) -> usize {
+        self.locks
+    }
+}
+
+/// A locked door and where its key is
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lock {
+    key: MoveType,
+    door: Point,
+    key_at: Point,
+    key_room: usize,
+}
+
+impl Lock {
+    /// Getter for the key: the custom [`MoveType`] that enters the door
+    pub fn key(&self) -> &MoveType {
+        &self.key
+    }
+
+    /// Getter for the position of the locked door
+    pub fn door(&self) -> Point {
+        self.door
+    }
+
+    /// Getter for the position of the key
+    pub fn key_at(&self) -> Point {
+        self.key_at
+    }
+
+    /// Getter for the room the key is in
+    pub fn key_room(&self) -> usize {
+        self.key_room
+    }
+}
+
+/// A step of the solution to a [`LockPuzzle`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PuzzleStep {
+    /// Go to the key of a lock and pick it up
+    CollectKey(usize),
+
+    /// Go through the door of a lock
+    Unlock(usize),
+}
+
+/// The locks of a level, in the order they have to be opened, with the order
+/// to collect keys and open doors in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockPuzzle {
+    locks: Vec<Lock>,
+    // keys of the locked doors that were already on the map
+    free_keys: Vec<MoveType>,
+    solution: Vec<PuzzleStep>,
+}
+
+impl LockPuzzle {
+    /// Getter for the locks, in the order they are met on the way to the exit
+    pub fn locks(&self) -> &Vec<Lock> {
+        &self.locks
+    }
+
+    /// Getter for the solution: the order to collect keys and go through
+    /// locked doors in, from the spawn to the exit
+    pub fn solution(&self) -> &Vec<PuzzleStep> {
+        &self.solution
+    }
+
+    /// Checks the solution against the map of a level: every key can be
+    /// reached with the keys collected before it, and the exit can be
+    /// reached with all of them.
+    pub fn is_solvable(&self, level: &GeneratedLevel) -> bool {
+        let (Some(spawn), Some(exit)) = (level.spawn(), level.exit()) else {
+            return self.locks.is_empty();
+        };
+
+        let mut keys = vec![];
+        for step in self.solution.iter() {
+            let regions = self.regions(level.map(), &keys);
+            match *step {
+                PuzzleStep::CollectKey(i) => {
+                    if !joined(&regions, spawn, self.locks[i].key_at) {
+                        return false;
+                    }
+                    keys.push(self.locks[i].key.clone());
+                }
+                PuzzleStep::Unlock(i) => {
+                    if !keys.contains(&self.locks[i].key)
+                        || !joined(&regions, spawn, self.locks[i].door)
+                    {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        joined(&self.regions(level.map(), &keys), spawn, exit)
+    }
+
+    /// The regions of a map for a walker holding some keys
+    fn regions(&self, map: &Labyrinth2D, keys: &[MoveType]) -> ConnectedComponents {
+        let mut movers = vec![MoveType::Walk, MoveType::opener()];
+        movers.extend(self.free_keys.iter().cloned());
+        movers.extend(keys.iter().cloned());
+
+        map.connected_components(movers)
+    }
+}
+
+/// Whether two points can be entered and are in the same region
+fn joined(regions: &ConnectedComponents, a: Point, b: Point) -> bool {
+    regions.region_of(a).is_some() && regions.region_of(a) == regions.region_of(b)
+}
+
+/// The name of the key to the `i`th lock
+fn key_name(i: usize) -> MoveType {
+    match KEY_COLOURS.get(i) {
+        Some(colour) => MoveType::custom(format!("key_{}", colour)),
+        None => MoveType::custom(format!("key_{}", i)),
+    }
+}
+
+/// Which tiles of a level a walker can get through, worked out once so that
+/// locks can be tried out without changing the map. A walker opens doors,
+/// and has the keys to the locked doors that were already on the map.
+struct Passage {
+    dimensions: Point,
+    neighborhood: Neighborhood,
+    open: Vec<bool>,
+    opaque: Vec<bool>,
+    walkable: Vec<bool>,
+}
+
+impl Passage {
+    fn new(map: &Labyrinth2D, puzzle: &LockPuzzle) -> Passage {
+        let mut movers = vec![MoveType::Walk, MoveType::opener()];
+        movers.extend(puzzle.free_keys.iter().cloned());
+
+        Passage {
+            dimensions: map.dimensions(),
+            neighborhood: map.neighborhood(),
+            open: map.iter().map(|tile| tile.can_enter(&movers)).collect(),
+            opaque: map.iter().map(Tile::is_opaque).collect(),
+            walkable: map
+                .iter()
+                .map(|tile| tile.can_enter(&[MoveType::Walk]))
+                .collect(),
+        }
+    }
+
+    fn index(&self, pt: Point) -> Option<usize> {
+        let inside = pt.x >= 0 && pt.y >= 0 && pt.x < self.dimensions.x && pt.y < self.dimensions.y;
+        inside.then(|| (pt.y * self.dimensions.x + pt.x) as usize)
+    }
+
+    /// The tiles that can be reached from the spawn, with some doors locked.
+    /// Each lock is passable if its key is held.
+    fn reached(&self, spawn: Point, locks: &[(Point, bool)]) -> Vec<bool> {
+        let mut open = self.open.clone();
+        let mut opaque = self.opaque.clone();
+        for &(at, held) in locks.iter() {
+            if let Some(idx) = self.index(at) {
+                open[idx] = held;
+                opaque[idx] = true;
+            }
+        }
+
+        let mut reached = vec![false; open.len()];
+        let Some(start) = self.index(spawn).filter(|&idx| open[idx]) else {
+            return reached;
+        };
+        reached[start] = true;
+
+        // steps can't squeeze past corners, if the map disallows it
+        let clear = |pt: Point| self.index(pt).is_some_and(|idx| open[idx] && !opaque[idx]);
+        let no_cutting = self.neighborhood == Neighborhood::DiagonalNoCornerCutting;
+
+        let mut queue = VecDeque::from([spawn]);
+        while let Some(pt) = queue.pop_front() {
+            for &delta in self.neighborhood.deltas() {
+                let next = pt + delta;
+                let Some(idx) = self.index(next) else {
+                    continue;
+                };
+                if reached[idx] || !open[idx] {
+                    continue;
+                }
+                let diagonal = delta.x != 0 && delta.y != 0;
+                if diagonal
+                    && no_cutting
+                    && !(clear(pt + Point::new(delta.x, 0)) && clear(pt + Point::new(0, delta.y)))
+                {
+                    continue;
+                }
+
+                reached[idx] = true;
+                queue.push_back(next);
+            }
+        }
+
+        reached
+    }
+
+    /// Whether a lock leads somewhere that can't be reached without going
+    /// through it
+    fn gates(&self, reached: &[bool], door: Point, locks: &[(Point, bool)]) -> bool {
+        [(0, -1), (0, 1), (-1, 0), (1, 0)]
+            .into_iter()
+            .map(|(dx, dy)| door + Point::new(dx, dy))
+            .filter(|pt| locks.iter().all(|(at, _)| at != pt))
+            .filter_map(|pt| self.index(pt))
+            .any(|idx| self.walkable[idx] && !reached[idx])
+    }
+}
+
+/// Locks the doors at some points of a level, in order, and drops the ones
+/// that can be walked around with the keys to the locks before them.
+/// Returns the locks that are kept, in order.
+fn prune_locks(passage: &Passage, spawn: Point, candidates: Vec<Point>) -> Vec<Point> {
+    // a lock that can be walked around with the keys before it gates nothing.
+    // Whether it can only depends on the locks after it, so go backwards.
+    let mut kept = vec![true; candidates.len()];
+    for i in (0..candidates.len()).rev() {
+        let locks: Vec<(Point, bool)> = candidates
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| kept[j])
+            .map(|(j, &at)| (at, j < i))
+            .collect();
+
+        let reached = passage.reached(spawn, &locks);
+        kept[i] = passage.gates(&reached, candidates[i], &locks);
+    }
+
+    candidates
+        .into_iter()
+        .zip(kept)
+        .filter_map(|(at, kept)| kept.then_some(at))
+        .collect()
+}
+
+/// Places locked doors along the critical path of a level, and the keys to
+/// them in rooms that can be reached before each lock.
+pub fn place_locks<R: Rng>(
+    level: &mut GeneratedLevel,
+    settings: &LockSettings,
+    rng: &mut R,
+) -> LockPuzzle {
+    let mut puzzle = LockPuzzle {
+        locks: vec![],
+        free_keys: level
+            .map()
+            .iter()
+            .filter(|tile| tile.door_state() == Some(DoorState::Locked))
+            .flat_map(|tile| tile.access().iter().cloned())
+            .collect(),
+        solution: vec![],
+    };
+
+    let (Some(spawn), Some(critical)) = (level.spawn(), level.room_graph().critical_path()) else {
+        return puzzle;
+    };
+
+    // doors can only go at the connections between rooms on the critical path
+    let mut candidates: Vec<Point> = critical
+        .windows(2)
+        .filter_map(|pair| level.room_graph().edge_between(pair[0], pair[1]))
+        .map(|edge| edge.at())
+        .filter(|at| level.connections().contains(at))
+        .collect();
+
+    // in the order they are met on the critical path
+    let order: Vec<Point> = critical
+        .windows(2)
+        .filter_map(|pair| level.room_graph().edge_between(pair[0], pair[1]))
+        .map(|edge| edge.at())
+        .collect();
+    candidates.sort_by_key(|at| order.iter().position(|other| other == at));
+
+    let passage = Passage::new(level.map(), &puzzle);
+
+    // locks that can be walked around go first, so that they don't take up
+    // the places of locks that gate something
+    let mut doors = prune_locks(&passage, spawn, candidates);
+    if doors.len() > settings.locks {
+        let mut pool = doors;
+        pool.shuffle(rng);
+
+        // opening a lock can open a way around the others, so locks are
+        // added back one at a time, and only kept if they all still gate
+        doors = vec![];
+        for at in pool {
+            if doors.len() == settings.locks {
+                break;
+            }
+
+            let mut trial = doors.clone();
+            trial.push(at);
+            trial.sort_by_key(|at| order.iter().position(|other| other == at));
+
+            let kept = prune_locks(&passage, spawn, trial.clone());
+            if kept.len() == trial.len() {
+                doors = kept;
+            }
+        }
+    }
+
+    for i in 0..doors.len() {
+        let locks: Vec<(Point, bool)> = doors
+            .iter()
+            .enumerate()
+            .map(|(j, &at)| (at, j < i))
+            .collect();
+        let reached = passage.reached(spawn, &locks);
+
+        // rooms that can be reached before this lock, and don't have a key
+        let reachable: Vec<usize> = (0..level.rooms().len())
+            .filter(|&room| {
+                let centre = level.rooms()[room].centre();
+                passage
+                    .index(centre)
+                    .is_some_and(|idx| passage.walkable[idx] && reached[idx])
+                    && puzzle.locks.iter().all(|lock| lock.key_room != room)
+            })
+            .collect();
+
+        // side branches first, then anywhere but the start
+        let off_path: Vec<usize> = reachable
+            .iter()
+            .copied()
+            .filter(|room| !critical.contains(room))
+            .collect();
+        let elsewhere: Vec<usize> = reachable
+            .iter()
+            .copied()
+            .filter(|&room| Some(room) != level.room_graph().start())
+            .collect();
+
+        let choices = [off_path, elsewhere, reachable]
+            .into_iter()
+            .find(|rooms| !rooms.is_empty());
+        let Some(choices) = choices else {
+            // no room for the key, so this lock and the ones after can't stay
+            doors.truncate(i);
+            break;
+        };
+        let key_room = choices[rng.random_range(0..choices.len())];
+
+        puzzle.locks.push(Lock {
+            key: key_name(i),
+            door: doors[i],
+            key_at: level.rooms()[key_room].centre(),
+            key_room,
+        });
+        puzzle.solution.push(PuzzleStep::CollectKey(i));
+        puzzle.solution.push(PuzzleStep::Unlock(i));
+    }
+
+    // only the kept locks change the map
+    for (i, &at) in doors.iter().enumerate() {
+        level
+            .map_mut()
+            .set_tile_at(at, Tile::locked_door(key_name(i)));
+    }
+
+    puzzle
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn puzzles_are_solvable() {
+        let mut locked = 0;
+
+        for seed in 0..2 {
+            let mut mapgen = MapGenerator2D::new(60, 40)
+                .with_seed(seed)
+                .with_locks(LockSettings::new().with_locks(3));
+
+            for method in [FloorGenAlg::Basic, FloorGenAlg::Bsp(BspSettings::new())] {
+                let level = mapgen.generate(method).unwrap();
+                let puzzle = level.lock_puzzle().unwrap();
+
+                assert!(puzzle.is_solvable(&level));
+                assert!(puzzle.locks().len() <= 3);
+                locked += puzzle.locks().len();
+            }
+        }
+
+        assert!(locked > 0);
+    }
+
+    #[test]
+    fn locks_gate_the_exit() {
+        let mut mapgen = MapGenerator2D::new(80, 50)
+            .with_seed(2)
+            .with_locks(LockSettings::new().with_locks(2));
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+        let puzzle = level.lock_puzzle().unwrap();
+        assert!(!puzzle.locks().is_empty());
+
+        let map = level.map();
+        let regions = map.connected_components([MoveType::Walk, MoveType::opener()]);
+        let (spawn, exit) = (level.spawn().unwrap(), level.exit().unwrap());
+        assert_ne!(regions.region_of(spawn), regions.region_of(exit));
+
+        // the first key can be reached without any keys
+        let first = puzzle.locks()[0].key_at();
+        assert_eq!(regions.region_of(spawn), regions.region_of(first));
+    }
+
+    #[test]
+    fn bypassed_locks_leave_room_for_others() {
+        let locks = |count: usize, seed: u64| {
+            MapGenerator2D::new(60, 40)
+                .with_locks(LockSettings::new().with_locks(count))
+                .generate_seeded(FloorGenAlg::Basic, seed)
+                .unwrap()
+                .lock_puzzle()
+                .unwrap()
+                .locks()
+                .len()
+        };
+
+        // a single lock is placed whenever any can be
+        for seed in 0..3 {
+            assert_eq!(locks(1, seed), locks(10, seed).min(1));
+        }
+    }
+
+    #[test]
+    fn locks_without_doors_add_no_doors() {
+        for seed in 0..3 {
+            let mut mapgen = MapGenerator2D::new(60, 40)
+                .without_doors()
+                .with_locks(LockSettings::new().with_locks(6));
+            let level = mapgen
+                .generate_seeded(FloorGenAlg::Bsp(BspSettings::new()), seed)
+                .unwrap();
+            let puzzle = level.lock_puzzle().unwrap();
+
+            // the only doors are the locks that were kept
+            let doors = level
+                .map()
+                .iter()
+                .filter(|tile| tile.door_state().is_some())
+                .count();
+            assert_eq!(doors, puzzle.locks().len());
+            assert!(puzzle.is_solvable(&level));
+        }
+    }
+
+    #[test]
+    fn keys_are_named_by_colour() {
+        assert_eq!(super::key_name(0), MoveType::custom("key_red"));
+        assert_eq!(super::key_name(7), MoveType::custom("key_7"));
+    }
+}
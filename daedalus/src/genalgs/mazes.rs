@@ -0,0 +1,760 @@
+//! Perfect maze generation.
+//!
+//! The map is split into a grid of cells with walls between them, and a
+//! spanning tree of the grid is carved out, so that there is exactly one way
+//! between any two cells. Braiding can then knock out the walls at some dead
+//! ends, adding loops.
+
+use std::collections::VecDeque;
+
+use bracket_geometry::prelude::*;
+use labyrinth_map::prelude::*;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::map_generators::MapGenerator2D;
+
+/// The algorithm that picks the spanning tree of a maze. They all make
+/// perfect mazes, but with a different feel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MazeAlgorithm {
+    /// Depth first search: long, winding corridors with few branches
+    RecursiveBacktracker,
+
+    /// Randomized Prim's: many short dead ends, branching out from the start
+    Prim,
+
+    /// Randomized Kruskal's: many short dead ends, spread evenly
+    Kruskal,
+
+    /// Eller's, one row at a time: long horizontal runs
+    Eller,
+
+    /// Wilson's loop-erased random walks: an unbiased pick of every possible
+    /// maze
+    Wilson,
+}
+
+/// Settings for the [`FloorGenAlg::Maze`](crate::prelude::FloorGenAlg::Maze)
+/// algorithm.
+///
+/// Every cell of the maze is a square of `cell_size` floor tiles, and cells
+/// are separated by walls `wall_thickness` tiles thick. Cells that don't fit
+/// on the map are left out, so the right and bottom edges might be thicker.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let settings = MazeSettings::new()
+///     .with_algorithm(MazeAlgorithm::Wilson)
+///     .with_cell_size(2)
+///     .with_wall_thickness(1)
+///     .with_braid(0.25);
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50);
+/// let level = mapgen.generate(FloorGenAlg::Maze(settings)).unwrap();
+///
+/// // the spawn and exit are the ends of the longest path through the maze
+/// let maze = level.maze().unwrap();
+/// let path = maze.longest_path();
+/// assert_eq!(level.spawn(), Some(maze.cell_point(path[0])));
+/// assert_eq!(level.exit(), Some(maze.cell_point(*path.last().unwrap())));
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeSettings {
+    algorithm: MazeAlgorithm,
+    cell_size: i32,
+    wall_thickness: i32,
+    braid: f64,
+}
+
+impl Default for MazeSettings {
+    fn default() -> Self {
+        MazeSettings {
+            algorithm: MazeAlgorithm::RecursiveBacktracker,
+            cell_size: 1,
+            wall_thickness: 1,
+            braid: 0.0,
+        }
+    }
+}
+
+impl MazeSettings {
+    /// Creates a new MazeSettings struct with the default settings
+    pub fn new() -> MazeSettings {
+        Default::default()
+    }
+
+    /// Sets the algorithm that carves the maze
+    pub fn with_algorithm(mut self, algorithm: MazeAlgorithm) -> MazeSettings {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Sets the width and height of every cell, in tiles
+    pub fn with_cell_size(mut self, cell_size: i32) -> MazeSettings {
+        self.cell_size = cell_size.max(1);
+        self
+    }
+
+    /// Sets the thickness of the walls between cells, in tiles
+    pub fn with_wall_thickness(mut self, wall_thickness: i32) -> MazeSettings {
+        self.wall_thickness = wall_thickness.max(1);
+        self
+    }
+
+    /// Sets the chance of each dead end being joined to another cell, from 0
+    /// (a perfect maze) to 1 (no dead ends)
+    pub fn with_braid(mut self, braid: f64) -> MazeSettings {
+        self.braid = braid.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Getter for the algorithm
+    pub fn algorithm(&self) -> MazeAlgorithm {
+        self.algorithm
+    }
+
+    /// Getter for the cell size
+    pub fn cell_size(&self) -> i32 {
+        self.cell_size
+    }
+
+    /// Getter for the wall thickness
+    pub fn wall_thickness(&self) -> i32 {
+        self.wall_thickness
+    }
+
+    /// Getter for the braid chance
+    pub fn braid(&self) -> f64 {
+        self.braid
+    }
+}
+
+/// The cells of a maze and the passages carved between them.
+///
+/// Cells are numbered row by row. The passages of the spanning tree are kept
+/// apart from the ones added by braiding, so paths through the tree are
+/// always unique.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeTree {
+    columns: i32,
+    rows: i32,
+    cell_size: i32,
+    wall_thickness: i32,
+    edges: Vec<(usize, usize)>,
+    braids: Vec<(usize, usize)>,
+}
+
+impl MazeTree {
+    /// Number of cells across the maze
+    pub fn columns(&self) -> i32 {
+        self.columns
+    }
+
+    /// Number of cells down the maze
+    pub fn rows(&self) -> i32 {
+        self.rows
+    }
+
+    /// Number of cells in the maze
+    pub fn cell_count(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Getter for the passages of the spanning tree, lowest cell first
+    pub fn edges(&self) -> &Vec<(usize, usize)> {
+        &self.edges
+    }
+
+    /// Getter for the passages added by braiding, lowest cell first
+    pub fn braids(&self) -> &Vec<(usize, usize)> {
+        &self.braids
+    }
+
+    /// The top left tile of a cell on the map
+    fn cell_origin(&self, cell: usize) -> Point {
+        let (x, y) = (cell as i32 % self.columns, cell as i32 / self.columns);
+        let step = self.cell_size + self.wall_thickness;
+        Point::new(
+            self.wall_thickness + x * step,
+            self.wall_thickness + y * step,
+        )
+    }
+
+    /// The tile in the middle of a cell on the map
+    pub fn cell_point(&self, cell: usize) -> Point {
+        let offset = (self.cell_size - 1) / 2;
+        self.cell_origin(cell) + Point::new(offset, offset)
+    }
+
+    /// The cell a point of the map is in, if it is in one
+    pub fn cell_of(&self, pt: Point) -> Option<usize> {
+        let step = self.cell_size + self.wall_thickness;
+        let (x, y) = (pt.x - self.wall_thickness, pt.y - self.wall_thickness);
+        if x < 0 || y < 0 || x % step >= self.cell_size || y % step >= self.cell_size {
+            return None;
+        }
+
+        let (column, row) = (x / step, y / step);
+        (column < self.columns && row < self.rows).then(|| (row * self.columns + column) as usize)
+    }
+
+    /// The cells joined to a cell by a passage, including braided ones, in
+    /// order
+    pub fn neighbors(&self, cell: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self
+            .edges
+            .iter()
+            .chain(self.braids.iter())
+            .filter_map(|&edge| match edge {
+                (a, b) if a == cell => Some(b),
+                (a, b) if b == cell => Some(a),
+                _ => None,
+            })
+            .collect();
+        neighbors.sort_unstable();
+        neighbors
+    }
+
+    /// Cells with only one way in or out
+    pub fn dead_ends(&self) -> Vec<usize> {
+        self.adjacency(true)
+            .iter()
+            .enumerate()
+            .filter(|(_, joined)| joined.len() == 1)
+            .map(|(cell, _)| cell)
+            .collect()
+    }
+
+    /// The cells joined to every cell by the spanning tree and, if asked,
+    /// by braiding
+    fn adjacency(&self, with_braids: bool) -> Vec<Vec<usize>> {
+        let braids = if with_braids { &self.braids[..] } else { &[] };
+
+        let mut adjacent = vec![vec![]; self.cell_count()];
+        for &(a, b) in self.edges.iter().chain(braids.iter()) {
+            adjacent[a].push(b);
+            adjacent[b].push(a);
+        }
+        adjacent
+    }
+
+    /// The cells on the only way through the spanning tree from one cell to
+    /// another, both included
+    pub fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let (_, previous) = self.search(from);
+        if to >= self.cell_count() || (to != from && previous[to].is_none()) {
+            return None;
+        }
+
+        let mut path = vec![to];
+        while let Some(cell) = previous[*path.last().unwrap()] {
+            path.push(cell);
+        }
+        path.reverse();
+
+        Some(path)
+    }
+
+    /// The longest path through the spanning tree, between the two cells
+    /// furthest apart. Its ends are good places for a start and an exit.
+    pub fn longest_path(&self) -> Vec<usize> {
+        if self.cell_count() == 0 {
+            return vec![];
+        }
+
+        let (depths, _) = self.search(0);
+        let a = furthest(&depths);
+        let (depths, _) = self.search(a);
+        let b = furthest(&depths);
+
+        self.path(a, b).unwrap_or_default()
+    }
+
+    /// Breadth first search through the spanning tree, returning the depth of
+    /// every cell and the cell it was reached from
+    fn search(&self, from: usize) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let n = self.cell_count();
+        let mut depths = vec![None; n];
+        let mut previous = vec![None; n];
+        if from >= n {
+            return (depths, previous);
+        }
+
+        let adjacent = self.adjacency(false);
+
+        depths[from] = Some(0);
+        let mut queue = VecDeque::from([from]);
+        while let Some(cell) = queue.pop_front() {
+            let depth = depths[cell].unwrap_or(0);
+            for &next in adjacent[cell].iter() {
+                if depths[next].is_none() {
+                    depths[next] = Some(depth + 1);
+                    previous[next] = Some(cell);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        (depths, previous)
+    }
+
+    /// Digs out the cells and passages on a map
+    fn carve(&self, map: &mut Labyrinth2D) {
+        for cell in 0..self.cell_count() {
+            let origin = self.cell_origin(cell);
+            fill(map, origin, self.cell_size, self.cell_size);
+        }
+
+        for &(a, b) in self.edges.iter().chain(self.braids.iter()) {
+            let origin = self.cell_origin(a);
+            // in a single column, the cell below is also the next one
+            if b == a + self.columns as usize {
+                let gap = origin + Point::new(0, self.cell_size);
+                fill(map, gap, self.cell_size, self.wall_thickness);
+            } else {
+                let gap = origin + Point::new(self.cell_size, 0);
+                fill(map, gap, self.wall_thickness, self.cell_size);
+            }
+        }
+    }
+}
+
+/// The cell with the greatest depth, the lowest one on ties
+fn furthest(depths: &[Option<usize>]) -> usize {
+    depths
+        .iter()
+        .enumerate()
+        .filter_map(|(cell, depth)| depth.map(|depth| (cell, depth)))
+        .fold(
+            (0, 0),
+            |best, (cell, depth)| {
+                if depth > best.1 {
+                    (cell, depth)
+                } else {
+                    best
+                }
+            },
+        )
+        .0
+}
+
+/// Sets a rectangle of tiles to floor
+fn fill(map: &mut Labyrinth2D, origin: Point, width: i32, height: i32) {
+    for y in 0..height {
+        for x in 0..width {
+            map.set_tile_at(origin + Point::new(x, y), Tile::floor());
+        }
+    }
+}
+
+/// The grid the spanning tree is picked over
+struct MazeGrid {
+    columns: usize,
+    rows: usize,
+}
+
+impl MazeGrid {
+    fn count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn cell(&self, x: usize, y: usize) -> usize {
+        y * self.columns + x
+    }
+
+    /// Cells next to a cell, in order
+    fn neighbors(&self, cell: usize) -> Vec<usize> {
+        let (x, y) = (cell % self.columns, cell / self.columns);
+        let mut neighbors = vec![];
+        if y > 0 {
+            neighbors.push(cell - self.columns);
+        }
+        if x > 0 {
+            neighbors.push(cell - 1);
+        }
+        if x + 1 < self.columns {
+            neighbors.push(cell + 1);
+        }
+        if y + 1 < self.rows {
+            neighbors.push(cell + self.columns);
+        }
+        neighbors
+    }
+
+    /// Every pair of cells next to each other, lowest cell first
+    fn all_edges(&self) -> Vec<(usize, usize)> {
+        (0..self.count())
+            .flat_map(|a| {
+                self.neighbors(a)
+                    .into_iter()
+                    .filter(move |&b| b > a)
+                    .map(move |b| (a, b))
+            })
+            .collect()
+    }
+
+    fn recursive_backtracker<R: Rng>(&self, rng: &mut R) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+        let mut visited = vec![false; self.count()];
+
+        let start = rng.random_range(0..self.count());
+        visited[start] = true;
+        let mut stack = vec![start];
+
+        while let Some(&cell) = stack.last() {
+            let unvisited: Vec<usize> = self
+                .neighbors(cell)
+                .into_iter()
+                .filter(|&next| !visited[next])
+                .collect();
+
+            if unvisited.is_empty() {
+                stack.pop();
+                continue;
+            }
+
+            let next = unvisited[rng.random_range(0..unvisited.len())];
+            visited[next] = true;
+            edges.push((cell, next));
+            stack.push(next);
+        }
+
+        edges
+    }
+
+    fn prim<R: Rng>(&self, rng: &mut R) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+        let mut visited = vec![false; self.count()];
+
+        let start = rng.random_range(0..self.count());
+        visited[start] = true;
+        let mut frontier: Vec<(usize, usize)> = self
+            .neighbors(start)
+            .into_iter()
+            .map(|next| (start, next))
+            .collect();
+
+        while !frontier.is_empty() {
+            let (cell, next) = frontier.swap_remove(rng.random_range(0..frontier.len()));
+            if visited[next] {
+                continue;
+            }
+
+            visited[next] = true;
+            edges.push((cell, next));
+            frontier.extend(
+                self.neighbors(next)
+                    .into_iter()
+                    .filter(|&other| !visited[other])
+                    .map(|other| (next, other)),
+            );
+        }
+
+        edges
+    }
+
+    fn kruskal<R: Rng>(&self, rng: &mut R) -> Vec<(usize, usize)> {
+        let mut candidates = self.all_edges();
+        candidates.shuffle(rng);
+
+        let mut sets = DisjointSets::new(self.count());
+        candidates
+            .into_iter()
+            .filter(|&(a, b)| sets.join(a, b))
+            .collect()
+    }
+
+    fn eller<R: Rng>(&self, rng: &mut R) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+        let mut sets: Vec<usize> = (0..self.columns).collect();
+        let mut next_set = self.columns;
+
+        for y in 0..self.rows {
+            let last_row = y + 1 == self.rows;
+
+            // join neighbours in the row, all of them on the last row
+            for x in 0..self.columns - 1 {
+                if sets[x] != sets[x + 1] && (last_row || rng.random_bool(0.5)) {
+                    edges.push((self.cell(x, y), self.cell(x + 1, y)));
+                    let (from, to) = (sets[x + 1], sets[x]);
+                    for set in sets.iter_mut().filter(|set| **set == from) {
+                        *set = to;
+                    }
+                }
+            }
+
+            if last_row {
+                break;
+            }
+
+            // every set goes down at least once
+            let mut below: Vec<Option<usize>> = vec![None; self.columns];
+            let mut seen: Vec<usize> = vec![];
+            for &set in sets.iter() {
+                if seen.contains(&set) {
+                    continue;
+                }
+                seen.push(set);
+
+                let mut members: Vec<usize> =
+                    (0..self.columns).filter(|&x| sets[x] == set).collect();
+                members.shuffle(rng);
+                let down = rng.random_range(1..=members.len());
+                for &x in members[..down].iter() {
+                    edges.push((self.cell(x, y), self.cell(x, y + 1)));
+                    below[x] = Some(set);
+                }
+            }
+
+            sets = below
+                .into_iter()
+                .map(|set| {
+                    set.unwrap_or_else(|| {
+                        next_set += 1;
+                        next_set
+                    })
+                })
+                .collect();
+        }
+
+        edges
+    }
+
+    fn wilson<R: Rng>(&self, rng: &mut R) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+        let mut in_tree = vec![false; self.count()];
+        let mut next = vec![0; self.count()];
+
+        in_tree[rng.random_range(0..self.count())] = true;
+
+        for start in 0..self.count() {
+            // walk until the tree is hit, remembering only the last way out
+            // of every cell, which erases the loops
+            let mut cell = start;
+            while !in_tree[cell] {
+                let neighbors = self.neighbors(cell);
+                next[cell] = neighbors[rng.random_range(0..neighbors.len())];
+                cell = next[cell];
+            }
+
+            let mut cell = start;
+            while !in_tree[cell] {
+                in_tree[cell] = true;
+                edges.push((cell, next[cell]));
+                cell = next[cell];
+            }
+        }
+
+        edges
+    }
+}
+
+/// Union-find over the cells, for Kruskal's algorithm
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(count: usize) -> DisjointSets {
+        DisjointSets {
+            parents: (0..count).collect(),
+        }
+    }
+
+    fn root(&mut self, mut cell: usize) -> usize {
+        while self.parents[cell] != cell {
+            self.parents[cell] = self.parents[self.parents[cell]];
+            cell = self.parents[cell];
+        }
+        cell
+    }
+
+    /// Joins the sets of two cells, returning false if they were already one
+    fn join(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.root(a), self.root(b));
+        self.parents[b] = a;
+        a != b
+    }
+}
+
+/// Carves a maze directly on the [`Labyrinth2D`] of the [`MapGenerator2D`],
+/// and stores its spanning tree in the Generator.
+pub fn build_maze<R: Rng>(mapgen: &mut MapGenerator2D, settings: &MazeSettings, rng: &mut R) {
+    let dims = *mapgen.dimensions();
+    let step = settings.cell_size + settings.wall_thickness;
+    let grid = MazeGrid {
+        columns: ((dims.x - settings.wall_thickness) / step).max(0) as usize,
+        rows: ((dims.y - settings.wall_thickness) / step).max(0) as usize,
+    };
+    if grid.count() == 0 {
+        return;
+    }
+
+    let edges = match settings.algorithm {
+        MazeAlgorithm::RecursiveBacktracker => grid.recursive_backtracker(rng),
+        MazeAlgorithm::Prim => grid.prim(rng),
+        MazeAlgorithm::Kruskal => grid.kruskal(rng),
+        MazeAlgorithm::Eller => grid.eller(rng),
+        MazeAlgorithm::Wilson => grid.wilson(rng),
+    };
+
+    let mut maze = MazeTree {
+        columns: grid.columns as i32,
+        rows: grid.rows as i32,
+        cell_size: settings.cell_size,
+        wall_thickness: settings.wall_thickness,
+        edges: edges
+            .into_iter()
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect(),
+        braids: vec![],
+    };
+    maze.edges.sort_unstable();
+
+    let mut joined = maze.adjacency(true);
+    for cell in maze.dead_ends() {
+        // an earlier braid might have joined this dead end already
+        if joined[cell].len() != 1 || !rng.random_bool(settings.braid) {
+            continue;
+        }
+
+        let others: Vec<usize> = grid
+            .neighbors(cell)
+            .into_iter()
+            .filter(|other| !joined[cell].contains(other))
+            .collect();
+        if !others.is_empty() {
+            let other = others[rng.random_range(0..others.len())];
+            maze.braids.push((cell.min(other), cell.max(other)));
+            joined[cell].push(other);
+            joined[other].push(cell);
+        }
+    }
+
+    maze.carve(mapgen.map_mut());
+    mapgen.set_maze(maze);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+
+    const ALGORITHMS: [MazeAlgorithm; 5] = [
+        MazeAlgorithm::RecursiveBacktracker,
+        MazeAlgorithm::Prim,
+        MazeAlgorithm::Kruskal,
+        MazeAlgorithm::Eller,
+        MazeAlgorithm::Wilson,
+    ];
+
+    #[test]
+    fn every_algorithm_makes_a_spanning_tree() {
+        for algorithm in ALGORITHMS {
+            let settings = MazeSettings::new().with_algorithm(algorithm);
+            let mut mapgen = MapGenerator2D::new(41, 31).with_seed(6);
+            let level = mapgen.generate(FloorGenAlg::Maze(settings)).unwrap();
+            let maze = level.maze().unwrap();
+
+            assert_eq!((maze.columns(), maze.rows()), (20, 15));
+            assert_eq!(maze.edges().len(), maze.cell_count() - 1);
+
+            // n - 1 edges that reach every cell make a tree
+            let mut sets = DisjointSets::new(maze.cell_count());
+            for &(a, b) in maze.edges() {
+                assert!(sets.join(a, b), "{:?} made a loop", algorithm);
+            }
+            assert!(level.map().is_fully_connected([MoveType::Walk]));
+        }
+    }
+
+    #[test]
+    fn cells_and_walls_are_sized() {
+        let settings = MazeSettings::new().with_cell_size(3).with_wall_thickness(2);
+        let mut mapgen = MapGenerator2D::new(40, 30)
+            .with_seed(1)
+            .without_connectivity_repair();
+        let level = mapgen.generate(FloorGenAlg::Maze(settings)).unwrap();
+        let maze = level.maze().unwrap();
+        let map = level.map();
+
+        assert_eq!((maze.columns(), maze.rows()), (7, 5));
+        assert_eq!(maze.cell_point(0), Point::new(3, 3));
+        assert_eq!(maze.cell_of(Point::new(8, 2)), Some(1));
+        assert_eq!(maze.cell_of(Point::new(5, 2)), None);
+
+        // the corners between cells are never dug
+        for y in 0..maze.rows() {
+            for x in 0..maze.columns() {
+                let corner = Point::new(5 * x, 5 * y);
+                assert_eq!(map.tile_kind(corner), "wall");
+            }
+        }
+    }
+
+    #[test]
+    fn single_column_and_row_mazes_are_connected() {
+        for (width, height) in [(3, 21), (21, 3)] {
+            for algorithm in ALGORITHMS {
+                let settings = MazeSettings::new().with_algorithm(algorithm);
+                let mut mapgen = MapGenerator2D::new(width, height)
+                    .with_seed(3)
+                    .without_connectivity_repair();
+                let level = mapgen.generate(FloorGenAlg::Maze(settings)).unwrap();
+                let map = level.map();
+
+                assert_eq!(level.maze().unwrap().cell_count(), 10);
+                assert!(map.is_fully_connected([MoveType::Walk]));
+
+                // the edges of the map are never dug
+                let (w, h) = (width as i32, height as i32);
+                for pt in (0..w)
+                    .flat_map(|x| [Point::new(x, 0), Point::new(x, h - 1)])
+                    .chain((0..h).flat_map(|y| [Point::new(0, y), Point::new(w - 1, y)]))
+                {
+                    assert_eq!(map.tile_kind(pt), "wall", "{:?} at {:?}", algorithm, pt);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn braiding_removes_dead_ends() {
+        let mut mapgen = MapGenerator2D::new(41, 31).with_seed(2);
+
+        let perfect = mapgen
+            .generate(FloorGenAlg::Maze(MazeSettings::new()))
+            .unwrap();
+        let braided = mapgen
+            .generate_seeded(FloorGenAlg::Maze(MazeSettings::new().with_braid(1.0)), 2)
+            .unwrap();
+
+        let perfect = perfect.maze().unwrap();
+        let braided = braided.maze().unwrap();
+        assert!(perfect.braids().is_empty());
+        assert!(!braided.braids().is_empty());
+        assert!(braided.dead_ends().len() < perfect.dead_ends().len());
+    }
+
+    #[test]
+    fn longest_path_ends_are_spawn_and_exit() {
+        let mut mapgen = MapGenerator2D::new(41, 31).with_seed(4);
+        let level = mapgen
+            .generate(FloorGenAlg::Maze(MazeSettings::new()))
+            .unwrap();
+        let maze = level.maze().unwrap();
+        let path = maze.longest_path();
+
+        assert_eq!(level.spawn(), Some(maze.cell_point(path[0])));
+        assert_eq!(level.exit(), Some(maze.cell_point(*path.last().unwrap())));
+
+        // no two cells are further apart
+        let (depths, _) = maze.search(path[0]);
+        assert_eq!(depths.iter().flatten().max(), Some(&(path.len() - 1)));
+    }
+}
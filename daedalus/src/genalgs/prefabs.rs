@@ -0,0 +1,246 @@
+//! Stamping of hand-made [`Prefab`] set pieces onto generated maps.
+
+use bracket_pathfinding::prelude::*;
+use labyrinth_map::prelude::*;
+use rand::{Rng, RngExt};
+
+use crate::map_generators::MapGenerator2D;
+
+use super::rooms::*;
+use super::sorted_points;
+
+/// Attempts at finding a place for each prefab before it is left out
+const PLACEMENT_ATTEMPTS: usize = 50;
+
+const NEIGHBORS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Stamps prefabs onto the map of the [`MapGenerator2D`], each in a random
+/// orientation, with at least one of its entries opening onto the rest of
+/// the map. The other entries may open onto solid rock.
+///
+/// A placement is only used if it fits inside the map, doesn't overlap the
+/// rooms already placed, only digs into solid rock, and leaves the map with
+/// no more separate regions than before. Every placed prefab becomes a room
+/// of the Generator, joined to the rooms its entries open onto. Prefabs that
+/// can't be placed are skipped; the ones that were placed are returned.
+pub fn place_prefabs<R: Rng>(
+    mapgen: &mut MapGenerator2D,
+    prefabs: &[Prefab],
+    rng: &mut R,
+) -> Vec<Prefab> {
+    let mut placed = vec![];
+
+    for prefab in prefabs.iter() {
+        if prefab.entries().is_empty() {
+            continue;
+        }
+
+        if let Some(prefab) = fit_prefab(mapgen, prefab.clone(), rng) {
+            stamp_prefab(mapgen, &prefab);
+            placed.push(prefab);
+        }
+    }
+
+    placed
+}
+
+/// Whether a tile can be walked on
+fn walkable(map: &Labyrinth2D, pt: Point) -> bool {
+    map.in_bounds(pt) && map.can_enter(pt, &[MoveType::Walk])
+}
+
+/// Finds a place for a prefab, returning it moved there
+fn fit_prefab<R: Rng>(mapgen: &MapGenerator2D, prefab: Prefab, rng: &mut R) -> Option<Prefab> {
+    let map = mapgen.map();
+
+    // solid tiles next to floor, where an entry could open onto the map
+    let targets: Vec<Point> = (0..map.size())
+        .map(|idx| map.index_to_point2d(idx))
+        .filter(|&pt| !walkable(map, pt))
+        .filter(|&pt| {
+            NEIGHBORS
+                .iter()
+                .any(|&(dx, dy)| walkable(map, pt + Point::new(dx, dy)))
+        })
+        .collect();
+    if targets.is_empty() {
+        return None;
+    }
+
+    let regions = map.connected_components([MoveType::Walk]).count();
+
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let mut candidate = prefab.clone();
+        for _ in 0..rng.random_range(0..4) {
+            candidate.rotate_right();
+        }
+        if rng.random_bool(0.5) {
+            candidate.mirror();
+        }
+
+        let entries = sorted_points(candidate.entries());
+        let entry = entries[rng.random_range(0..entries.len())];
+        let target = targets[rng.random_range(0..targets.len())];
+        candidate.shift(target - entry);
+
+        if fits(mapgen, &candidate, regions) {
+            return Some(candidate);
+        }
+    }
+
+    None
+}
+
+/// Whether a prefab can go where it is
+fn fits(mapgen: &MapGenerator2D, prefab: &Prefab, regions: usize) -> bool {
+    let map = mapgen.map();
+
+    // stays inside the map, off its edges
+    let dims = map.dimensions();
+    let inside = |pt: &Point| pt.x > 0 && pt.y > 0 && pt.x < dims.x - 1 && pt.y < dims.y - 1;
+    if !prefab.all_points().iter().all(inside) {
+        return false;
+    }
+
+    if mapgen.rooms().count() > 0 && mapgen.rooms().collides_with(prefab) {
+        return false;
+    }
+
+    // doesn't dig over floor that is already there
+    if prefab.floor().iter().any(|&pt| walkable(map, pt)) {
+        return false;
+    }
+
+    // and at least one entry opens onto the map, without splitting it
+    let opens = prefab.entries().iter().any(|&entry| {
+        NEIGHBORS.iter().any(|&(dx, dy)| {
+            let pt = entry + Point::new(dx, dy);
+            walkable(map, pt) && !prefab.floor().contains(&pt)
+        })
+    });
+    if !opens {
+        return false;
+    }
+
+    let mut stamped = map.clone();
+    for (pt, tile) in prefab.tiles() {
+        stamped.set_tile_at(*pt, tile.clone());
+    }
+    stamped.connected_components([MoveType::Walk]).count() <= regions.max(1)
+}
+
+/// Sets the tiles of a placed prefab, and adds it to the rooms of the
+/// Generator, joined to the rooms its entries open onto
+fn stamp_prefab(mapgen: &mut MapGenerator2D, prefab: &Prefab) {
+    let index = mapgen.rooms().count();
+
+    for entry in sorted_points(prefab.entries()) {
+        let other = NEIGHBORS
+            .iter()
+            .map(|&(dx, dy)| entry + Point::new(dx, dy))
+            .filter(|pt| !prefab.floor().contains(pt))
+            .find_map(|pt| {
+                mapgen
+                    .rooms()
+                    .rooms()
+                    .iter()
+                    .position(|room| room.floor().contains(&pt))
+            });
+
+        if let Some(other) = other {
+            mapgen.rooms_mut().link(other, index, entry);
+            mapgen.rooms_mut().connections.insert(entry);
+        }
+    }
+
+    let map = mapgen.map_mut();
+    let zone = map.add_zone(format!("room {}", index));
+    for (pt, tile) in prefab.tiles() {
+        map.set_tile_at(*pt, tile.clone());
+    }
+    for pt in prefab.floor() {
+        map.set_zone_at(pt, Some(zone));
+    }
+
+    mapgen
+        .rooms_mut()
+        .rooms_mut()
+        .push(Box::new(prefab.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    fn prepare_vault() -> Prefab {
+        Prefab::from_ascii(
+            "vault",
+            "G = gold transparent Walk\n---\n?#*#?\n##.##\n#...#\n#.G.#\n#####",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn prefabs_become_rooms() {
+        let mut placements = 0;
+
+        for seed in 0..4 {
+            for method in [FloorGenAlg::Basic, FloorGenAlg::Bsp(BspSettings::new())] {
+                let mut mapgen = MapGenerator2D::new(80, 50)
+                    .with_seed(seed)
+                    .with_prefab(prepare_vault());
+                let level = mapgen.generate(method).unwrap();
+                let map = level.map();
+
+                // some maps have no room left for the vault
+                let placed = mapgen.placed_prefabs();
+                assert!(placed.len() <= 1);
+                let Some(vault) = placed.first() else {
+                    continue;
+                };
+                placements += 1;
+
+                for (pt, tile) in vault.tiles() {
+                    assert_eq!(map.tile_kind(*pt), tile.kind());
+                }
+                assert!(map.is_fully_connected([MoveType::Walk]));
+
+                // the vault is the last room, and joined to the others
+                let room = level.rooms().len() - 1;
+                assert!(!level.room_graph().neighbors(room).is_empty());
+                let inside = *vault.floor().iter().next().unwrap();
+                let zone = map.zone_at(inside).unwrap();
+                assert_eq!(
+                    map.zone_label(zone),
+                    Some(format!("room {}", room).as_str())
+                );
+            }
+        }
+
+        assert!(placements >= 6);
+    }
+
+    #[test]
+    fn prefabs_fit_in_caves() {
+        let mut mapgen = MapGenerator2D::new(60, 40)
+            .with_seed(2)
+            .with_prefab(prepare_vault())
+            .with_prefab(prepare_vault());
+        let level = mapgen
+            .generate(FloorGenAlg::Caves(CaveSettings::new()))
+            .unwrap();
+
+        assert!(!mapgen.placed_prefabs().is_empty());
+        assert!(level.map().is_fully_connected([MoveType::Walk]));
+        assert!(level.map().iter().any(|tile| tile.kind() == "gold"));
+    }
+
+    #[test]
+    fn prefabs_without_entries_are_skipped() {
+        let sealed = Prefab::from_ascii("sealed", "###\n#.#\n###").unwrap();
+        let mut mapgen = MapGenerator2D::new(60, 40).with_seed(1).with_prefab(sealed);
+        mapgen.generate(FloorGenAlg::Basic).unwrap();
+
+        assert!(mapgen.placed_prefabs().is_empty());
+    }
+}
@@ -7,6 +7,9 @@ use super::shapes;
 pub mod compound_room;
 pub use compound_room::*;
 
+pub mod prefab;
+pub use prefab::*;
+
 pub trait Room {
     fn floor(&self) -> HashSet<Point>;
     fn walls(&self) -> HashSet<Point>;
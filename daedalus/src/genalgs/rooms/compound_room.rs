@@ -5,6 +5,8 @@ use rand::Rng;
 pub struct CompoundRoom {
     rooms: Vec<Box<dyn Room>>,
     pub connections: HashSet<Point>,
+    // which rooms join, by index, and the point where they do
+    links: Vec<(usize, usize, Point)>,
 }
 
 impl CompoundRoom {
@@ -12,6 +14,7 @@ impl CompoundRoom {
         CompoundRoom {
             rooms: vec![],
             connections: HashSet::new(),
+            links: vec![],
         }
     }
 
@@ -19,6 +22,7 @@ impl CompoundRoom {
         CompoundRoom {
             rooms: vec![Box::new(room)],
             connections: HashSet::new(),
+            links: vec![],
         }
     }
 
@@ -32,6 +36,15 @@ impl CompoundRoom {
 
     pub fn attach_room<T: RoomCollisions + 'static>(&mut self, room: T, connection: Point) -> bool {
         if self.walls().contains(&connection) && self.connects_to(&room) {
+            // the connection is in the walls of the room it attaches to
+            if let Some(old) = self
+                .rooms
+                .iter()
+                .position(|other| other.walls().contains(&connection))
+            {
+                self.links.push((old, self.rooms.len(), connection));
+            }
+
             self.rooms.push(Box::new(room));
             self.connections.insert(connection);
             return true;
@@ -39,6 +52,18 @@ impl CompoundRoom {
         false
     }
 
+    /// Records that two rooms, by index, join at a point. Unlike
+    /// [`CompoundRoom::attach_room`], the point doesn't become a connection.
+    pub fn link(&mut self, a: usize, b: usize, at: Point) {
+        self.links.push((a, b, at));
+    }
+
+    /// Which rooms join, by index, and the point where they do, in the order
+    /// they were linked
+    pub fn links(&self) -> &Vec<(usize, usize, Point)> {
+        &self.links
+    }
+
     pub fn count(&self) -> usize {
         self.rooms.len()
     }
@@ -0,0 +1,276 @@
+//! Hand-made rooms, read from maps or plain text.
+
+use super::*;
+use bracket_pathfinding::prelude::Algorithm2D;
+use labyrinth_map::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Symbol for a cell of a prefab that keeps whatever tile was there before
+pub const PREFAB_DONT_CARE: char = '?';
+
+/// Symbol for an entry point of a prefab, which is dug out as floor
+pub const PREFAB_ENTRY: char = '*';
+
+/// A hand-made set piece, such as a shrine or a treasure vault, that can be
+/// stamped onto generated maps.
+///
+/// Prefabs are rooms like any other: they can be rotated, mirrored and
+/// shifted, and they are placed by attaching one of their entry points to
+/// the map. Cells marked as "don't care" are left as they were.
+///
+/// The floor of a prefab is every cell it sets that isn't a wall, and its
+/// walls are its wall tiles, its entries, and whatever else is around its
+/// floor.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// // `?` cells are left alone, and `*` cells are entry points
+/// let shrine = Prefab::from_ascii(
+///     "shrine",
+///     "???*???\n?##.##?\n?#...#?\n*..~..*\n?#####?",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(shrine.entries().len(), 3);
+/// assert_eq!(shrine.floor().len(), 9);
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50).with_prefab(shrine);
+/// assert!(mapgen.generate(FloorGenAlg::Basic).is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    name: String,
+    tiles: Vec<(Point, Tile)>,
+    entries: HashSet<Point>,
+}
+
+impl Prefab {
+    /// Creates a prefab from every tile of a small map
+    pub fn from_map<T: Into<String>>(name: T, map: &Labyrinth2D) -> Prefab {
+        Prefab {
+            name: name.into(),
+            tiles: map
+                .iter()
+                .enumerate()
+                .map(|(idx, tile)| (map.index_to_point2d(idx), tile.clone()))
+                .collect(),
+            entries: HashSet::new(),
+        }
+    }
+
+    /// Creates a prefab from a map in the plain text format of
+    /// [`Labyrinth2D::from_ascii`], where [`PREFAB_DONT_CARE`] and
+    /// [`PREFAB_ENTRY`] cells mark the cells to leave alone and the entry
+    /// points. Those two symbols can't be used for tiles.
+    pub fn from_ascii<T: Into<String>>(name: T, raw: &str) -> Result<Prefab, LabyrinthError> {
+        let lines: Vec<&str> = raw.lines().collect();
+        let grid_start = lines
+            .iter()
+            .position(|line| line.trim_end() == "---")
+            .map_or(0, |separator| separator + 1);
+
+        // the grid rows are counted from the first one that isn't blank
+        let mut dont_care = vec![];
+        let mut entries = HashSet::new();
+        let mut cleaned: Vec<String> = lines[..grid_start].iter().map(|l| l.to_string()).collect();
+        let mut row = None;
+
+        for line in lines[grid_start..].iter() {
+            if row.is_none() && !line.is_empty() {
+                row = Some(0);
+            }
+
+            let mut clean = String::with_capacity(line.len());
+            for (x, glyph) in line.chars().enumerate() {
+                let pt = Point::new(x, row.unwrap_or(0));
+                match glyph {
+                    PREFAB_DONT_CARE => {
+                        dont_care.push(pt);
+                        clean.push('.');
+                    }
+                    PREFAB_ENTRY => {
+                        entries.insert(pt);
+                        clean.push('.');
+                    }
+                    _ => clean.push(glyph),
+                }
+            }
+            cleaned.push(clean);
+
+            if let Some(row) = row.as_mut() {
+                *row += 1;
+            }
+        }
+
+        let map = Labyrinth2D::from_ascii(&cleaned.join("\n"))?;
+        let mut prefab = Prefab::from_map(name, &map);
+        prefab.tiles.retain(|(pt, _)| !dont_care.contains(pt));
+        for entry in entries {
+            prefab = prefab.with_entry(entry);
+        }
+
+        Ok(prefab)
+    }
+
+    /// Marks a cell as an entry point. Entries are dug out as floor when the
+    /// prefab is placed, and the prefab is always placed with an entry
+    /// opening onto the rest of the map.
+    pub fn with_entry(mut self, pt: Point) -> Prefab {
+        self.tiles.retain(|(other, _)| *other != pt);
+        self.tiles.push((pt, Tile::floor()));
+        self.entries.insert(pt);
+        self
+    }
+
+    /// Marks a cell as "don't care", so that placing the prefab leaves the
+    /// tile there alone
+    pub fn with_dont_care(mut self, pt: Point) -> Prefab {
+        self.tiles.retain(|(other, _)| *other != pt);
+        self.entries.remove(&pt);
+        self
+    }
+
+    /// Getter for the name of the prefab
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Getter for the cells the prefab sets, and their tiles
+    pub fn tiles(&self) -> &Vec<(Point, Tile)> {
+        &self.tiles
+    }
+
+    /// Getter for the entry points of the prefab
+    pub fn entries(&self) -> &HashSet<Point> {
+        &self.entries
+    }
+
+    fn transform(&mut self, f: impl Fn(Point) -> Point) {
+        for (pt, _) in self.tiles.iter_mut() {
+            *pt = f(*pt);
+        }
+        self.entries = self.entries.iter().map(|&pt| f(pt)).collect();
+    }
+}
+
+impl RoomCollisions for Prefab {}
+
+impl Room for Prefab {
+    fn floor(&self) -> HashSet<Point> {
+        self.tiles
+            .iter()
+            .filter(|(pt, tile)| tile.kind() != "wall" && !self.entries.contains(pt))
+            .map(|(pt, _)| *pt)
+            .collect()
+    }
+
+    fn walls(&self) -> HashSet<Point> {
+        let floor = self.floor();
+        let mut walls: HashSet<Point> = self
+            .tiles
+            .iter()
+            .filter(|(_, tile)| tile.kind() == "wall")
+            .map(|(pt, _)| *pt)
+            .collect();
+        walls.extend(self.entries.iter());
+
+        let neighbors = [
+            Point::new(1, 0),
+            Point::new(0, 1),
+            Point::new(-1, 0),
+            Point::new(0, -1),
+        ];
+        for &pt in floor.iter() {
+            for n in neighbors {
+                if !floor.contains(&(pt + n)) {
+                    walls.insert(pt + n);
+                }
+            }
+        }
+
+        walls
+    }
+
+    fn borders(&self) -> HashSet<Point> {
+        let floor = self.floor();
+        let mut borders = self.walls();
+
+        for &pt in floor.iter() {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if !floor.contains(&(pt + Point::new(dx, dy))) {
+                        borders.insert(pt + Point::new(dx, dy));
+                    }
+                }
+            }
+        }
+
+        borders
+    }
+
+    fn entries(&mut self) -> HashSet<Point> {
+        self.entries.clone()
+    }
+
+    fn mirror(&mut self) {
+        self.transform(|pt| Point::new(-pt.x, pt.y));
+    }
+
+    fn rotate_left(&mut self) {
+        self.transform(|pt| Point::new(pt.y, -pt.x));
+    }
+
+    fn rotate_right(&mut self) {
+        self.transform(|pt| Point::new(-pt.y, pt.x));
+    }
+
+    fn shift(&mut self, offset: Point) {
+        self.transform(|pt| pt + offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepare_prefab() -> Prefab {
+        Prefab::from_ascii("vault", "?#*#?\n##.##\n#...#\n#####").unwrap()
+    }
+
+    #[test]
+    fn ascii_marks_entries_and_dont_cares() {
+        let prefab = prepare_prefab();
+
+        assert_eq!(prefab.name(), "vault");
+        assert_eq!(prefab.tiles().len(), 18);
+        assert_eq!(prefab.entries(), &HashSet::from_iter([Point::new(2, 0)]));
+        assert_eq!(prefab.floor().len(), 4);
+        assert!(prefab.walls().contains(&Point::new(2, 0)));
+        assert!(!prefab.floor().contains(&Point::new(2, 0)));
+    }
+
+    #[test]
+    fn prefab_returns_after_four_rotations() {
+        let mut prefab = prepare_prefab();
+        for _ in 0..4 {
+            prefab.rotate_right();
+        }
+        assert_eq!(prefab.floor(), prepare_prefab().floor());
+
+        prefab.rotate_left();
+        prefab.rotate_right();
+        prefab.mirror();
+        prefab.mirror();
+        assert_eq!(prefab.entries(), prepare_prefab().entries());
+    }
+
+    #[test]
+    fn rotation_moves_entries() {
+        let mut prefab = prepare_prefab();
+        prefab.rotate_right();
+
+        assert_eq!(prefab.entries(), &HashSet::from_iter([Point::new(0, 2)]));
+    }
+}
@@ -0,0 +1,610 @@
+//! Wave Function Collapse generation, with the overlapping model.
+//!
+//! Every square pattern of tiles in a sample map is learned, along with how
+//! often it shows up. A new map is then filled in one spot at a time, always
+//! at the spot with the fewest patterns left that fit with its neighbors,
+//! until every spot of the new map looks like some part of the sample.
+
+use std::collections::{HashMap, VecDeque};
+
+use bracket_pathfinding::prelude::*;
+use labyrinth_map::prelude::*;
+use rand::{Rng, RngExt};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::BuilderError;
+use crate::map_generators::MapGenerator2D;
+
+/// Offsets to the neighbors of a spot, so that the opposite of direction `d`
+/// is `(d + 2) % 4`
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Settings for the [`FloorGenAlg::Wfc`](crate::prelude::FloorGenAlg::Wfc)
+/// algorithm.
+///
+/// The tiles of the generated map are copies of the tiles of the sample, so
+/// custom kinds, [`MoveType`] access and costs all carry over. Like every
+/// other algorithm, the result goes through the connectivity repair of the
+/// [`MapGenerator2D`] afterwards, unless it is turned off.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// // a sample could also be drawn in the editor, and read with Labyrinth2D::read_ron
+/// let rows = [
+///     "##########",
+///     "#....#...#",
+///     "#.~~.#...#",
+///     "#.~~.....#",
+///     "#....#...#",
+///     "##########",
+/// ];
+/// let raw = format!("~ = water transparent Swim\n---\n{}", rows.join("\n"));
+/// let sample = Labyrinth2D::from_ascii(&raw).unwrap();
+///
+/// let settings = WfcSettings::new(sample)
+///     .with_pattern_size(3)
+///     .with_rotations(true)
+///     .with_reflections(true)
+///     .with_backtracking(32);
+///
+/// let mut mapgen = MapGenerator2D::new(30, 20).with_seed(1);
+/// assert!(mapgen.generate(FloorGenAlg::Wfc(settings)).is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WfcSettings {
+    sample: Labyrinth2D,
+    pattern_size: usize,
+    rotations: bool,
+    reflections: bool,
+    backtracking: usize,
+    attempts: u32,
+}
+
+impl WfcSettings {
+    /// Creates a new WfcSettings struct that learns from a sample map, with
+    /// 3x3 patterns, every rotation and reflection, and some backtracking
+    pub fn new(sample: Labyrinth2D) -> WfcSettings {
+        WfcSettings {
+            sample,
+            pattern_size: 3,
+            rotations: true,
+            reflections: true,
+            backtracking: 32,
+            attempts: 10,
+        }
+    }
+
+    /// Sets the width and height of the patterns learned from the sample.
+    /// Bigger patterns copy more of the sample's structure, but need a bigger
+    /// sample to be varied.
+    pub fn with_pattern_size(mut self, pattern_size: usize) -> WfcSettings {
+        self.pattern_size = pattern_size.max(1);
+        self
+    }
+
+    /// Sets whether the patterns are also learned rotated by quarter turns
+    pub fn with_rotations(mut self, rotations: bool) -> WfcSettings {
+        self.rotations = rotations;
+        self
+    }
+
+    /// Sets whether the patterns are also learned mirrored
+    pub fn with_reflections(mut self, reflections: bool) -> WfcSettings {
+        self.reflections = reflections;
+        self
+    }
+
+    /// Sets how many of the latest choices can be undone when the map runs
+    /// into a spot that no pattern fits. With no backtracking, the map is
+    /// started over instead.
+    pub fn with_backtracking(mut self, backtracking: usize) -> WfcSettings {
+        self.backtracking = backtracking;
+        self
+    }
+
+    /// Sets how many times the map is started over before giving up
+    pub fn with_attempts(mut self, attempts: u32) -> WfcSettings {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Getter for the sample map
+    pub fn sample(&self) -> &Labyrinth2D {
+        &self.sample
+    }
+
+    /// Getter for the pattern size
+    pub fn pattern_size(&self) -> usize {
+        self.pattern_size
+    }
+
+    /// Getter for whether rotated patterns are learned
+    pub fn rotations(&self) -> bool {
+        self.rotations
+    }
+
+    /// Getter for whether mirrored patterns are learned
+    pub fn reflections(&self) -> bool {
+        self.reflections
+    }
+
+    /// Getter for how many choices can be undone
+    pub fn backtracking(&self) -> usize {
+        self.backtracking
+    }
+
+    /// Getter for how many times the map is started over
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/// The patterns of a sample, and which of them can overlap
+struct Model {
+    // every different tile of the sample
+    palette: Vec<Tile>,
+    size: usize,
+    // palette indices of every pattern, row by row
+    patterns: Vec<Vec<usize>>,
+    weights: Vec<f64>,
+    // for every direction and pattern, the set of patterns that can be next to it
+    propagator: Vec<Vec<Vec<u64>>>,
+    words: usize,
+}
+
+impl Model {
+    /// Learns the patterns of the sample in the settings
+    fn learn(settings: &WfcSettings) -> Result<Model, BuilderError> {
+        let sample = &settings.sample;
+        let dims = sample.dimensions();
+        let size = settings.pattern_size;
+
+        if dims.x < size as i32 || dims.y < size as i32 {
+            return Err(BuilderError::BuildError(format!(
+                "The WFC sample ({}x{}) is smaller than its {}x{} patterns",
+                dims.x, dims.y, size, size
+            )));
+        }
+
+        // Tiles can't be hashed, but samples only have a few different ones
+        let mut palette: Vec<Tile> = vec![];
+        let grid: Vec<usize> = sample
+            .iter()
+            .map(
+                |tile| match palette.iter().position(|known| known == tile) {
+                    Some(i) => i,
+                    None => {
+                        palette.push(tile.clone());
+                        palette.len() - 1
+                    }
+                },
+            )
+            .collect();
+
+        let mut patterns: Vec<Vec<usize>> = vec![];
+        let mut weights: Vec<f64> = vec![];
+        let mut seen: HashMap<Vec<usize>, usize> = HashMap::new();
+
+        for y in 0..=(dims.y as usize - size) {
+            for x in 0..=(dims.x as usize - size) {
+                let pattern: Vec<usize> = (0..size * size)
+                    .map(|i| grid[(y + i / size) * dims.x as usize + x + i % size])
+                    .collect();
+
+                for variant in variants(pattern, size, settings) {
+                    match seen.get(&variant) {
+                        Some(&i) => weights[i] += 1.0,
+                        None => {
+                            seen.insert(variant.clone(), patterns.len());
+                            patterns.push(variant);
+                            weights.push(1.0);
+                        }
+                    }
+                }
+            }
+        }
+
+        let words = patterns.len().div_ceil(64);
+        let propagator = DIRECTIONS
+            .iter()
+            .map(|&(dx, dy)| {
+                patterns
+                    .iter()
+                    .map(|p| {
+                        let mut fits = vec![0; words];
+                        for (i, q) in patterns.iter().enumerate() {
+                            if overlaps(p, q, size, dx, dy) {
+                                fits[i / 64] |= 1 << (i % 64);
+                            }
+                        }
+                        fits
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Model {
+            palette,
+            size,
+            patterns,
+            weights,
+            propagator,
+            words,
+        })
+    }
+
+    /// Tries to fill a map of a given size once, returning the palette index
+    /// of every tile, or `None` if it ran into a spot nothing fits
+    fn run<R: Rng>(
+        &self,
+        width: usize,
+        height: usize,
+        backtracking: usize,
+        rng: &mut R,
+    ) -> Option<Vec<usize>> {
+        let mut wave = Wave {
+            model: self,
+            columns: width + 1 - self.size,
+            rows: height + 1 - self.size,
+            bits: vec![],
+        };
+        let count = wave.columns * wave.rows;
+
+        let mut all = vec![u64::MAX; self.words];
+        if !self.patterns.len().is_multiple_of(64) {
+            all[self.words - 1] = (1 << (self.patterns.len() % 64)) - 1;
+        }
+        wave.bits = all.repeat(count);
+
+        // patterns that can't have neighbors on some side only fit on the edges
+        if !wave.propagate((0..count).collect()) {
+            return None;
+        }
+
+        // the state before each of the latest choices, and the choice
+        let mut history: VecDeque<(Vec<u64>, usize, usize)> = VecDeque::new();
+        let mut backtracks = 0;
+
+        while let Some(spot) = wave.lowest_entropy(rng) {
+            let pattern = wave.choose(spot, rng);
+
+            if backtracking > 0 {
+                if history.len() == backtracking {
+                    history.pop_front();
+                }
+                history.push_back((wave.bits.clone(), spot, pattern));
+            }
+
+            wave.collapse(spot, pattern);
+            if wave.propagate(vec![spot]) {
+                continue;
+            }
+
+            // undo choices until one can be ruled out without a contradiction
+            loop {
+                let (bits, spot, pattern) = history.pop_back()?;
+                backtracks += 1;
+                if backtracks > count {
+                    return None;
+                }
+
+                wave.bits = bits;
+                wave.ban(spot, pattern);
+                if wave.options(spot).next().is_some() && wave.propagate(vec![spot]) {
+                    break;
+                }
+            }
+        }
+
+        let mut tiles = vec![0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let (column, row) = (x.min(wave.columns - 1), y.min(wave.rows - 1));
+                let pattern = wave.options(row * wave.columns + column).next()?;
+                tiles[y * width + x] = self.patterns[pattern][(y - row) * self.size + x - column];
+            }
+        }
+
+        Some(tiles)
+    }
+}
+
+/// The patterns that still fit at every spot of the map being generated.
+/// A spot is where the top left corner of a pattern goes.
+struct Wave<'a> {
+    model: &'a Model,
+    columns: usize,
+    rows: usize,
+    bits: Vec<u64>,
+}
+
+impl Wave<'_> {
+    fn spot_bits(&self, spot: usize) -> &[u64] {
+        &self.bits[spot * self.model.words..(spot + 1) * self.model.words]
+    }
+
+    /// The patterns that still fit at a spot
+    fn options(&self, spot: usize) -> impl Iterator<Item = usize> + '_ {
+        self.spot_bits(spot)
+            .iter()
+            .enumerate()
+            .flat_map(|(word, &bits)| {
+                (0..64)
+                    .filter(move |bit| bits & (1 << bit) != 0)
+                    .map(move |bit| word * 64 + bit)
+            })
+    }
+
+    /// The undecided spot with the least entropy, with a little noise to
+    /// break ties at random. `None` once every spot is decided.
+    fn lowest_entropy<R: Rng>(&self, rng: &mut R) -> Option<usize> {
+        let mut lowest: Option<(usize, f64)> = None;
+
+        for spot in 0..self.columns * self.rows {
+            let weights: Vec<f64> = self.options(spot).map(|p| self.model.weights[p]).collect();
+            if weights.len() < 2 {
+                continue;
+            }
+
+            let total: f64 = weights.iter().sum();
+            let entropy = total.ln() - weights.iter().map(|w| w * w.ln()).sum::<f64>() / total
+                + rng.random_range(0.0..1e-6);
+
+            if lowest.is_none_or(|(_, least)| entropy < least) {
+                lowest = Some((spot, entropy));
+            }
+        }
+
+        lowest.map(|(spot, _)| spot)
+    }
+
+    /// Picks one of the patterns that fit at a spot, weighted by how often
+    /// they show up in the sample
+    fn choose<R: Rng>(&self, spot: usize, rng: &mut R) -> usize {
+        let options: Vec<usize> = self.options(spot).collect();
+        let total: f64 = options.iter().map(|&p| self.model.weights[p]).sum();
+
+        let mut roll = rng.random_range(0.0..total);
+        for &p in options.iter() {
+            roll -= self.model.weights[p];
+            if roll < 0.0 {
+                return p;
+            }
+        }
+        *options.last().unwrap()
+    }
+
+    fn collapse(&mut self, spot: usize, pattern: usize) {
+        let words = self.model.words;
+        for (word, bits) in self.bits[spot * words..(spot + 1) * words]
+            .iter_mut()
+            .enumerate()
+        {
+            *bits = if word == pattern / 64 {
+                1 << (pattern % 64)
+            } else {
+                0
+            };
+        }
+    }
+
+    fn ban(&mut self, spot: usize, pattern: usize) {
+        self.bits[spot * self.model.words + pattern / 64] &= !(1 << (pattern % 64));
+    }
+
+    /// Rules out the patterns that don't fit next to the changed spots, and
+    /// so on until nothing changes. Returns false if a spot is left with no
+    /// patterns at all.
+    fn propagate(&mut self, mut changed: Vec<usize>) -> bool {
+        let words = self.model.words;
+
+        while let Some(spot) = changed.pop() {
+            let (x, y) = ((spot % self.columns) as i32, (spot / self.columns) as i32);
+
+            for (d, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= self.columns as i32 || ny >= self.rows as i32 {
+                    continue;
+                }
+                let next = ny as usize * self.columns + nx as usize;
+
+                let mut allowed = vec![0; words];
+                for p in self.options(spot) {
+                    for (bits, fits) in allowed.iter_mut().zip(&self.model.propagator[d][p]) {
+                        *bits |= fits;
+                    }
+                }
+
+                let mut shrunk = false;
+                let mut empty = true;
+                for (bits, allowed) in self.bits[next * words..(next + 1) * words]
+                    .iter_mut()
+                    .zip(allowed)
+                {
+                    let kept = *bits & allowed;
+                    shrunk |= kept != *bits;
+                    empty &= kept == 0;
+                    *bits = kept;
+                }
+
+                if empty {
+                    return false;
+                }
+                if shrunk {
+                    changed.push(next);
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// A pattern, with its rotations and reflections if the settings ask for
+/// them
+fn variants(pattern: Vec<usize>, size: usize, settings: &WfcSettings) -> Vec<Vec<usize>> {
+    let rotate = |p: &Vec<usize>| -> Vec<usize> {
+        (0..size * size)
+            .map(|i| p[(size - 1 - i % size) * size + i / size])
+            .collect()
+    };
+    let reflect = |p: &Vec<usize>| -> Vec<usize> {
+        (0..size * size)
+            .map(|i| p[(i / size) * size + size - 1 - i % size])
+            .collect()
+    };
+
+    let mut variants = vec![pattern];
+    if settings.rotations {
+        for _ in 0..3 {
+            variants.push(rotate(variants.last().unwrap()));
+        }
+    }
+    if settings.reflections {
+        let reflected: Vec<Vec<usize>> = variants.iter().map(reflect).collect();
+        variants.extend(reflected);
+    }
+
+    variants
+}
+
+/// Whether pattern `q`, shifted by `(dx, dy)` from pattern `p`, agrees with
+/// it where they overlap
+fn overlaps(p: &[usize], q: &[usize], size: usize, dx: i32, dy: i32) -> bool {
+    let n = size as i32;
+    for y in dy.max(0)..(n + dy).min(n) {
+        for x in dx.max(0)..(n + dx).min(n) {
+            let (qx, qy) = (x - dx, y - dy);
+            if p[(y * n + x) as usize] != q[(qy * n + qx) as usize] {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Fills the [`Labyrinth2D`] of the [`MapGenerator2D`] with the patterns of
+/// a sample map.
+pub fn build_wfc<R: Rng>(
+    mapgen: &mut MapGenerator2D,
+    settings: &WfcSettings,
+    rng: &mut R,
+) -> Result<(), BuilderError> {
+    let model = Model::learn(settings)?;
+    let dims = *mapgen.dimensions();
+
+    if dims.x < model.size as i32 || dims.y < model.size as i32 {
+        return Err(BuilderError::BuildError(format!(
+            "A {}x{} map can't fit the {}x{} WFC patterns",
+            dims.x, dims.y, model.size, model.size
+        )));
+    }
+
+    let (width, height) = (dims.x as usize, dims.y as usize);
+    let tiles = (0..settings.attempts)
+        .find_map(|_| model.run(width, height, settings.backtracking, rng))
+        .ok_or_else(|| {
+            BuilderError::BuildError(format!(
+                "WFC ran into contradictions on all {} attempts",
+                settings.attempts
+            ))
+        })?;
+
+    let map = mapgen.map_mut();
+    for (idx, &tile) in tiles.iter().enumerate() {
+        let pt = map.index_to_point2d(idx);
+        map.set_tile_at(pt, model.palette[tile].clone());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+    use rand::SeedableRng;
+
+    fn prepare_sample() -> Labyrinth2D {
+        Labyrinth2D::from_ascii(
+            "~ = water transparent Swim\n---\n\
+             ##########\n\
+             #....#...#\n\
+             #.~~.#...#\n\
+             #.~~.....#\n\
+             #....#...#\n\
+             ###.####.#\n\
+             #........#\n\
+             ##########",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn output_is_made_of_sample_patterns() {
+        let settings = WfcSettings::new(prepare_sample());
+        let model = Model::learn(&settings).unwrap();
+        let mut rng = MapRng::seed_from_u64(5);
+
+        let tiles = (0..10)
+            .find_map(|_| model.run(20, 14, 32, &mut rng))
+            .unwrap();
+
+        for y in 0..=14 - 3 {
+            for x in 0..=20 - 3 {
+                let window: Vec<usize> = (0..9)
+                    .map(|i| tiles[(y + i / 3) * 20 + x + i % 3])
+                    .collect();
+                assert!(model.patterns.contains(&window));
+            }
+        }
+    }
+
+    #[test]
+    fn augmentation_adds_patterns() {
+        let plain = WfcSettings::new(prepare_sample())
+            .with_rotations(false)
+            .with_reflections(false);
+        let plain = Model::learn(&plain).unwrap();
+        let augmented = Model::learn(&WfcSettings::new(prepare_sample())).unwrap();
+
+        assert_eq!(plain.weights.iter().sum::<f64>(), 8.0 * 6.0);
+        assert!(augmented.patterns.len() > plain.patterns.len());
+    }
+
+    #[test]
+    fn custom_tiles_carry_over() {
+        let settings = WfcSettings::new(prepare_sample());
+        let mut mapgen = MapGenerator2D::new(30, 20).without_connectivity_repair();
+
+        // water is rare in the sample, so it may take a few maps to show up
+        let water = (0..10)
+            .find_map(|seed| {
+                let map = mapgen
+                    .generate_seeded(FloorGenAlg::Wfc(settings.clone()), seed)
+                    .unwrap()
+                    .into_map();
+                map.iter().find(|tile| tile.kind() == "water").cloned()
+            })
+            .expect("no water was generated");
+        assert!(water.can_enter(&[MoveType::Swim]));
+        assert!(!water.can_enter(&[MoveType::Walk]));
+    }
+
+    #[test]
+    fn bad_sizes_are_errors() {
+        let tiny = Labyrinth2D::new_walled(2, 2);
+        let mut mapgen = MapGenerator2D::new(30, 20);
+        assert!(mapgen
+            .generate(FloorGenAlg::Wfc(WfcSettings::new(tiny)))
+            .is_err());
+
+        let mut mapgen = MapGenerator2D::new(2, 20);
+        assert!(mapgen
+            .generate(FloorGenAlg::Wfc(WfcSettings::new(prepare_sample())))
+            .is_err());
+    }
+}
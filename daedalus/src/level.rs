@@ -0,0 +1,438 @@
+//! Module containing the result of a generation run
+
+use bracket_geometry::prelude::*;
+use bracket_pathfinding::prelude::Algorithm2D;
+use serde::{Deserialize, Serialize};
+
+use labyrinth_map::prelude::*;
+
+use crate::errors::BuilderError;
+use crate::genalgs::locks::LockPuzzle;
+use crate::genalgs::mazes::MazeTree;
+use crate::genalgs::rooms::{CompoundRoom, Room};
+use crate::map_generators::{FloorGenAlg, MapGenerator2D};
+use crate::room_graph::RoomGraph;
+use crate::steps::{MapStep, StepSettings};
+
+/// A room placed by the generator, as it ended up on the map.
+///
+/// Rooms are listed in the order they were placed, which is also the order of
+/// the `room {i}` zones of the map.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelRoom {
+    bounds: Rect,
+    centre: Point,
+}
+
+impl LevelRoom {
+    /// Constructs a LevelRoom from the floor of a [`Room`]
+    pub(crate) fn from_room(room: &dyn Room) -> LevelRoom {
+        let floor = room.floor();
+
+        let x1 = floor.iter().map(|pt| pt.x).min().unwrap_or(0);
+        let y1 = floor.iter().map(|pt| pt.y).min().unwrap_or(0);
+        let x2 = floor.iter().map(|pt| pt.x + 1).max().unwrap_or(0);
+        let y2 = floor.iter().map(|pt| pt.y + 1).max().unwrap_or(0);
+        let bounds = Rect::with_exact(x1, y1, x2, y2);
+
+        // the floor tile closest to the middle, so that the centre of an odd
+        // shaped room is still inside it
+        let middle = bounds.center();
+        let centre = floor
+            .iter()
+            .min_by_key(|pt| {
+                (
+                    (pt.x - middle.x).pow(2) + (pt.y - middle.y).pow(2),
+                    pt.y,
+                    pt.x,
+                )
+            })
+            .copied()
+            .unwrap_or(middle);
+
+        LevelRoom { bounds, centre }
+    }
+
+    /// Getter for the bounding box of the room's floor. Like every [`Rect`],
+    /// it includes `x1` and `y1` but not `x2` and `y2`.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Getter for the floor tile closest to the middle of the room
+    pub fn centre(&self) -> Point {
+        self.centre
+    }
+}
+
+/// Everything produced by a [`MapGenerator2D::generate`](crate::prelude::MapGenerator2D::generate)
+/// call: the map, and what is needed to populate it or generate it again.
+///
+/// A GeneratedLevel can be serialized, so it can be saved along with the rest
+/// of a game.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50).with_seed(7);
+/// let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+///
+/// let spawn = level.spawn().unwrap();
+/// let exit = level.exit().unwrap();
+/// assert!(level.map().can_enter(spawn, &[MoveType::Walk]));
+/// assert!(level.map().can_enter(exit, &[MoveType::Walk]));
+///
+/// // every level has a seed of its own, which generates it again
+/// let next = mapgen.generate(FloorGenAlg::Basic).unwrap();
+/// let again = MapGenerator2D::new(80, 50)
+///     .generate_seeded(next.algorithm().clone(), next.seed())
+///     .unwrap();
+/// assert_eq!(next, again);
+///
+/// // as do the steps it recorded, without the Generator
+/// assert_eq!(next.regenerate().unwrap(), next);
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedLevel {
+    map: Labyrinth2D,
+    rooms: Vec<LevelRoom>,
+    connections: Vec<Point>,
+    room_graph: RoomGraph,
+    seed: u64,
+    spawn: Option<Point>,
+    exit: Option<Point>,
+    algorithm: FloorGenAlg,
+    #[serde(default)]
+    lock_puzzle: Option<LockPuzzle>,
+    #[serde(default)]
+    maze: Option<MazeTree>,
+    #[serde(default)]
+    steps: Vec<StepSettings>,
+}
+
+impl GeneratedLevel {
+    /// Bundles a finished map with its rooms and connections, and picks the
+    /// spawn and exit points.
+    pub(crate) fn new(
+        map: Labyrinth2D,
+        compound: &CompoundRoom,
+        seed: u64,
+        algorithm: FloorGenAlg,
+    ) -> GeneratedLevel {
+        let rooms: Vec<LevelRoom> = compound
+            .rooms()
+            .iter()
+            .map(|room| LevelRoom::from_room(room.as_ref()))
+            .collect();
+
+        let mut connections: Vec<Point> = compound.connections.iter().copied().collect();
+        connections.sort_unstable_by_key(|pt| (pt.y, pt.x));
+
+        let spawn = GeneratedLevel::pick_spawn(&map, &rooms);
+        let exit = spawn.map(|spawn| GeneratedLevel::pick_exit(&map, spawn));
+
+        let room_of = |pt: Option<Point>| pt.and_then(|pt| GeneratedLevel::room_of(compound, pt));
+        let room_graph =
+            RoomGraph::new(rooms.len(), compound.links()).with_ends(room_of(spawn), room_of(exit));
+
+        GeneratedLevel {
+            map,
+            rooms,
+            connections,
+            room_graph,
+            seed,
+            spawn,
+            exit,
+            algorithm,
+            lock_puzzle: None,
+            maze: None,
+            steps: vec![],
+        }
+    }
+
+    /// The centre of the first room, or else the first walkable tile
+    fn pick_spawn(map: &Labyrinth2D, rooms: &[LevelRoom]) -> Option<Point> {
+        let walkable = |pt: &Point| map.can_enter(*pt, &[MoveType::Walk]);
+
+        rooms
+            .first()
+            .map(LevelRoom::centre)
+            .filter(walkable)
+            .or_else(|| {
+                (0..map.size())
+                    .map(|idx| map.index_to_point2d(idx))
+                    .find(walkable)
+            })
+    }
+
+    /// The first room with a point in its floor or, for points in the walls
+    /// between rooms, next to its floor
+    fn room_of(compound: &CompoundRoom, pt: Point) -> Option<usize> {
+        let floors: Vec<_> = compound.rooms().iter().map(|room| room.floor()).collect();
+
+        floors
+            .iter()
+            .position(|floor| floor.contains(&pt))
+            .or_else(|| {
+                [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                    .into_iter()
+                    .map(|(dx, dy)| pt + Point::new(dx, dy))
+                    .find_map(|next| floors.iter().position(|floor| floor.contains(&next)))
+            })
+    }
+
+    /// The walkable tile furthest away from the spawn, going through doors
+    fn pick_exit(map: &Labyrinth2D, spawn: Point) -> Point {
+        // walkers that can open every door, with the keys to the locked ones
+        let mut movers = vec![MoveType::Walk, MoveType::opener()];
+        for tile in map.iter() {
+            if tile.door_state() == Some(DoorState::Locked) {
+                movers.extend(tile.access().iter().cloned());
+            }
+        }
+        movers.sort_unstable();
+        movers.dedup();
+
+        let dijkstra = map.dijkstra_map(&[spawn], movers);
+
+        dijkstra
+            .map
+            .iter()
+            .enumerate()
+            .filter(|&(idx, &distance)| {
+                distance < f32::MAX && map.can_enter(map.index_to_point2d(idx), &[MoveType::Walk])
+            })
+            .fold(
+                None,
+                |furthest: Option<(usize, f32)>, (idx, &distance)| match furthest {
+                    Some((_, most)) if most >= distance => furthest,
+                    _ => Some((idx, distance)),
+                },
+            )
+            .map_or(spawn, |(idx, _)| map.index_to_point2d(idx))
+    }
+
+    /// Getter for the map
+    pub fn map(&self) -> &Labyrinth2D {
+        &self.map
+    }
+
+    /// Retrieves a mutable reference to the map, for the steps that run on a
+    /// finished level
+    pub(crate) fn map_mut(&mut self) -> &mut Labyrinth2D {
+        &mut self.map
+    }
+
+    /// Takes the map out of the level
+    pub fn into_map(self) -> Labyrinth2D {
+        self.map
+    }
+
+    /// Getter for the rooms, in the order they were placed
+    pub fn rooms(&self) -> &Vec<LevelRoom> {
+        &self.rooms
+    }
+
+    /// Getter for the points where rooms were connected, such as doorways
+    pub fn connections(&self) -> &Vec<Point> {
+        &self.connections
+    }
+
+    /// Getter for the graph of which rooms join which
+    pub fn room_graph(&self) -> &RoomGraph {
+        &self.room_graph
+    }
+
+    /// Outlines the floor of every room of the level on a [`MapRenderer`],
+    /// using the `room {i}` zones of the map.
+    #[cfg(feature = "render")]
+    pub fn outline_rooms(&self, renderer: MapRenderer, colour: Rgb) -> MapRenderer {
+        let mut floors = vec![vec![]; self.map.zone_count()];
+        for idx in 0..self.map.size() {
+            let pt = self.map.index_to_point2d(idx);
+            if let Some(zone) = self.map.zone_at(pt) {
+                floors[zone].push(pt);
+            }
+        }
+
+        floors
+            .into_iter()
+            .enumerate()
+            .filter(|&(zone, _)| {
+                self.map
+                    .zone_label(zone)
+                    .is_some_and(|label| label.starts_with("room "))
+            })
+            .fold(renderer, |renderer, (_, floor)| {
+                renderer.with_outline(floor, colour)
+            })
+    }
+
+    /// Getter for the seed the level was generated with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Getter for the spawn point: the centre of the first room, or the first
+    /// walkable tile of maps without rooms. Mazes start at one end of their
+    /// longest path. `None` if nothing can be walked on.
+    pub fn spawn(&self) -> Option<Point> {
+        self.spawn
+    }
+
+    /// Getter for the exit point: the walkable tile furthest from the spawn,
+    /// counting the cost of opening doors on the way. Mazes end at the other
+    /// end of their longest path.
+    pub fn exit(&self) -> Option<Point> {
+        self.exit
+    }
+
+    /// Getter for the algorithm and settings the level was generated with
+    pub fn algorithm(&self) -> &FloorGenAlg {
+        &self.algorithm
+    }
+
+    /// Getter for the settings of the steps that generated the level, in the
+    /// order they ran
+    pub fn steps(&self) -> &Vec<StepSettings> {
+        &self.steps
+    }
+
+    /// Generates the level again, running the steps it recorded with its
+    /// seed. Levels with steps from outside the crate can't be regenerated.
+    pub fn regenerate(&self) -> Result<GeneratedLevel, BuilderError> {
+        let steps: Vec<Box<dyn MapStep>> = self
+            .steps
+            .iter()
+            .map(|step| Box::new(step.clone()) as Box<dyn MapStep>)
+            .collect();
+
+        let dims = self.map.dimensions();
+        MapGenerator2D::new(dims.x as usize, dims.y as usize).run_steps(
+            self.algorithm.clone(),
+            &steps,
+            self.seed,
+        )
+    }
+
+    /// Getter for the lock-and-key puzzle, if the generator made one
+    pub fn lock_puzzle(&self) -> Option<&LockPuzzle> {
+        self.lock_puzzle.as_ref()
+    }
+
+    /// Getter for the spanning tree of the maze, if the level is one
+    pub fn maze(&self) -> Option<&MazeTree> {
+        self.maze.as_ref()
+    }
+
+    /// Sets the spanning tree of the maze, moving the spawn and exit to the
+    /// ends of its longest path
+    pub(crate) fn set_maze(&mut self, maze: MazeTree) {
+        let path = maze.longest_path();
+        if let (Some(&first), Some(&last)) = (path.first(), path.last()) {
+            self.spawn = Some(maze.cell_point(first));
+            self.exit = Some(maze.cell_point(last));
+        }
+        self.maze = Some(maze);
+    }
+
+    /// Sets the lock-and-key puzzle
+    pub(crate) fn set_lock_puzzle(&mut self, puzzle: LockPuzzle) {
+        self.lock_puzzle = Some(puzzle);
+    }
+
+    /// Moves the spawn and exit, and the rooms at the ends of the room graph
+    pub(crate) fn set_ends(
+        &mut self,
+        compound: &CompoundRoom,
+        spawn: Option<Point>,
+        exit: Option<Point>,
+    ) {
+        let room_of = |pt: Option<Point>| pt.and_then(|pt| GeneratedLevel::room_of(compound, pt));
+        self.room_graph = self
+            .room_graph
+            .clone()
+            .with_ends(room_of(spawn), room_of(exit));
+        self.spawn = spawn;
+        self.exit = exit;
+    }
+
+    /// Sets the settings of the steps that generated the level
+    pub(crate) fn set_steps(&mut self, steps: Vec<StepSettings>) {
+        self.steps = steps;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::genalgs::caves::CaveSettings;
+    use crate::genalgs::rooms::RectRoom;
+    use crate::map_generators::MapGenerator2D;
+
+    #[test]
+    fn rooms_have_bounds_and_centres() {
+        let mut room = RectRoom::new(5, 3);
+        room.shift(Point::new(2, 4));
+
+        let level_room = LevelRoom::from_room(&room);
+
+        assert_eq!(level_room.bounds(), Rect::with_size(2, 4, 5, 3));
+        assert_eq!(level_room.centre(), Point::new(4, 5));
+    }
+
+    #[test]
+    fn level_is_reversible() {
+        let mut mapgen = MapGenerator2D::new(40, 30).with_seed(11);
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+
+        assert!(!level.rooms().is_empty());
+        assert_eq!(level.rooms().len(), level.map().zone_count());
+
+        let raw = ron::to_string(&level).unwrap();
+        let level2: GeneratedLevel = ron::from_str(&raw).unwrap();
+
+        assert_eq!(level, level2);
+    }
+
+    #[test]
+    fn caves_still_have_a_spawn_and_exit() {
+        let mut mapgen = MapGenerator2D::new(40, 30).with_seed(2);
+        let level = mapgen
+            .generate(FloorGenAlg::Caves(CaveSettings::new()))
+            .unwrap();
+
+        assert!(level.rooms().is_empty());
+
+        let (spawn, exit) = (level.spawn().unwrap(), level.exit().unwrap());
+        assert_ne!(spawn, exit);
+        assert!(level.map().can_enter(exit, &[MoveType::Walk]));
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn rooms_are_outlined() {
+        let renderer = MapRenderer::new().with_tile_size(4);
+        let red = [255, 0, 0];
+
+        let level = MapGenerator2D::new(40, 30)
+            .with_seed(11)
+            .generate(FloorGenAlg::Basic)
+            .unwrap();
+        let plain = renderer.clone().render(level.map());
+        let outlined = level.outline_rooms(renderer.clone(), red);
+        assert_ne!(outlined.render(level.map()), plain);
+
+        // caves have no rooms to outline
+        let cave = MapGenerator2D::new(40, 30)
+            .with_seed(2)
+            .generate(FloorGenAlg::Caves(CaveSettings::new()))
+            .unwrap();
+        assert_eq!(
+            cave.outline_rooms(renderer.clone(), red).render(cave.map()),
+            renderer.render(cave.map())
+        );
+    }
+}
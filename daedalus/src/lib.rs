@@ -13,12 +13,26 @@ use labyrinth_map;
 mod errors;
 
 mod genalgs;
+mod level;
 mod map_generators;
+mod room_graph;
+mod steps;
 
 pub mod prelude {
     //! Re-exported important objects (public API)
+    pub use crate::errors::BuilderError;
+    pub use crate::genalgs::bsp::BspSettings;
+    pub use crate::genalgs::caves::{CaveRegions, CaveSettings};
+    pub use crate::genalgs::doors::DoorSettings;
+    pub use crate::genalgs::drunkards::{DrunkardSettings, DrunkardSpawn, DrunkardSymmetry};
+    pub use crate::genalgs::locks::{Lock, LockPuzzle, LockSettings, PuzzleStep};
+    pub use crate::genalgs::mazes::{MazeAlgorithm, MazeSettings, MazeTree};
     pub use crate::genalgs::rooms::*;
+    pub use crate::genalgs::wfc::WfcSettings;
+    pub use crate::level::*;
     pub use crate::map_generators::*;
+    pub use crate::room_graph::*;
+    pub use crate::steps::*;
     pub use labyrinth_map::prelude::*;
 }
 
@@ -7,16 +7,50 @@ use std::collections::HashSet;
 use bracket_geometry::prelude::*;
 use bracket_pathfinding::prelude::*;
 
+use rand::rngs::Xoshiro256PlusPlus;
+use rand::{RngExt, SeedableRng};
+use serde::{Deserialize, Serialize};
+
 use crate::genalgs;
+use crate::level::GeneratedLevel;
+use crate::steps::{ConnectivityRepair, MapStep};
+use genalgs::bsp::BspSettings;
+use genalgs::caves::CaveSettings;
+use genalgs::doors::DoorSettings;
+use genalgs::drunkards::DrunkardSettings;
+use genalgs::locks::{LockPuzzle, LockSettings};
+use genalgs::mazes::{MazeSettings, MazeTree};
 use genalgs::rooms::*;
+use genalgs::wfc::WfcSettings;
 
 use super::errors::BuilderError;
 
 use labyrinth_map::prelude::*;
 
-#[derive(Debug)]
+/// Random number generator used by the [`MapGenerator2D`].
+///
+/// Xoshiro256++ is a fixed, portable algorithm, so the same seed generates
+/// the same map on every platform.
+pub type MapRng = Xoshiro256PlusPlus;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FloorGenAlg {
     Basic, // Rooms and Corridors
+
+    /// Binary space partitioning, with a room in every leaf
+    Bsp(BspSettings),
+
+    /// Cellular automata caves
+    Caves(CaveSettings),
+
+    /// Winding tunnels dug by random walkers
+    DrunkardsWalk(DrunkardSettings),
+
+    /// Perfect mazes, optionally braided
+    Maze(MazeSettings),
+
+    /// Wave Function Collapse, copying the patterns of a sample map
+    Wfc(WfcSettings),
 }
 
 /// Builder struct for 2D Maps
@@ -35,25 +69,184 @@ pub enum FloorGenAlg {
 /// let floor3 = mapgen.generate(FloorGenAlg::Basic);
 /// assert!(floor3.is_ok());
 /// ```
+///
+/// # Seeding
+/// Every generator owns a seeded rng, which all generation algorithms draw
+/// from. Generators made with the same seed produce the same sequence of maps.
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let mut mapgen1 = MapGenerator2D::new(80, 50).with_seed(1234);
+/// let mut mapgen2 = MapGenerator2D::new(80, 50).with_seed(1234);
+///
+/// assert_eq!(
+///     mapgen1.generate(FloorGenAlg::Basic).unwrap(),
+///     mapgen2.generate(FloorGenAlg::Basic).unwrap()
+/// );
+/// ```
+///
+/// # Connectivity
+/// After any algorithm, the Generator tunnels corridors between regions of
+/// the map that can't reach each other, so every floor tile can be walked to.
+/// The movement types to connect the map for can be changed, or the repair
+/// can be turned off entirely.
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50);
+/// let floor = mapgen.generate(FloorGenAlg::Caves(CaveSettings::new())).unwrap().into_map();
+/// assert!(floor.is_fully_connected([MoveType::Walk]));
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50).without_connectivity_repair();
+/// assert!(mapgen.generate(FloorGenAlg::Basic).is_ok());
+/// ```
+///
+/// # Pipelines
+/// Every level is generated by a list of [`MapStep`]s: the algorithm, the
+/// steps added with [`MapGenerator2D::with_step`] in the order they were
+/// added, then the connectivity repair, prefabs, doors and locks of the
+/// Generator. The settings of every step are recorded in the level, which can
+/// be generated again from them with [`GeneratedLevel::regenerate`].
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50)
+///     .with_step(DrunkardSettings::new().with_walkers(10))
+///     .with_step(CullUnreachable::new([MoveType::Walk]));
+///
+/// let level = mapgen.generate(FloorGenAlg::Bsp(BspSettings::new())).unwrap();
+/// assert!(level.map().is_fully_connected([MoveType::Walk]));
+/// assert_eq!(level.steps().len(), 4);
+/// assert_eq!(level.regenerate().unwrap(), level);
+/// ```
 pub struct MapGenerator2D {
     map: Labyrinth2D,
     rooms: CompoundRoom,
     dimensions: Point,
     dirty: bool,
+    seed: u64,
+    rng: MapRng,
+    repair: Option<Vec<MoveType>>,
+    doors: Option<DoorSettings>,
+    locks: Option<LockSettings>,
+    maze: Option<MazeTree>,
+    prefabs: Vec<Prefab>,
+    placed_prefabs: Vec<Prefab>,
+    steps: Vec<Box<dyn MapStep>>,
+    // the algorithm of the level being generated
+    algorithm: FloorGenAlg,
+    // the last puzzle a lock step made, with the spawn and exit it locks
+    lock_puzzle: Option<(LockPuzzle, Option<Point>, Option<Point>)>,
 }
 
 impl MapGenerator2D {
     // ------------------ Initialization Methods ----------------------
-    /// Creates a new Generator struct using width and height inputs
+    /// Creates a new Generator struct using width and height inputs,
+    /// with a random seed
     pub fn new(width: usize, height: usize) -> MapGenerator2D {
+        let seed = rand::rng().random();
+
         MapGenerator2D {
             map: Labyrinth2D::new(width, height),
             rooms: CompoundRoom::new(),
             dimensions: Point::new(width, height),
             dirty: false,
+            seed,
+            rng: MapRng::seed_from_u64(seed),
+            repair: Some(vec![MoveType::Walk]),
+            doors: None,
+            locks: None,
+            maze: None,
+            prefabs: vec![],
+            placed_prefabs: vec![],
+            steps: vec![],
+            algorithm: FloorGenAlg::Basic,
+            lock_puzzle: None,
         }
     }
 
+    /// Sets the seed of the Generator
+    pub fn with_seed(mut self, seed: u64) -> MapGenerator2D {
+        self.reseed(seed);
+        self
+    }
+
+    /// Resets the rng of the Generator to the start of a given seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = MapRng::seed_from_u64(seed);
+    }
+
+    /// Sets the movement types the Generator connects maps for, after
+    /// generating them. Defaults to [`MoveType::Walk`].
+    pub fn with_connectivity_repair<T: Into<Vec<MoveType>>>(
+        mut self,
+        move_types: T,
+    ) -> MapGenerator2D {
+        self.repair = Some(move_types.into());
+        self
+    }
+
+    /// Turns off the connectivity repair after generating maps
+    pub fn without_connectivity_repair(mut self) -> MapGenerator2D {
+        self.repair = None;
+        self
+    }
+
+    /// Sets the doors the Generator places where rooms connect, after
+    /// generating and repairing maps. There are no doors by default.
+    pub fn with_doors(mut self, doors: DoorSettings) -> MapGenerator2D {
+        self.doors = Some(doors);
+        self
+    }
+
+    /// Turns off door placement
+    pub fn without_doors(mut self) -> MapGenerator2D {
+        self.doors = None;
+        self
+    }
+
+    /// Sets the lock-and-key puzzle the Generator builds over the rooms of
+    /// every level, after placing doors. There is no puzzle by default.
+    pub fn with_locks(mut self, locks: LockSettings) -> MapGenerator2D {
+        self.locks = Some(locks);
+        self
+    }
+
+    /// Turns off lock-and-key puzzles
+    pub fn without_locks(mut self) -> MapGenerator2D {
+        self.locks = None;
+        self
+    }
+
+    /// Adds a prefab for the Generator to stamp onto every map, after
+    /// repairing it and before placing doors. Prefabs are placed in the
+    /// order they were added, and left out if there is no room for them.
+    pub fn with_prefab(mut self, prefab: Prefab) -> MapGenerator2D {
+        self.prefabs.push(prefab);
+        self
+    }
+
+    /// Removes every prefab from the Generator
+    pub fn without_prefabs(mut self) -> MapGenerator2D {
+        self.prefabs.clear();
+        self
+    }
+
+    /// Adds a step for the Generator to run on every map, after the algorithm
+    /// and before the connectivity repair. Steps run in the order they were
+    /// added.
+    pub fn with_step<T: MapStep + 'static>(mut self, step: T) -> MapGenerator2D {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Removes every step added with [`MapGenerator2D::with_step`]
+    pub fn without_steps(mut self) -> MapGenerator2D {
+        self.steps.clear();
+        self
+    }
+
     // ----------------- Access Methods ---------------------
     /// Retrieves a reference to the internal [`Labyrinth2D`] of the Generator
     pub fn map(&self) -> &Labyrinth2D {
@@ -85,32 +278,177 @@ impl MapGenerator2D {
         &self.dimensions
     }
 
+    /// Retrieves the prefabs placed on the last map, where they were placed
+    pub fn placed_prefabs(&self) -> &Vec<Prefab> {
+        &self.placed_prefabs
+    }
+
+    /// Records prefabs placed on the current map
+    pub(crate) fn add_placed_prefabs(&mut self, placed: Vec<Prefab>) {
+        self.placed_prefabs.extend(placed);
+    }
+
+    /// Retrieves the spanning tree of the last maze the Generator made, if
+    /// the last map was a maze
+    pub fn maze(&self) -> Option<&MazeTree> {
+        self.maze.as_ref()
+    }
+
+    /// Stores the spanning tree of the maze on the map
+    pub(crate) fn set_maze(&mut self, maze: MazeTree) {
+        self.maze = Some(maze);
+    }
+
+    /// Stores a lock-and-key puzzle, along with the map and ends of the level
+    /// it was made for
+    pub(crate) fn set_lock_puzzle(&mut self, puzzle: LockPuzzle, level: &GeneratedLevel) {
+        self.map = level.map().clone();
+        self.lock_puzzle = Some((puzzle, level.spawn(), level.exit()));
+    }
+
+    /// Bundles the map so far into a level
+    pub(crate) fn level(&self) -> GeneratedLevel {
+        let mut level = GeneratedLevel::new(
+            self.map.clone(),
+            &self.rooms,
+            self.seed,
+            self.algorithm.clone(),
+        );
+        if let Some(maze) = self.maze.clone() {
+            level.set_maze(maze);
+        }
+
+        // the exit could move once doors are locked, so it stays where the
+        // puzzle was made for
+        if let Some((puzzle, spawn, exit)) = self.lock_puzzle.clone() {
+            level.set_ends(&self.rooms, spawn, exit);
+            level.set_lock_puzzle(puzzle);
+        }
+
+        level
+    }
+
+    /// Retrieves the seed of the last level the Generator made, or the one it
+    /// was seeded with if it hasn't made any since
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Retrieves a mutable reference to the rng of the Generator. While a
+    /// [`MapStep`] runs, the rng is handed to the step instead, and this is a
+    /// stand-in whose draws don't carry over.
+    pub fn rng_mut(&mut self) -> &mut MapRng {
+        &mut self.rng
+    }
+
     // ----------------- Generation Methods -------------------------
-    /// Generates a FinishedMap using the current settings.
-    pub fn generate(&mut self, method: FloorGenAlg) -> Result<Labyrinth2D, BuilderError> {
+    /// Generates a [`GeneratedLevel`] using the current settings.
+    ///
+    /// Every level is generated from a seed of its own, drawn from the rng of
+    /// the Generator. That seed is stored in the level, and generates it
+    /// again with [`MapGenerator2D::generate_seeded`].
+    pub fn generate(&mut self, method: FloorGenAlg) -> Result<GeneratedLevel, BuilderError> {
+        let seed = self.rng.random();
+        self.generate_seeded(method, seed)
+    }
+
+    /// Reseeds the Generator, then generates a map. The same seed always
+    /// generates the same map.
+    pub fn generate_seeded(
+        &mut self,
+        method: FloorGenAlg,
+        seed: u64,
+    ) -> Result<GeneratedLevel, BuilderError> {
+        let mut steps: Vec<Box<dyn MapStep>> = vec![Box::new(method.clone())];
+        let added = self.steps.len();
+        steps.append(&mut self.steps);
+        steps.extend(self.post_processors());
+
+        let guard = StepsGuard {
+            mapgen: self,
+            steps,
+            added,
+        };
+        guard.mapgen.run_steps(method, &guard.steps, seed)
+    }
+
+    /// The connectivity repair, prefabs, doors and locks of the Generator, in
+    /// the order they run
+    fn post_processors(&self) -> Vec<Box<dyn MapStep>> {
+        let mut steps: Vec<Box<dyn MapStep>> = vec![];
+
+        if let Some(move_types) = self.repair.clone() {
+            steps.push(Box::new(ConnectivityRepair::new(move_types)));
+        }
+
+        for prefab in self.prefabs.iter() {
+            steps.push(Box::new(prefab.clone()));
+        }
+
+        // doors go in after the repair, so it doesn't dig around them
+        if let Some(doors) = self.doors.clone() {
+            steps.push(Box::new(doors));
+        }
+
+        if let Some(locks) = self.locks.clone() {
+            steps.push(Box::new(locks));
+        }
+
+        steps
+    }
+
+    /// Reseeds the Generator, then runs a list of steps on a new map
+    pub(crate) fn run_steps(
+        &mut self,
+        algorithm: FloorGenAlg,
+        steps: &[Box<dyn MapStep>],
+        seed: u64,
+    ) -> Result<GeneratedLevel, BuilderError> {
+        if steps.is_empty() {
+            return Err(BuilderError::BuildError(
+                "There are no steps to generate the level with".to_string(),
+            ));
+        }
+
+        self.reseed(seed);
+        self.algorithm = algorithm;
+
         // Start with a new map
         self.flush_map();
 
-        // Figure out the correct way to build the map
-        match method {
-            FloorGenAlg::Basic => {
-                genalgs::build_rooms_and_corridors(self);
-            }
-            _ => {
-                return Err(BuilderError::BuildError(format!(
-                    "FloorGenAlg {:?} is unimplemented for this Generator",
-                    method
-                )))
-            }
-        };
+        let mut rng = self.take_rng();
+        let result = steps.iter().try_for_each(|step| step.run(self, &mut rng));
+        self.rng = rng;
+        result?;
 
-        Ok(self.map.clone())
+        let mut level = self.level();
+        level.set_steps(steps.iter().map(|step| step.settings()).collect());
+        Ok(level)
+    }
+
+    /// Tunnels corridors between the regions of the internal [`Labyrinth2D`],
+    /// until it is fully connected for the given movement types.
+    pub fn repair_connectivity(&mut self, move_types: &[MoveType]) -> Result<(), BuilderError> {
+        let mut rng = self.take_rng();
+        let result = genalgs::connect_regions(self, move_types, &mut rng);
+        self.rng = rng;
+        result
+    }
+
+    /// Moves the rng out of the Generator, so it can be handed to the
+    /// algorithms alongside the Generator itself. Until it is put back, a
+    /// stand-in takes its place, and any draws from it are lost.
+    fn take_rng(&mut self) -> MapRng {
+        std::mem::replace(&mut self.rng, MapRng::seed_from_u64(0))
     }
 
     /// Resets the internal [`Labyrinth2D`] to a complely filled-in map
     pub fn flush_map(&mut self) {
         self.map = Labyrinth2D::new_from_dims(self.dimensions);
         self.rooms = CompoundRoom::new();
+        self.maze = None;
+        self.lock_puzzle = None;
+        self.placed_prefabs.clear();
         self.dirty = true;
     }
 
@@ -149,21 +487,179 @@ impl MapGenerator2D {
     }
 
     /// flashes the contents of the rooms to the internal map,
-    /// but only if it's been updated since
+    /// but only if it's been updated since. The connections between rooms
+    /// are opened up too.
+    ///
+    /// Every room also becomes a zone of the map, labelled `room {i}` after
+    /// its place in [`MapGenerator2D::rooms`]. Where rooms overlap, the tile
+    /// stays in the zone of the first one.
     pub fn update_rooms(&mut self) {
         if self.dirty {
-            for room in self.rooms.rooms() {
+            self.map.clear_zones();
+            for (i, room) in self.rooms.rooms().iter().enumerate() {
+                let zone = self.map.add_zone(format!("room {}", i));
                 for &floortile in room.floor().iter() {
                     if self.map.in_bounds(floortile) {
                         self.map.set_tile_at(floortile, Tile::floor());
+                        if self.map.zone_at(floortile).is_none() {
+                            self.map.set_zone_at(floortile, Some(zone));
+                        }
                     }
                 }
             }
+
+            for &connection in self.rooms.connections.iter() {
+                if self.map.in_bounds(connection) {
+                    self.map.set_tile_at(connection, Tile::floor());
+                }
+            }
         }
     }
 }
 
+/// Hands the steps added with [`MapGenerator2D::with_step`] back to the
+/// Generator once a level is done, even if one of the steps panics. They sit
+/// after the algorithm, at the front of the steps being run.
+struct StepsGuard<'a> {
+    mapgen: &'a mut MapGenerator2D,
+    steps: Vec<Box<dyn MapStep>>,
+    added: usize,
+}
+
+impl Drop for StepsGuard<'_> {
+    fn drop(&mut self) {
+        self.mapgen.steps = self.steps.drain(1..=self.added).collect();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn same_seed_same_map() {
+        let mut mapgen1 = MapGenerator2D::new(50, 50).with_seed(42);
+        let mut mapgen2 = MapGenerator2D::new(50, 50).with_seed(42);
+
+        for _ in 0..3 {
+            let floor1 = mapgen1.generate(FloorGenAlg::Basic).unwrap();
+            let floor2 = mapgen2.generate(FloorGenAlg::Basic).unwrap();
+
+            assert_eq!(floor1, floor2);
+            assert_eq!(mapgen1.connections(), mapgen2.connections());
+        }
+    }
+
+    #[test]
+    fn different_seeds_different_maps() {
+        let mut mapgen = MapGenerator2D::new(50, 50);
+
+        let floor1 = mapgen.generate_seeded(FloorGenAlg::Basic, 1).unwrap();
+        let floor2 = mapgen.generate_seeded(FloorGenAlg::Basic, 2).unwrap();
+
+        assert_ne!(floor1, floor2);
+        assert_eq!(mapgen.seed(), 2);
+    }
+
+    #[test]
+    fn generate_seeded_is_repeatable() {
+        let mut mapgen = MapGenerator2D::new(50, 50);
+
+        let floor1 = mapgen.generate_seeded(FloorGenAlg::Basic, 7).unwrap();
+        let _ = mapgen.generate(FloorGenAlg::Basic).unwrap();
+        let floor2 = mapgen.generate_seeded(FloorGenAlg::Basic, 7).unwrap();
+
+        assert_eq!(floor1, floor2);
+    }
+
+    #[test]
+    fn every_level_has_its_own_seed() {
+        let mut mapgen = MapGenerator2D::new(50, 50).with_seed(99);
+
+        let floor1 = mapgen.generate(FloorGenAlg::Basic).unwrap();
+        let floor2 = mapgen.generate(FloorGenAlg::Basic).unwrap();
+        assert_ne!(floor1.seed(), floor2.seed());
+        assert_eq!(mapgen.seed(), floor2.seed());
+
+        let rebuilt = MapGenerator2D::new(50, 50)
+            .generate_seeded(floor2.algorithm().clone(), floor2.seed())
+            .unwrap();
+        assert_eq!(rebuilt, floor2);
+    }
+
+    #[test]
+    fn rooms_become_zones() {
+        let mut mapgen = MapGenerator2D::new(50, 50).with_seed(5);
+        let floor = mapgen.generate(FloorGenAlg::Basic).unwrap().into_map();
+
+        assert_eq!(floor.zone_count(), mapgen.rooms().rooms().len());
+        for (i, room) in mapgen.rooms().rooms().iter().enumerate() {
+            let zone = floor.find_zone(&format!("room {}", i)).unwrap();
+            assert!(floor
+                .zone_points(zone)
+                .iter()
+                .all(|pt| room.floor().contains(pt)));
+        }
+    }
+
+    #[test]
+    fn generated_maps_are_connected() {
+        let mut mapgen = MapGenerator2D::new(50, 50);
+
+        for seed in 0..5 {
+            let floor = mapgen
+                .generate_seeded(FloorGenAlg::Basic, seed)
+                .unwrap()
+                .into_map();
+            assert!(floor.is_fully_connected([MoveType::Walk]));
+        }
+    }
+
+    #[test]
+    fn repair_connects_regions() {
+        let mut mapgen = MapGenerator2D::new(20, 20).with_seed(3);
+        mapgen.walled_map();
+        for y in 0..20 {
+            mapgen.map_mut().set_tile_at(Point::new(7, y), Tile::wall());
+            mapgen
+                .map_mut()
+                .set_tile_at(Point::new(12, y), Tile::lava());
+        }
+        assert_eq!(
+            mapgen.map().connected_components([MoveType::Walk]).count(),
+            3
+        );
+
+        mapgen.repair_connectivity(&[MoveType::Walk]).unwrap();
+        assert!(mapgen.map().is_fully_connected([MoveType::Walk]));
+    }
+
+    #[test]
+    fn repair_digs_the_shortest_corridors() {
+        let mut mapgen = MapGenerator2D::new(20, 10).with_seed(3);
+        *mapgen.map_mut() = Labyrinth2D::new_empty(20, 10);
+        for y in 0..10 {
+            for x in [7, 8, 14] {
+                mapgen.map_mut().set_tile_at(Point::new(x, y), Tile::wall());
+            }
+        }
+
+        mapgen.repair_connectivity(&[MoveType::Walk]).unwrap();
+        assert!(mapgen.map().is_fully_connected([MoveType::Walk]));
+        let walls = (0..mapgen.map().size())
+            .filter(|&idx| {
+                !mapgen
+                    .map()
+                    .can_enter(mapgen.map().index_to_point2d(idx), &[MoveType::Walk])
+            })
+            .count();
+        assert_eq!(walls, 27);
+    }
+
+    #[test]
+    fn repair_needs_floor_to_be_enterable() {
+        let mut mapgen = MapGenerator2D::new(20, 10).with_connectivity_repair([MoveType::Swim]);
+
+        assert!(mapgen.generate(FloorGenAlg::Basic).is_err());
+    }
 }
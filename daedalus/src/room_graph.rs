@@ -0,0 +1,342 @@
+//! Module containing the graph of which rooms of a level connect to which
+
+use std::collections::VecDeque;
+
+use bracket_geometry::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "petgraph")]
+use petgraph::graph::{NodeIndex, UnGraph};
+
+/// A connection between two rooms of a [`RoomGraph`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomEdge {
+    rooms: (usize, usize),
+    at: Point,
+}
+
+impl RoomEdge {
+    /// Getter for the rooms joined by the edge, lowest index first
+    pub fn rooms(&self) -> (usize, usize) {
+        self.rooms
+    }
+
+    /// Getter for the point where the rooms join. For rooms attached to each
+    /// other, it is the connection in their walls, where a door can go.
+    pub fn at(&self) -> Point {
+        self.at
+    }
+
+    /// The other room of the edge, if it touches a given room
+    pub fn other(&self, room: usize) -> Option<usize> {
+        match self.rooms {
+            (a, b) if a == room => Some(b),
+            (a, b) if b == room => Some(a),
+            _ => None,
+        }
+    }
+}
+
+/// Graph of the rooms of a level, with an edge wherever two rooms join.
+///
+/// Rooms are numbered as in [`GeneratedLevel::rooms`](crate::prelude::GeneratedLevel::rooms),
+/// and two rooms share at most one edge. The start and exit rooms are the
+/// rooms of the spawn and exit points of the level, if they are in a room.
+///
+/// Corridors dug by the connectivity repair are not rooms, so rooms they join
+/// are not connected in the graph.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50).with_seed(3);
+/// let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+/// let graph = level.room_graph();
+///
+/// let critical = graph.critical_path().unwrap();
+/// assert_eq!(critical.first(), graph.start().as_ref());
+/// assert_eq!(critical.last(), graph.exit().as_ref());
+///
+/// // dead ends are off the critical path, good places for side content
+/// for room in graph.dead_ends() {
+///     assert!(!critical.contains(&room));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoomGraph {
+    room_count: usize,
+    edges: Vec<RoomEdge>,
+    start: Option<usize>,
+    exit: Option<usize>,
+}
+
+impl RoomGraph {
+    /// Constructs the graph of a number of rooms from the points where they
+    /// join. Only the first join between each pair of rooms is kept.
+    pub(crate) fn new(room_count: usize, links: &[(usize, usize, Point)]) -> RoomGraph {
+        let mut edges: Vec<RoomEdge> = vec![];
+
+        for &(a, b, at) in links {
+            let rooms = (a.min(b), a.max(b));
+            if a == b || rooms.1 >= room_count || edges.iter().any(|e| e.rooms == rooms) {
+                continue;
+            }
+            edges.push(RoomEdge { rooms, at });
+        }
+
+        RoomGraph {
+            room_count,
+            edges,
+            start: None,
+            exit: None,
+        }
+    }
+
+    /// Sets the start and exit rooms
+    pub(crate) fn with_ends(mut self, start: Option<usize>, exit: Option<usize>) -> RoomGraph {
+        self.start = start;
+        self.exit = exit;
+        self
+    }
+
+    /// Number of rooms in the graph
+    pub fn room_count(&self) -> usize {
+        self.room_count
+    }
+
+    /// Getter for the edges, in the order the rooms were joined
+    pub fn edges(&self) -> &Vec<RoomEdge> {
+        &self.edges
+    }
+
+    /// Getter for the room of the spawn point
+    pub fn start(&self) -> Option<usize> {
+        self.start
+    }
+
+    /// Getter for the room of the exit point
+    pub fn exit(&self) -> Option<usize> {
+        self.exit
+    }
+
+    /// The rooms joined to a room, in order
+    pub fn neighbors(&self, room: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.edges.iter().filter_map(|e| e.other(room)).collect();
+        neighbors.sort_unstable();
+        neighbors
+    }
+
+    /// The edge between two rooms, if they are joined
+    pub fn edge_between(&self, a: usize, b: usize) -> Option<&RoomEdge> {
+        self.edges.iter().find(|e| e.rooms == (a.min(b), a.max(b)))
+    }
+
+    /// Number of steps from a room to every other room, going from room to
+    /// room, or `None` for rooms that can't be reached from it
+    pub fn depths_from(&self, room: usize) -> Vec<Option<usize>> {
+        self.search(room)
+            .into_iter()
+            .map(|(depth, _)| depth)
+            .collect()
+    }
+
+    /// Number of steps from the start room to a room
+    pub fn depth(&self, room: usize) -> Option<usize> {
+        self.start
+            .and_then(|start| self.depths_from(start).get(room).copied().flatten())
+    }
+
+    /// The rooms on the shortest way from one room to another, both included
+    pub fn path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        if from >= self.room_count || to >= self.room_count {
+            return None;
+        }
+
+        let found = self.search(from);
+        found[to].0?;
+
+        let mut path = vec![to];
+        while let Some(previous) = found[*path.last().unwrap()].1 {
+            path.push(previous);
+        }
+        path.reverse();
+
+        Some(path)
+    }
+
+    /// The rooms on the shortest way from the start room to the exit room.
+    /// Every room off this path is optional to a player.
+    pub fn critical_path(&self) -> Option<Vec<usize>> {
+        self.path(self.start?, self.exit?)
+    }
+
+    /// Rooms joined to only one other room, other than the start and exit
+    pub fn dead_ends(&self) -> Vec<usize> {
+        (0..self.room_count)
+            .filter(|&room| Some(room) != self.start && Some(room) != self.exit)
+            .filter(|&room| self.neighbors(room).len() == 1)
+            .collect()
+    }
+
+    /// Breadth first search from a room, returning the depth of every room
+    /// and the room it was reached from
+    fn search(&self, from: usize) -> Vec<(Option<usize>, Option<usize>)> {
+        let mut found = vec![(None, None); self.room_count];
+        if from >= self.room_count {
+            return found;
+        }
+
+        found[from].0 = Some(0);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(room) = queue.pop_front() {
+            let depth = found[room].0.unwrap_or(0);
+            for next in self.neighbors(room) {
+                if found[next].0.is_none() {
+                    found[next] = (Some(depth + 1), Some(room));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Writes the graph in the DOT language of Graphviz. The start and exit
+    /// rooms are labelled, and the critical path is drawn in bold.
+    pub fn to_dot(&self) -> String {
+        let critical = self.critical_path().unwrap_or_default();
+        let on_critical =
+            |a: usize, b: usize| critical.windows(2).any(|w| w == [a, b] || w == [b, a]);
+
+        let mut out = String::from("graph rooms {\n");
+
+        for room in 0..self.room_count {
+            let mut label = format!("room {}", room);
+            if Some(room) == self.start {
+                label += "\\nstart";
+            }
+            if Some(room) == self.exit {
+                label += "\\nexit";
+            }
+            out += &format!("    {} [label=\"{}\"];\n", room, label);
+        }
+
+        for edge in self.edges.iter() {
+            let (a, b) = edge.rooms;
+            let style = if on_critical(a, b) {
+                ", style=bold"
+            } else {
+                ""
+            };
+            out += &format!(
+                "    {} -- {} [label=\"{}, {}\"{}];\n",
+                a, b, edge.at.x, edge.at.y, style
+            );
+        }
+
+        out += "}\n";
+        out
+    }
+
+    /// Converts the graph to a petgraph graph, with the room numbers as node
+    /// weights and the points where rooms join as edge weights. Node indices
+    /// are the room numbers.
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> UnGraph<usize, Point> {
+        let mut graph = UnGraph::with_capacity(self.room_count, self.edges.len());
+
+        for room in 0..self.room_count {
+            graph.add_node(room);
+        }
+        for edge in self.edges.iter() {
+            let (a, b) = edge.rooms;
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), edge.at);
+        }
+
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 0 - 1 - 2 - 3, with 4 off 1 and 5 off 4
+    fn prepare_graph() -> RoomGraph {
+        let links = [
+            (0, 1, Point::new(1, 0)),
+            (1, 2, Point::new(2, 0)),
+            (2, 3, Point::new(3, 0)),
+            (1, 4, Point::new(1, 1)),
+            (4, 5, Point::new(1, 2)),
+            (2, 1, Point::new(9, 9)),
+        ];
+        RoomGraph::new(6, &links).with_ends(Some(0), Some(3))
+    }
+
+    #[test]
+    fn repeated_links_are_one_edge() {
+        let graph = prepare_graph();
+
+        assert_eq!(graph.edges().len(), 5);
+        assert_eq!(graph.edge_between(2, 1).unwrap().at(), Point::new(2, 0));
+        assert_eq!(graph.neighbors(1), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn critical_path_and_depths() {
+        let graph = prepare_graph();
+
+        assert_eq!(graph.critical_path(), Some(vec![0, 1, 2, 3]));
+        assert_eq!(graph.path(5, 3), Some(vec![5, 4, 1, 2, 3]));
+        assert_eq!(graph.depth(5), Some(3));
+        assert_eq!(graph.dead_ends(), vec![5]);
+    }
+
+    #[test]
+    fn unreachable_rooms_have_no_path() {
+        let graph = RoomGraph::new(3, &[(0, 1, Point::new(0, 0))]).with_ends(Some(0), Some(2));
+
+        assert_eq!(graph.critical_path(), None);
+        assert_eq!(graph.depths_from(0), vec![Some(0), Some(1), None]);
+    }
+
+    #[test]
+    fn generated_rooms_are_all_joined() {
+        use crate::prelude::{BspSettings, FloorGenAlg, MapGenerator2D};
+
+        for seed in 0..4 {
+            let mut mapgen = MapGenerator2D::new(80, 50).with_seed(seed);
+            for method in [FloorGenAlg::Basic, FloorGenAlg::Bsp(BspSettings::new())] {
+                let level = mapgen.generate(method).unwrap();
+                let graph = level.room_graph();
+
+                assert_eq!(graph.room_count(), level.rooms().len());
+                assert!(graph.depths_from(0).iter().all(Option::is_some));
+                assert!(graph.critical_path().is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn dot_marks_the_critical_path() {
+        let dot = prepare_graph().to_dot();
+
+        assert!(dot.starts_with("graph rooms {\n"));
+        assert!(dot.contains("    0 [label=\"room 0\\nstart\"];\n"));
+        assert!(dot.contains("    2 -- 3 [label=\"3, 0\", style=bold];\n"));
+        assert!(dot.contains("    1 -- 4 [label=\"1, 1\"];\n"));
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn petgraph_has_every_room() {
+        let graph = prepare_graph().to_petgraph();
+
+        assert_eq!(graph.node_count(), 6);
+        assert_eq!(graph.edge_count(), 5);
+        assert_eq!(graph[NodeIndex::new(4)], 4);
+    }
+}
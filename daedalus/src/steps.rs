@@ -0,0 +1,544 @@
+//! Module containing the steps a [`MapGenerator2D`] can chain together
+
+use labyrinth_map::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::BuilderError;
+use crate::genalgs;
+use crate::genalgs::bsp::BspSettings;
+use crate::genalgs::caves::CaveSettings;
+use crate::genalgs::doors::DoorSettings;
+use crate::genalgs::drunkards::DrunkardSettings;
+use crate::genalgs::locks::LockSettings;
+use crate::genalgs::mazes::MazeSettings;
+use crate::genalgs::rooms::Prefab;
+use crate::genalgs::wfc::WfcSettings;
+use crate::map_generators::{FloorGenAlg, MapGenerator2D, MapRng};
+
+/// A step of map generation, which changes the map, rooms or metadata of a
+/// [`MapGenerator2D`].
+///
+/// Every level is generated by a list of steps: the algorithm, the steps added
+/// with [`MapGenerator2D::with_step`], then the post-processors of the
+/// Generator. Every algorithm and post-processor of the crate is a step,
+/// through its settings, and new steps only have to implement this trait.
+/// Steps that add rooms should call [`MapGenerator2D::update_rooms`] to put
+/// them on the map.
+///
+/// # Example Usage
+/// ```rust
+/// use daedalus::prelude::*;
+/// use bracket_pathfinding::prelude::*;
+///
+/// /// Floods the floor along the middle row of the map
+/// struct Flood;
+///
+/// impl MapStep for Flood {
+///     fn run(&self, mapgen: &mut MapGenerator2D, _: &mut MapRng) -> Result<(), BuilderError> {
+///         let dims = *mapgen.dimensions();
+///         let map = mapgen.map_mut();
+///         for x in 0..dims.x {
+///             let pt = Point::new(x, dims.y / 2);
+///             if map.can_enter(pt, &[MoveType::Walk]) {
+///                 map.set_tile_at(pt, Tile::water());
+///             }
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut mapgen = MapGenerator2D::new(80, 50)
+///     .without_connectivity_repair()
+///     .with_step(CaveSettings::new().with_iterations(2))
+///     .with_step(CullUnreachable::new([MoveType::Walk]))
+///     .with_step(Flood)
+///     .with_step(DoorSettings::new());
+///
+/// let level = mapgen.generate(FloorGenAlg::Bsp(BspSettings::new())).unwrap();
+/// assert!(level.map().iter().any(|tile| tile.kind() == "water"));
+/// assert!(matches!(level.steps()[3], StepSettings::Custom(_)));
+/// ```
+pub trait MapStep {
+    /// Runs the step on a Generator, drawing any randomness from the `rng` it
+    /// is handed. That is the rng of the Generator, moved out for the run, so
+    /// steps must not draw from [`MapGenerator2D::rng_mut`] instead.
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError>;
+
+    /// The settings the step is recorded with in a [`GeneratedLevel`]. Steps
+    /// from outside the crate are recorded by the name of their type.
+    ///
+    /// [`GeneratedLevel`]: crate::prelude::GeneratedLevel
+    fn settings(&self) -> StepSettings {
+        StepSettings::Custom(std::any::type_name::<Self>().to_string())
+    }
+}
+
+/// The settings of a step, as recorded in a
+/// [`GeneratedLevel`](crate::prelude::GeneratedLevel).
+///
+/// Every step of the crate can be run again from its settings, so levels can
+/// be generated again from the steps they recorded.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StepSettings {
+    /// One of the algorithms of [`FloorGenAlg`]
+    Algorithm(FloorGenAlg),
+
+    /// Tunnels between the regions of the map
+    ConnectivityRepair(ConnectivityRepair),
+
+    /// Fills in the regions that can't be reached
+    CullUnreachable(CullUnreachable),
+
+    /// Stamps a prefab onto the map
+    Prefab(Prefab),
+
+    /// Places doors where rooms connect
+    Doors(DoorSettings),
+
+    /// Builds a lock-and-key puzzle
+    Locks(LockSettings),
+
+    /// A step from outside the crate, by the name of its type. It can't be
+    /// run again from its settings.
+    Custom(String),
+}
+
+impl MapStep for StepSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        match self {
+            StepSettings::Algorithm(algorithm) => algorithm.run(mapgen, rng),
+            StepSettings::ConnectivityRepair(repair) => repair.run(mapgen, rng),
+            StepSettings::CullUnreachable(cull) => cull.run(mapgen, rng),
+            StepSettings::Prefab(prefab) => prefab.run(mapgen, rng),
+            StepSettings::Doors(doors) => doors.run(mapgen, rng),
+            StepSettings::Locks(locks) => locks.run(mapgen, rng),
+            StepSettings::Custom(name) => Err(BuilderError::BuildError(format!(
+                "The step {} can't be run from its settings",
+                name
+            ))),
+        }
+    }
+
+    fn settings(&self) -> StepSettings {
+        self.clone()
+    }
+}
+
+/// Step that runs one of the algorithms of [`FloorGenAlg`]
+impl MapStep for FloorGenAlg {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        match self {
+            FloorGenAlg::Basic => {
+                genalgs::build_rooms_and_corridors(mapgen, rng);
+                Ok(())
+            }
+            FloorGenAlg::Bsp(settings) => settings.run(mapgen, rng),
+            FloorGenAlg::Caves(settings) => settings.run(mapgen, rng),
+            FloorGenAlg::DrunkardsWalk(settings) => settings.run(mapgen, rng),
+            FloorGenAlg::Maze(settings) => settings.run(mapgen, rng),
+            FloorGenAlg::Wfc(settings) => settings.run(mapgen, rng),
+        }
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Algorithm(self.clone())
+    }
+}
+
+/// Step that partitions the map and puts a room in every leaf
+impl MapStep for BspSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::bsp::build_bsp(mapgen, self, rng);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Algorithm(FloorGenAlg::Bsp(self.clone()))
+    }
+}
+
+/// Step that grows caves over the whole map
+impl MapStep for CaveSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::caves::build_caves(mapgen, self, rng);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Algorithm(FloorGenAlg::Caves(self.clone()))
+    }
+}
+
+/// Step that digs tunnels with random walkers, on top of the map so far
+impl MapStep for DrunkardSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::drunkards::build_drunkards_walk(mapgen, self, rng);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Algorithm(FloorGenAlg::DrunkardsWalk(self.clone()))
+    }
+}
+
+/// Step that carves a maze, on top of the map so far
+impl MapStep for MazeSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::mazes::build_maze(mapgen, self, rng);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Algorithm(FloorGenAlg::Maze(self.clone()))
+    }
+}
+
+/// Step that replaces the map with one made of the patterns of a sample
+impl MapStep for WfcSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::wfc::build_wfc(mapgen, self, rng)
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Algorithm(FloorGenAlg::Wfc(self.clone()))
+    }
+}
+
+/// Step that places doors at the connections between rooms
+impl MapStep for DoorSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::doors::place_doors(mapgen, self, rng);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Doors(self.clone())
+    }
+}
+
+/// Step that stamps a prefab onto the map, if there is room for it
+impl MapStep for Prefab {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        let placed = genalgs::prefabs::place_prefabs(mapgen, std::slice::from_ref(self), rng);
+        mapgen.add_placed_prefabs(placed);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Prefab(self.clone())
+    }
+}
+
+/// Step that builds a lock-and-key puzzle over the rooms placed so far. The
+/// level keeps the spawn and exit the puzzle was made for, even if locking
+/// doors moves the tile furthest from the spawn.
+impl MapStep for LockSettings {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        let mut level = mapgen.level();
+        let puzzle = genalgs::locks::place_locks(&mut level, self, rng);
+        mapgen.set_lock_puzzle(puzzle, &level);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::Locks(self.clone())
+    }
+}
+
+/// Step that tunnels corridors between the regions of the map, until it is
+/// fully connected for some movement types.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnectivityRepair {
+    move_types: Vec<MoveType>,
+}
+
+impl ConnectivityRepair {
+    /// Creates a repair step for the given movement types
+    pub fn new<T: Into<Vec<MoveType>>>(move_types: T) -> ConnectivityRepair {
+        ConnectivityRepair {
+            move_types: move_types.into(),
+        }
+    }
+}
+
+impl MapStep for ConnectivityRepair {
+    fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::connect_regions(mapgen, &self.move_types, rng)
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::ConnectivityRepair(self.clone())
+    }
+}
+
+/// Step that fills in every region of the map but the largest one, for some
+/// movement types, so that nothing is left that can't be reached.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CullUnreachable {
+    move_types: Vec<MoveType>,
+}
+
+impl CullUnreachable {
+    /// Creates a culling step for the given movement types
+    pub fn new<T: Into<Vec<MoveType>>>(move_types: T) -> CullUnreachable {
+        CullUnreachable {
+            move_types: move_types.into(),
+        }
+    }
+}
+
+impl MapStep for CullUnreachable {
+    fn run(&self, mapgen: &mut MapGenerator2D, _: &mut MapRng) -> Result<(), BuilderError> {
+        genalgs::cull_unreachable(mapgen.map_mut(), &self.move_types);
+        Ok(())
+    }
+
+    fn settings(&self) -> StepSettings {
+        StepSettings::CullUnreachable(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::*;
+    use bracket_pathfinding::prelude::*;
+    use std::cell::Cell;
+    use std::panic::AssertUnwindSafe;
+    use std::rc::Rc;
+
+    /// Turns some of the floor into water
+    struct Puddles;
+
+    impl MapStep for Puddles {
+        fn run(&self, mapgen: &mut MapGenerator2D, rng: &mut MapRng) -> Result<(), BuilderError> {
+            use rand::RngExt;
+
+            let map = mapgen.map_mut();
+            for idx in 0..map.size() {
+                let pt = map.index_to_point2d(idx);
+                if map.tile_kind(pt) == "floor" && rng.random_bool(0.1) {
+                    map.set_tile_at(pt, Tile::water());
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn single_steps_match_the_algorithms() {
+        use rand::SeedableRng;
+
+        for method in [
+            FloorGenAlg::Basic,
+            FloorGenAlg::Bsp(BspSettings::new()),
+            FloorGenAlg::Caves(CaveSettings::new()),
+        ] {
+            let mut mapgen = MapGenerator2D::new(60, 40).without_connectivity_repair();
+            let level = mapgen.generate_seeded(method.clone(), 4).unwrap();
+            assert_eq!(
+                level.steps(),
+                &vec![StepSettings::Algorithm(method.clone())]
+            );
+
+            // nothing runs but the algorithm itself
+            let mut direct = MapGenerator2D::new(60, 40);
+            direct.flush_map();
+            let mut rng = MapRng::seed_from_u64(4);
+            match &method {
+                FloorGenAlg::Basic => genalgs::build_rooms_and_corridors(&mut direct, &mut rng),
+                FloorGenAlg::Bsp(settings) => {
+                    genalgs::bsp::build_bsp(&mut direct, settings, &mut rng)
+                }
+                FloorGenAlg::Caves(settings) => {
+                    genalgs::caves::build_caves(&mut direct, settings, &mut rng)
+                }
+                _ => unreachable!(),
+            }
+
+            assert_eq!(level.map(), direct.map());
+        }
+    }
+
+    #[test]
+    fn post_processors_are_steps() {
+        let vault = Prefab::from_ascii("vault", "#?#\n#.#\n###").unwrap();
+        let mut mapgen = MapGenerator2D::new(60, 40)
+            .with_seed(3)
+            .with_prefab(vault.clone())
+            .with_doors(DoorSettings::new())
+            .with_locks(LockSettings::new());
+
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+
+        assert_eq!(
+            level.steps(),
+            &vec![
+                StepSettings::Algorithm(FloorGenAlg::Basic),
+                StepSettings::ConnectivityRepair(ConnectivityRepair::new([MoveType::Walk])),
+                StepSettings::Prefab(vault),
+                StepSettings::Doors(DoorSettings::new()),
+                StepSettings::Locks(LockSettings::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_chain_in_order() {
+        let mut mapgen = MapGenerator2D::new(60, 40)
+            .with_seed(9)
+            .without_connectivity_repair()
+            .with_step(
+                DrunkardSettings::new()
+                    .with_walkers(5)
+                    .with_floor_percent(1.0),
+            )
+            .with_step(CullUnreachable::new([MoveType::Walk]))
+            .with_step(Puddles)
+            .with_step(ConnectivityRepair::new([MoveType::Walk]));
+
+        let level = mapgen
+            .generate(FloorGenAlg::Bsp(BspSettings::new()))
+            .unwrap();
+        let map = level.map();
+
+        assert!(!level.rooms().is_empty());
+        assert!(map.iter().any(|tile| tile.kind() == "water"));
+        assert!(map.is_fully_connected([MoveType::Walk]));
+
+        // the same seed runs the same steps the same way
+        let again = mapgen
+            .generate_seeded(level.algorithm().clone(), level.seed())
+            .unwrap();
+        assert_eq!(level, again);
+
+        // but a step from outside the crate can't be run from its settings
+        assert!(matches!(level.steps()[3], StepSettings::Custom(_)));
+        assert!(level.regenerate().is_err());
+    }
+
+    /// Draws a number from the Generator, instead of the rng it is handed
+    struct Draws(Rc<Cell<u64>>);
+
+    impl MapStep for Draws {
+        fn run(&self, mapgen: &mut MapGenerator2D, _: &mut MapRng) -> Result<(), BuilderError> {
+            use rand::RngExt;
+
+            self.0.set(mapgen.rng_mut().random());
+            Ok(())
+        }
+    }
+
+    /// Panics the first time it runs
+    struct Panics(Cell<bool>);
+
+    impl MapStep for Panics {
+        fn run(&self, _: &mut MapGenerator2D, _: &mut MapRng) -> Result<(), BuilderError> {
+            if !self.0.replace(true) {
+                panic!("first run");
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn steps_are_handed_the_only_rng() {
+        use rand::{RngExt, SeedableRng};
+
+        let drawn = Rc::new(Cell::new(0));
+        let mut mapgen = MapGenerator2D::new(20, 20)
+            .with_seed(2)
+            .with_step(Draws(drawn.clone()));
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+
+        // the Generator holds a stand-in, not a copy of the rng of the level
+        let mut rng = MapRng::seed_from_u64(level.seed());
+        assert_ne!(drawn.get(), rng.random::<u64>());
+    }
+
+    #[test]
+    fn steps_outlive_a_panicking_step() {
+        let mut mapgen = MapGenerator2D::new(20, 20)
+            .with_seed(2)
+            .with_step(Puddles)
+            .with_step(Panics(Cell::new(false)));
+
+        let result =
+            std::panic::catch_unwind(AssertUnwindSafe(|| mapgen.generate(FloorGenAlg::Basic)));
+        assert!(result.is_err());
+
+        let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+        assert!(matches!(level.steps()[1], StepSettings::Custom(_)));
+        assert!(matches!(level.steps()[2], StepSettings::Custom(_)));
+    }
+
+    #[test]
+    fn culling_leaves_one_region() {
+        let mut mapgen = MapGenerator2D::new(60, 40)
+            .with_seed(1)
+            .without_connectivity_repair()
+            .with_step(Puddles)
+            .with_step(CullUnreachable::new([MoveType::Walk]));
+
+        let level = mapgen
+            .generate(FloorGenAlg::Caves(
+                CaveSettings::new().with_regions(CaveRegions::ConnectAll),
+            ))
+            .unwrap();
+
+        assert!(level.map().is_fully_connected([MoveType::Walk]));
+    }
+
+    #[test]
+    fn locks_are_a_step() {
+        let mut locked = 0;
+
+        for seed in 0..3 {
+            let mut mapgen = MapGenerator2D::new(60, 40)
+                .with_seed(seed)
+                .with_step(ConnectivityRepair::new([MoveType::Walk]))
+                .with_step(LockSettings::new().with_locks(2))
+                .without_connectivity_repair();
+            let level = mapgen.generate(FloorGenAlg::Basic).unwrap();
+
+            let puzzle = level.lock_puzzle().unwrap();
+            assert!(puzzle.is_solvable(&level));
+            locked += puzzle.locks().len();
+        }
+
+        assert!(locked > 0);
+    }
+
+    #[test]
+    fn pipelines_regenerate_from_their_settings() {
+        let mut mapgen = MapGenerator2D::new(60, 40)
+            .with_seed(6)
+            .with_step(CaveSettings::new().with_iterations(2))
+            .with_step(CullUnreachable::new([MoveType::Walk]))
+            .with_doors(DoorSettings::new())
+            .with_locks(LockSettings::new());
+        let level = mapgen
+            .generate(FloorGenAlg::Bsp(BspSettings::new()))
+            .unwrap();
+
+        let raw = ron::to_string(&level).unwrap();
+        let loaded: GeneratedLevel = ron::from_str(&raw).unwrap();
+
+        assert_eq!(loaded.regenerate().unwrap(), level);
+    }
+
+    #[test]
+    fn levels_without_steps_are_errors() {
+        let level = MapGenerator2D::new(60, 40)
+            .generate(FloorGenAlg::Basic)
+            .unwrap();
+        let mut raw = ron::to_string(&level).unwrap();
+        let start = raw.find("steps:").unwrap();
+        raw.replace_range(start.., ")");
+        let loaded: GeneratedLevel = ron::from_str(&raw).unwrap();
+
+        assert!(loaded.steps().is_empty());
+        assert!(loaded.regenerate().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Module for the error type of the crate
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use bracket_pathfinding::prelude::{Point, Point3};
+
+use crate::map_objects::MoveType;
+
+/// Errors returned by the public API of labyrinth_map.
+///
+/// # Example Usage
+/// ```rust
+/// use labyrinth_map::prelude::*;
+///
+/// match TileBuilder::new().with_kind("ice").build() {
+///     Err(LabyrinthError::BuilderIncomplete(field)) => assert_eq!(field, "opaque"),
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LabyrinthError {
+    /// Reading or writing a file failed
+    Io(io::Error),
+
+    /// A map could not be parsed from its text representation
+    Parse(String),
+
+    /// A map could not be turned into its text representation
+    Serialize(String),
+
+    /// A symbol in a mapstring has no matching tile
+    UnknownGlyph {
+        /// The symbol that was not found
+        glyph: String,
+        /// Where it was found, as (column, row)
+        position: Point,
+    },
+
+    /// A tile of the map has nothing to be drawn as in another map format
+    UnmappedTile {
+        /// Kind of the tile
+        kind: String,
+        /// Where it was found, as (column, row)
+        position: Point,
+    },
+
+    /// The rows of a mapstring are not all the same length
+    RaggedRows {
+        /// The first row with the wrong length
+        row: usize,
+        /// Length of the first row
+        expected: usize,
+        /// Length of the offending row
+        found: usize,
+    },
+
+    /// A text map could not be read, at a given line and column (from 1)
+    Syntax {
+        /// Line of the error
+        line: usize,
+        /// Column of the error
+        column: usize,
+        /// What went wrong
+        message: String,
+    },
+
+    /// A [`TileBuilder`](crate::prelude::TileBuilder) was built without a
+    /// required field
+    BuilderIncomplete(&'static str),
+
+    /// A tile was given a cost that isn't finite and positive
+    InvalidCost {
+        /// The movement type the cost was for
+        move_type: MoveType,
+        /// The offending cost
+        cost: f32,
+    },
+
+    /// A [`Labyrinth3D`](crate::prelude::Labyrinth3D) was made without levels
+    NoLevels,
+
+    /// A level does not have the same dimensions as the rest of a
+    /// [`Labyrinth3D`](crate::prelude::Labyrinth3D)
+    LevelSizeMismatch {
+        /// Dimensions of the map
+        expected: Point,
+        /// Dimensions of the level
+        found: Point,
+    },
+
+    /// A [`Link`](crate::prelude::Link) has an end outside of the map
+    LinkOutOfBounds(Point3),
+}
+
+impl fmt::Display for LabyrinthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LabyrinthError::Io(err) => write!(f, "I/O error: {}", err),
+            LabyrinthError::Parse(msg) => write!(f, "Deserialize failed: {}", msg),
+            LabyrinthError::Serialize(msg) => write!(f, "Serialize failed: {}", msg),
+            LabyrinthError::UnknownGlyph { glyph, position } => write!(
+                f,
+                "Symbol {:?} at row {}, column {} is not in the tiledict",
+                glyph, position.y, position.x
+            ),
+            LabyrinthError::UnmappedTile { kind, position } => write!(
+                f,
+                "Tile {:?} at row {}, column {} has no mapping",
+                kind, position.y, position.x
+            ),
+            LabyrinthError::RaggedRows {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Row lengths do not match: row {} has length {}, expected {}",
+                row, found, expected
+            ),
+            LabyrinthError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "Line {}, column {}: {}", line, column, message),
+            LabyrinthError::BuilderIncomplete(field) => {
+                write!(f, "Builder not fully initialized: missing {}", field)
+            }
+            LabyrinthError::InvalidCost { move_type, cost } => write!(
+                f,
+                "Tile costs must be finite and positive, got {} for {:?}",
+                cost, move_type
+            ),
+            LabyrinthError::NoLevels => write!(f, "Labyrinth3D needs at least one level"),
+            LabyrinthError::LevelSizeMismatch { expected, found } => write!(
+                f,
+                "Level dimensions do not match: expected {:?}, found {:?}",
+                expected, found
+            ),
+            LabyrinthError::LinkOutOfBounds(loc) => {
+                write!(f, "Link end {:?} is out of bounds", loc)
+            }
+        }
+    }
+}
+
+impl Error for LabyrinthError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LabyrinthError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LabyrinthError {
+    fn from(err: io::Error) -> Self {
+        LabyrinthError::Io(err)
+    }
+}
@@ -4,9 +4,11 @@
 
 #![warn(missing_docs)]
 
+mod errors;
 mod map_objects;
 
 /// Prelude for re-exporting all important structs from the crate.
 pub mod prelude {
+    pub use super::errors::LabyrinthError;
     pub use super::map_objects::*;
 }
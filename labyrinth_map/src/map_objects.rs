@@ -1,23 +1,52 @@
 //! Module for map objects
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
 use bracket_pathfinding::prelude::*;
 
 #[cfg(feature = "serialization")]
 mod labyrinth_serialization;
 
+mod labyrinth_ascii;
+
+#[cfg(feature = "rexpaint")]
+mod labyrinth_rexpaint;
+#[cfg(feature = "rexpaint")]
+pub use labyrinth_rexpaint::XpCell;
+
+#[cfg(feature = "render")]
+mod labyrinth_render;
+#[cfg(feature = "render")]
+pub use labyrinth_render::*;
+
 mod tiles;
 pub use tiles::MoveType;
 pub use tiles::*;
 
+mod movement;
+pub use movement::*;
+
+mod visibility;
+
+mod connectivity;
+pub use connectivity::*;
+
+mod zones;
+
+mod doors;
+
+mod labyrinth3d;
+pub use labyrinth3d::*;
+
 // TODO: Better Map struct documentation
 /// Labyrinth2D struct, the output of the MapGenerator2D.
 ///
 /// Implements [`Algorithm2D`] and [`BaseMap`] traits from bracket-pathfinding,
 /// which allows for bracket-lib pathfinding algorithms.
 /// Also comes with built-in implementations for pathfinding for alternate
-/// movement methods (swim and fly).
+/// movement methods (swim and fly), through a [`MovementView`] of the map.
+/// Used directly as a [`BaseMap`], the map is pathed as if walking.
 /// ```rust
 /// use labyrinth_map::prelude::*;
 ///
@@ -29,8 +58,16 @@ pub struct Labyrinth2D {
     tiles: Vec<Tile>,
     dimensions: Point,
 
-    // Internal state vector for pathfinding filters
-    _filter: Vec<MoveType>,
+    // Default neighborhood used for pathfinding queries
+    neighborhood: Neighborhood,
+
+    // The zone of every tile, if any, and the label of every zone
+    zones: Vec<Option<usize>>,
+    zone_labels: Vec<String>,
+
+    // Cheapest tile to walk onto, for the A* heuristic of the map itself.
+    // Cleared whenever the tiles can change.
+    walk_min_cost: OnceLock<f32>,
 }
 
 // Implementing Algorithm2D from bracket-pathfinding on Labyrinth2D
@@ -47,40 +84,22 @@ impl BaseMap for Labyrinth2D {
     }
 
     fn get_available_exits(&self, _idx: usize) -> SmallVec<[(usize, f32); 10]> {
-        let start = self.index_to_point2d(_idx);
-        let deltas = [
-            Point::new(-1, 0),
-            Point::new(0, -1),
-            Point::new(1, 0),
-            Point::new(0, 1),
-        ];
-
-        deltas
-            .iter()
-            // apply each delta to the point
-            .map(|&diff| start + diff)
-            // filter to only points in map bounds
-            .filter(|&pt| self.in_bounds(pt))
-            // // filter to only tiles that are walkable
-            .filter(|&pt| self.can_enter(pt, &self._filter))
-            // map points -> vector indices
-            .map(|pt| self.point2d_to_index(pt))
-            // package into final struct
-            // TODO: Make the cost variable (have can_enter return (bool, float)?)
-            .map(|pos| (pos, 1.0))
-            // finally, collect into the final SmallVec
-            .collect::<SmallVec<[(_, _); 10]>>()
+        MovementView::walking(self).get_available_exits(_idx)
     }
 
     fn get_pathing_distance(&self, _idx1: usize, _idx2: usize) -> f32 {
-        DistanceAlg::Pythagoras
-            .distance2d(self.index_to_point2d(_idx1), self.index_to_point2d(_idx2))
+        // same heuristic as a walking view, scaled down for cheap tiles
+        MovementView::walking(self).get_pathing_distance(_idx1, _idx2)
     }
 }
 
 impl PartialEq for Labyrinth2D {
     fn eq(&self, other: &Self) -> bool {
-        self.tiles == other.tiles && self.dimensions == other.dimensions
+        self.tiles == other.tiles
+            && self.dimensions == other.dimensions
+            && self.neighborhood == other.neighborhood
+            && self.zones == other.zones
+            && self.zone_labels == other.zone_labels
     }
 }
 
@@ -90,22 +109,20 @@ impl Labyrinth2D {
     ///
     /// Initial Tiles are all walls.
     pub fn new(width: usize, height: usize) -> Labyrinth2D {
-        Labyrinth2D {
-            tiles: vec![Default::default(); width * height],
-            dimensions: Point::new(width, height),
-            _filter: vec![],
-        }
+        Labyrinth2D::from_tiles(
+            vec![Default::default(); width * height],
+            Point::new(width, height),
+        )
     }
 
     /// Constructs a new Labyrinth with the passed width and height values.
     ///
     /// Initial Tiles are all floors.
     pub fn new_empty(width: usize, height: usize) -> Labyrinth2D {
-        Labyrinth2D {
-            tiles: vec![Tile::floor(); width * height],
-            dimensions: Point::new(width, height),
-            _filter: vec![],
-        }
+        Labyrinth2D::from_tiles(
+            vec![Tile::floor(); width * height],
+            Point::new(width, height),
+        )
     }
 
     /// Constructus a new Labyrinth with the passed width and height values.
@@ -129,10 +146,18 @@ impl Labyrinth2D {
             })
             .collect();
 
+        Labyrinth2D::from_tiles(tiles, Point::new(width, height))
+    }
+
+    /// Constructs a Labyrinth from its tiles, row by row, without any zones
+    fn from_tiles(tiles: Vec<Tile>, dimensions: Point) -> Labyrinth2D {
         Labyrinth2D {
+            zones: vec![None; tiles.len()],
+            zone_labels: vec![],
             tiles,
-            dimensions: Point::new(width, height),
-            _filter: vec![],
+            dimensions,
+            neighborhood: Neighborhood::default(),
+            walk_min_cost: OnceLock::new(),
         }
     }
 
@@ -167,18 +192,56 @@ impl Labyrinth2D {
         self.tile_at(loc).can_enter(move_types)
     }
 
+    /// Getter for the default [`Neighborhood`] used for pathfinding
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// Sets the default [`Neighborhood`] used for pathfinding
+    pub fn set_neighborhood(&mut self, neighborhood: Neighborhood) {
+        self.neighborhood = neighborhood;
+    }
+
+    /// Returns a [`MovementView`] of the [`Labyrinth2D`] for an entity with
+    /// the specified movement types. The view implements [`BaseMap`], so it
+    /// can be passed directly to bracket-lib pathfinding algorithms.
+    pub fn movement_view<T>(&self, move_types: T) -> MovementView<'_>
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        MovementView::new(self, move_types)
+    }
+
+    /// Cost of the cheapest tile to walk onto, and at most 1.0. Computed once,
+    /// until the tiles of the map change.
+    pub(crate) fn walk_min_cost(&self) -> f32 {
+        *self.walk_min_cost.get_or_init(|| {
+            self.iter()
+                .filter_map(|tile| tile.cost(&[MoveType::Walk]))
+                .fold(1.0, f32::min)
+        })
+    }
+
+    /// Returns the cost for an entity with the specified movement types to
+    /// enter the tile at a given [`Point`], or `None` if it can't be entered.
+    pub fn tile_cost<'a, T>(&self, loc: Point, move_types: T) -> Option<f32>
+    where
+        T: IntoIterator<Item = &'a MoveType>,
+    {
+        self.tile_at(loc).cost(move_types)
+    }
+
     /// Returns the neighbors of a [`Point`] on the [`Labyrinth2D`],
     /// given the usable move types. Takes a collection of [`MoveType`] that
     /// implements Into<Vec<Movetype>>
-    pub fn get_neighbors<T>(&mut self, loc: Point, move_types: T) -> Vec<Point>
+    pub fn get_neighbors<T>(&self, loc: Point, move_types: T) -> Vec<Point>
     where
         T: Into<Vec<MoveType>>,
     {
-        self._filter = move_types.into();
-
+        let view = self.movement_view(move_types);
         let idx = self.point2d_to_index(loc);
 
-        self.get_available_exits(idx)
+        view.get_available_exits(idx)
             .iter()
             .map(|(idx, _)| self.index_to_point2d(*idx))
             .collect()
@@ -187,49 +250,27 @@ impl Labyrinth2D {
     /// Find the path between two [`Points`](Point) for an entity with multiple
     /// movement types.
     // TODO: Examples here
-    // TODO: Try a version of this that doesn't require mutable access?
-    pub fn find_path<T>(&mut self, start: Point, end: Point, move_types: T) -> NavigationPath
+    pub fn find_path<T>(&self, start: Point, end: Point, move_types: T) -> NavigationPath
     where
         T: Into<Vec<MoveType>>,
     {
-        let move_types_vec = move_types.into();
-
-        // If the movetype is only walk, then pathfinding can be done on
-        // the Map as-is
-        if move_types_vec == vec![MoveType::Walk] || move_types_vec == vec![] {
-            self._filter = vec![MoveType::Walk];
-            // return self.find_path_walk(start, end);
-        } else {
-            // if it's not walk, then sort and assign as filter to the map
-            self._filter = move_types_vec;
-            self._filter.sort();
-        }
+        let view = self.movement_view(move_types);
 
-        let path = a_star_search(
+        a_star_search(
             self.point2d_to_index(start),
             self.point2d_to_index(end),
-            self,
-        );
-
-        self._filter.clear();
-        path
+            &view,
+        )
     }
 
     /// Returns Dijkstra map for a set of starting [`Points`](Point), given
     /// the movement types of the entity.
     // TODO: Examples here
-    pub fn dijkstra_map<T>(&mut self, starts: &[Point], move_types: T) -> DijkstraMap
+    pub fn dijkstra_map<T>(&self, starts: &[Point], move_types: T) -> DijkstraMap
     where
         T: Into<Vec<MoveType>>,
     {
-        let move_types_vec: Vec<MoveType> = move_types.into();
-        // if the MoveType is only walk, then it can be done on the map itself
-        if move_types_vec == [MoveType::Walk] || move_types_vec == [] {
-            self._filter = vec![MoveType::Walk];
-        } else {
-            self._filter = move_types_vec;
-            self._filter.sort();
-        }
+        let view = self.movement_view(move_types);
 
         let Point {
             x: size_x,
@@ -238,9 +279,7 @@ impl Labyrinth2D {
 
         let starts_idx: Vec<usize> = starts.iter().map(|&pt| self.point2d_to_index(pt)).collect();
 
-        let dmap = DijkstraMap::new(size_x, size_y, &starts_idx, self, 1024.0);
-        self._filter.clear();
-        dmap
+        DijkstraMap::new(size_x, size_y, &starts_idx, &view, 1024.0)
     }
 
     // ---------------- Map editing methods --------------
@@ -253,6 +292,7 @@ impl Labyrinth2D {
     /// Gets a mutable reference to a tile at a given [`Point`](Point)
     fn tile_at_mut(&mut self, loc: Point) -> &mut Tile {
         let idx = self.point2d_to_index(loc);
+        self.walk_min_cost.take();
         &mut self.tiles[idx]
     }
 
@@ -266,6 +306,28 @@ impl Labyrinth2D {
         self.tile_at(loc).kind()
     }
 
+    /// Changes the size of the map, keeping the tiles and zones of the area
+    /// both sizes share, anchored at the top left corner. New tiles are walls,
+    /// outside of any zone.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let old_width = self.dimensions.x as usize;
+        let mut tiles = vec![Tile::default(); width * height];
+        let mut zones = vec![None; width * height];
+
+        for (i, (tile, zone)) in self.tiles.drain(..).zip(self.zones.drain(..)).enumerate() {
+            let (x, y) = (i % old_width, i / old_width);
+            if x < width && y < height {
+                tiles[y * width + x] = tile;
+                zones[y * width + x] = zone;
+            }
+        }
+
+        self.tiles = tiles;
+        self.zones = zones;
+        self.dimensions = Point::new(width, height);
+        self.walk_min_cost.take();
+    }
+
     /// Sets the tile at the given [`Point`](Point) to a [`Tile`].
     pub fn set_tile_at(&mut self, loc: Point, tile: Tile) {
         *self.tile_at_mut(loc) = tile;
@@ -295,6 +357,12 @@ impl Labyrinth2D {
         // }
     }
 
+    /// Sets the cost of entering the tile at the given [`Point`] with a
+    /// movement type.
+    pub fn set_tile_cost(&mut self, loc: Point, move_type: MoveType, cost: f32) {
+        self.tile_at_mut(loc).set_cost(move_type, cost);
+    }
+
     /// Removes a set of movetypes to a tile at the given [`Point`](Point).
     pub fn remove_movetypes<T>(&mut self, loc: Point, move_types: T)
     where
@@ -319,23 +387,53 @@ impl Labyrinth2D {
     }
 
     /// Gets an immutable iterator of all tiles in the [`Labyrinth2D`]
-    pub fn iter(&self) -> core::slice::Iter<Tile> {
+    pub fn iter(&self) -> core::slice::Iter<'_, Tile> {
         self.tiles.iter()
     }
 
     /// Gets a mutable iterator of all tiles in the [`Labyrinth2D`]
-    pub fn iter_mut(&mut self) -> core::slice::IterMut<Tile> {
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, Tile> {
+        self.walk_min_cost.take();
         self.tiles.iter_mut()
     }
 
+    /// Lists every distinct tile of the map in order of first appearance,
+    /// along with the index into that list of each tile of the map.
+    ///
+    /// Tiles are told apart by everything they hold, not just their kind, and
+    /// the same map always gives the same palette.
+    fn palette(&self) -> (Vec<&Tile>, Vec<usize>) {
+        let mut palette: Vec<&Tile> = vec![];
+        let mut by_kind: HashMap<&str, Vec<usize>> = HashMap::new();
+
+        let indices = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                let candidates = by_kind.entry(tile.kind()).or_default();
+                match candidates.iter().find(|&&i| palette[i] == tile) {
+                    Some(&i) => i,
+                    None => {
+                        palette.push(tile);
+                        candidates.push(palette.len() - 1);
+                        palette.len() - 1
+                    }
+                }
+            })
+            .collect();
+
+        (palette, indices)
+    }
+
     /// Gets an immutable iterator over the rows of the [`Labyrinth2D`]
-    pub fn rows(&self) -> Rows<Tile> {
+    pub fn rows(&self) -> Rows<'_, Tile> {
         Rows(self.tiles.chunks(self.dimensions().x as usize))
     }
 
     /// Gets a mutable iterator over the rows of the [`Labyrinth2D`]
-    pub fn rows_mut(&mut self) -> RowsMut<Tile> {
+    pub fn rows_mut(&mut self) -> RowsMut<'_, Tile> {
         let width = self.dimensions().x as usize;
+        self.walk_min_cost.take();
         RowsMut(self.tiles.chunks_mut(width))
     }
 }
@@ -366,6 +464,8 @@ impl<'a, T> Iterator for RowsMut<'a, T> {
 mod tests {
     use super::*;
 
+    use crate::errors::LabyrinthError;
+
     // Serialization
     // #[test]
     // fn serialize() {
@@ -380,8 +480,7 @@ mod tests {
 
     #[test]
     fn out_of_bounds_neighbors_are_ignored() {
-        let mut map = Labyrinth2D::new_empty(3, 3);
-        map._filter = vec![MoveType::Walk];
+        let map = Labyrinth2D::new_empty(3, 3);
 
         println!("{:?}", map.get_available_exits(4));
 
@@ -409,12 +508,6 @@ mod tests {
         map
     }
 
-    fn prepare_testmap_3x3_for_movtype(movtypes: &[MoveType]) -> Labyrinth2D {
-        let mut map = prepare_testmap_3x3();
-        map._filter = movtypes.to_vec();
-        map
-    }
-
     fn smallvecs_are_equal<T: Copy + PartialEq>(
         a: SmallVec<[T; 10]>,
         b: SmallVec<[T; 10]>,
@@ -476,7 +569,8 @@ mod tests {
 
     #[test]
     fn walk_on_default_tiles() {
-        let map = prepare_testmap_3x3_for_movtype(&[MoveType::Walk]);
+        let testmap = prepare_testmap_3x3();
+        let map = testmap.movement_view([MoveType::Walk]);
 
         let center = map.point2d_to_index(Point::new(1, 1));
         let expected: SmallVec<[(usize, f32); 10]> =
@@ -487,7 +581,8 @@ mod tests {
 
     #[test]
     fn fly_on_default_tiles() {
-        let map = prepare_testmap_3x3_for_movtype(&[MoveType::Fly]);
+        let testmap = prepare_testmap_3x3();
+        let map = testmap.movement_view([MoveType::Fly]);
 
         let center = map.point2d_to_index(Point::new(1, 1));
 
@@ -506,7 +601,8 @@ mod tests {
 
     #[test]
     fn swim_on_default_tiles() {
-        let map = prepare_testmap_3x3_for_movtype(&[MoveType::Swim]);
+        let testmap = prepare_testmap_3x3();
+        let map = testmap.movement_view([MoveType::Swim]);
 
         let center = map.point2d_to_index(Point::new(1, 1));
 
@@ -522,19 +618,23 @@ mod tests {
 
     #[test]
     fn no_movement_can_enter_walls() {
-        let mut map = Labyrinth2D::new(3, 3);
+        let map = Labyrinth2D::new(3, 3);
 
         let center = map.point2d_to_index(Point::new(1, 1));
 
         assert!(map.get_available_exits(center).is_empty());
-        map._filter = vec![MoveType::Fly];
-        assert!(map.get_available_exits(center).is_empty());
-        map._filter = vec![MoveType::Swim];
-        assert!(map.get_available_exits(center).is_empty());
+        assert!(map
+            .movement_view([MoveType::Fly])
+            .get_available_exits(center)
+            .is_empty());
+        assert!(map
+            .movement_view([MoveType::Swim])
+            .get_available_exits(center)
+            .is_empty());
     }
 
     #[test]
-    fn custom_movement_types_are_usable() -> Result<(), String> {
+    fn custom_movement_types_are_usable() -> Result<(), LabyrinthError> {
         let mut map = Labyrinth2D::new(3, 3);
 
         let mut phasewall = Tile::wall();
@@ -550,10 +650,10 @@ mod tests {
             (map.point2d_to_index(Point::new(1, 0)), 1.0),
         ];
 
-        map._filter = vec![MoveType::custom("phasing")];
+        let view = map.movement_view([MoveType::custom("phasing")]);
 
         assert!(smallvecs_are_equal(
-            map.get_available_exits(center),
+            view.get_available_exits(center),
             expected
         ));
 
@@ -614,27 +714,272 @@ mod tests {
 
     // Pathfinding tests
     #[test]
-    fn find_path_resets_filter() {
-        let mut map = Labyrinth2D::new(3, 3);
-        let start = Point::new(0, 0);
-        let end = Point::new(2, 2);
+    fn find_path_on_shared_map() {
+        let map = Labyrinth2D::new_walled(5, 5);
+        let start = Point::new(1, 1);
+        let end = Point::new(3, 3);
 
-        map._filter = vec![MoveType::Fly];
+        let path = map.find_path(start, end, [MoveType::Walk]);
 
-        map.find_path(start, end, [MoveType::Walk]);
+        assert!(path.success);
+        assert_eq!(path.steps.len(), 5);
+    }
 
-        assert_eq!(map._filter, vec![]);
+    #[test]
+    fn find_path_with_no_movetypes_walks() {
+        let mut map = Labyrinth2D::new_walled(7, 5);
+        let start = Point::new(1, 1);
+        let end = Point::new(5, 3);
+
+        let unset = map.find_path(start, end, []);
+        let walking = map.find_path(start, end, [MoveType::Walk]);
+        assert!(unset.success);
+        assert_eq!(unset.steps, walking.steps);
+
+        for y in 1..4 {
+            map.set_tile_at(Point::new(3, y), Tile::water());
+        }
+        assert!(!map.find_path(start, end, []).success);
+        assert!(
+            map.find_path(start, end, [MoveType::Swim, MoveType::Walk])
+                .success
+        );
     }
 
     #[test]
-    fn dijkstra_resets_filter() {
-        let mut map = Labyrinth2D::new(3, 3);
-        let start = Point::new(0, 0);
+    fn pathfinding_across_threads() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let map = Arc::new(Labyrinth2D::new_walled(10, 10));
+
+        let handles: Vec<_> = [MoveType::Walk, MoveType::Fly, MoveType::Swim]
+            .into_iter()
+            .map(|move_type| {
+                let map = Arc::clone(&map);
+                thread::spawn(move || {
+                    map.find_path(Point::new(1, 1), Point::new(8, 8), [move_type])
+                        .success
+                })
+            })
+            .collect();
+
+        let results: Vec<bool> = handles.into_iter().map(|h| h.join().unwrap()).collect();
 
-        map._filter = vec![MoveType::Fly];
+        assert_eq!(results, vec![true, true, false]);
+    }
+
+    #[test]
+    fn exits_use_tile_costs() {
+        let mut map = prepare_testmap_3x3();
+        map.set_tile_cost(Point::new(0, 1), MoveType::Swim, 3.0);
+        map.set_tile_cost(Point::new(2, 1), MoveType::Fly, 0.5);
+
+        let center = map.point2d_to_index(Point::new(1, 1));
+
+        let expected: SmallVec<[(usize, f32); 10]> = smallvec![
+            (map.point2d_to_index(Point::new(0, 1)), 1.0), // water, flying
+            (map.point2d_to_index(Point::new(1, 0)), 1.0), // floor
+            (map.point2d_to_index(Point::new(1, 2)), 1.0), // lava
+            (map.point2d_to_index(Point::new(2, 1)), 0.5), // chasm
+        ];
+
+        assert!(smallvecs_are_equal(
+            map.movement_view([MoveType::Fly, MoveType::Swim])
+                .get_available_exits(center),
+            expected
+        ));
+    }
+
+    #[test]
+    fn find_path_avoids_expensive_tiles() {
+        let mut map = Labyrinth2D::new_walled(5, 5);
+        let start = Point::new(1, 2);
+        let end = Point::new(3, 2);
+
+        // the direct route goes through slow-to-wade shallows
+        let mut shallows = Tile::floor();
+        shallows.set_kind("shallows");
+        shallows.set_cost(MoveType::Walk, 5.0);
+        map.set_tile_at(Point::new(2, 2), shallows);
+
+        let path = map.find_path(start, end, [MoveType::Walk]);
+
+        assert!(path.success);
+        assert!(!path.steps.contains(&map.point2d_to_index(Point::new(2, 2))));
+        assert_eq!(path.steps.len(), 5);
+    }
+
+    #[test]
+    fn find_path_prefers_cheap_tiles() {
+        let mut map = Labyrinth2D::new_walled(7, 5);
+        let start = Point::new(1, 1);
+        let end = Point::new(5, 1);
+
+        // a longer, but much cheaper, flight path over a chasm
+        for x in 1..6 {
+            map.set_tile_at(Point::new(x, 3), Tile::chasm());
+            map.set_tile_cost(Point::new(x, 3), MoveType::Fly, 0.1);
+        }
+
+        let path = map.find_path(start, end, [MoveType::Fly]);
+
+        assert!(path.success);
+        assert!(path.steps.contains(&map.point2d_to_index(Point::new(3, 3))));
+    }
+
+    #[test]
+    fn diagonal_neighbors() {
+        let mut map = Labyrinth2D::new_empty(3, 3);
+        map.set_neighborhood(Neighborhood::Diagonal);
+
+        assert_eq!(count_neighbors(&map, 4), 8); // Center
+        assert_eq!(count_neighbors(&map, 0), 3); // Upper left corner
+        assert_eq!(count_neighbors(&map, 1), 5); // Top edge
+
+        // the neighborhood can be overridden by the view
+        let view = map.movement_view([MoveType::Walk]);
+        assert_eq!(
+            view.with_neighborhood(Neighborhood::Orthogonal)
+                .get_available_exits(4)
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn diagonal_steps_cost_more() {
+        let mut map = Labyrinth2D::new_empty(3, 3);
+        map.set_neighborhood(Neighborhood::Diagonal);
+        let view = map.movement_view([MoveType::Walk]);
+
+        let center = map.point2d_to_index(Point::new(1, 1));
+        let exits = view.get_available_exits(center);
+
+        let cost_to = |pt: Point| {
+            exits
+                .iter()
+                .find(|(idx, _)| *idx == map.point2d_to_index(pt))
+                .map(|(_, cost)| *cost)
+        };
+
+        assert_eq!(cost_to(Point::new(1, 0)), Some(1.0));
+        assert_eq!(cost_to(Point::new(0, 0)), Some(DIAGONAL_COST));
+
+        let view = view.with_diagonal_cost(1.0);
+        assert!(view
+            .get_available_exits(center)
+            .iter()
+            .all(|(_, cost)| *cost == 1.0));
+    }
+
+    #[test]
+    fn diagonals_can_not_cut_corners() {
+        let mut map = Labyrinth2D::new_empty(3, 3);
+        map.set_tile_at(Point::new(1, 0), Tile::wall());
+        map.set_tile_at(Point::new(0, 1), Tile::water());
+
+        let center = map.point2d_to_index(Point::new(1, 1));
+        let view = map.movement_view([MoveType::Walk]);
+
+        // (0, 0) is past the wall, (2, 0) past the wall and (0, 2) past the
+        // water, which can't be walked on
+        let exits: Vec<Point> = view
+            .with_neighborhood(Neighborhood::DiagonalNoCornerCutting)
+            .get_available_exits(center)
+            .iter()
+            .map(|(idx, _)| map.index_to_point2d(*idx))
+            .collect();
+
+        assert_eq!(exits.len(), 3);
+        assert!(exits.contains(&Point::new(2, 1)));
+        assert!(exits.contains(&Point::new(1, 2)));
+        assert!(exits.contains(&Point::new(2, 2)));
+
+        // but with cutting allowed, every floor tile is reachable
+        map.set_neighborhood(Neighborhood::Diagonal);
+        assert_eq!(
+            map.get_neighbors(Point::new(1, 1), [MoveType::Walk]).len(),
+            6
+        );
+    }
+
+    #[test]
+    fn find_path_with_diagonals() {
+        let mut map = Labyrinth2D::new_walled(7, 7);
+        let start = Point::new(1, 1);
+        let end = Point::new(5, 5);
+
+        assert_eq!(map.find_path(start, end, [MoveType::Walk]).steps.len(), 9);
+
+        map.set_neighborhood(Neighborhood::Diagonal);
+        assert_eq!(map.find_path(start, end, [MoveType::Walk]).steps.len(), 5);
+
+        let dmap = map.dijkstra_map(&[start], [MoveType::Walk]);
+        let dist = dmap.map[map.point2d_to_index(end)];
+        assert!((dist - 4.0 * DIAGONAL_COST).abs() < 1e-4);
+    }
+
+    #[test]
+    fn heuristic_matches_neighborhood() {
+        let mut map = Labyrinth2D::new_empty(5, 5);
+        let a = map.point2d_to_index(Point::new(0, 0));
+        let b = map.point2d_to_index(Point::new(3, 1));
+
+        assert_eq!(map.get_pathing_distance(a, b), 4.0);
+
+        map.set_neighborhood(Neighborhood::Diagonal);
+        assert_eq!(map.get_pathing_distance(a, b), 2.0 + DIAGONAL_COST);
+
+        // tiles cheaper than a step scale the estimate down, like in a view
+        map.set_tile_cost(Point::new(4, 4), MoveType::Walk, 0.5);
+        assert_eq!(
+            map.get_pathing_distance(a, b),
+            map.movement_view([MoveType::Walk])
+                .get_pathing_distance(a, b)
+        );
+        assert_eq!(map.get_pathing_distance(a, b), (2.0 + DIAGONAL_COST) * 0.5);
+    }
+
+    #[test]
+    fn direct_a_star_scales() {
+        // the heuristic of the bare map doesn't rescan it on every call
+        let map = Labyrinth2D::new_walled(200, 200);
+        let start = map.point2d_to_index(Point::new(1, 1));
+        let end = map.point2d_to_index(Point::new(198, 198));
+
+        let timer = std::time::Instant::now();
+        let path = a_star_search(start, end, &map);
+
+        assert!(path.success);
+        assert!(timer.elapsed() < std::time::Duration::from_secs(2));
+
+        let view_path = map.find_path(Point::new(1, 1), Point::new(198, 198), [MoveType::Walk]);
+        assert_eq!(path.steps.len(), view_path.steps.len());
+    }
+
+    #[test]
+    fn diagonal_cost_is_at_least_one() {
+        let mut map = Labyrinth2D::new_empty(3, 3);
+        map.set_neighborhood(Neighborhood::Diagonal);
+        let view = map.movement_view([MoveType::Walk]).with_diagonal_cost(0.5);
+
+        let center = map.point2d_to_index(Point::new(1, 1));
+        assert!(view
+            .get_available_exits(center)
+            .iter()
+            .all(|(_, cost)| *cost == 1.0));
+    }
+
+    #[test]
+    fn dijkstra_on_shared_map() {
+        let map = Labyrinth2D::new_walled(5, 5);
+        let start = Point::new(1, 1);
 
-        map.dijkstra_map(&[start], [MoveType::Walk]);
+        let dmap = map.dijkstra_map(&[start], [MoveType::Walk]);
 
-        assert_eq!(map._filter, vec![]);
+        assert_eq!(dmap.map[map.point2d_to_index(Point::new(3, 3))], 4.0);
+        // walls are never reached
+        assert_eq!(dmap.map[map.point2d_to_index(Point::new(0, 0))], f32::MAX);
     }
 }
@@ -0,0 +1,187 @@
+//! This module holds the connected components analysis for [`Labyrinth2D`].
+
+use std::collections::VecDeque;
+
+use bracket_pathfinding::prelude::*;
+
+use super::{Labyrinth2D, MoveType};
+
+/// The connected regions of a [`Labyrinth2D`], for a given set of
+/// [`MoveTypes`](MoveType).
+///
+/// Regions are numbered in the order their first tile appears in the map
+/// (row by row), and each region lists its tiles in that same order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectedComponents {
+    dimensions: Point,
+    labels: Vec<Option<usize>>,
+    regions: Vec<Vec<Point>>,
+}
+
+impl ConnectedComponents {
+    /// Number of separate regions
+    pub fn count(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Whether or not every enterable tile is reachable from every other one.
+    /// A map without any enterable tiles is not considered connected.
+    pub fn is_connected(&self) -> bool {
+        self.regions.len() == 1
+    }
+
+    /// Getter for the regions, as lists of the [`Points`](Point) in each
+    pub fn regions(&self) -> &[Vec<Point>] {
+        &self.regions
+    }
+
+    /// The region a [`Point`] belongs to, if it can be entered at all
+    pub fn region_of(&self, loc: Point) -> Option<usize> {
+        if loc.x < 0 || loc.y < 0 || loc.x >= self.dimensions.x || loc.y >= self.dimensions.y {
+            return None;
+        }
+        self.labels[(loc.y * self.dimensions.x + loc.x) as usize]
+    }
+
+    /// The index of the region with the most tiles. Ties go to the region
+    /// that comes first.
+    pub fn largest(&self) -> Option<usize> {
+        self.regions
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, region)| region.len())
+            .map(|(i, _)| i)
+    }
+}
+
+impl Labyrinth2D {
+    /// Splits the map into regions that are reachable from each other, using
+    /// the given [`MoveTypes`](MoveType) and the [`Neighborhood`](super::Neighborhood)
+    /// of the map.
+    /// ```rust
+    /// use bracket_pathfinding::prelude::*;
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// let mut map = Labyrinth2D::new_empty(9, 5);
+    /// for y in 0..5 {
+    ///     map.set_tile_at(Point::new(4, y), Tile::water());
+    /// }
+    ///
+    /// assert_eq!(map.connected_components([MoveType::Walk]).count(), 2);
+    /// assert!(map.is_fully_connected([MoveType::Walk, MoveType::Swim]));
+    /// ```
+    pub fn connected_components<T: Into<Vec<MoveType>>>(
+        &self,
+        move_types: T,
+    ) -> ConnectedComponents {
+        let view = self.movement_view(move_types);
+
+        let mut labels = vec![None; self.size()];
+        let mut regions = vec![];
+
+        for idx in 0..self.size() {
+            if labels[idx].is_some()
+                || !self.can_enter(self.index_to_point2d(idx), view.move_types())
+            {
+                continue;
+            }
+
+            let label = regions.len();
+            let mut region = vec![];
+            let mut queue = VecDeque::from([idx]);
+            labels[idx] = Some(label);
+
+            while let Some(current) = queue.pop_front() {
+                region.push(current);
+                for (next, _) in view.get_available_exits(current) {
+                    if labels[next].is_none() {
+                        labels[next] = Some(label);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            region.sort_unstable();
+            regions.push(
+                region
+                    .into_iter()
+                    .map(|idx| self.index_to_point2d(idx))
+                    .collect(),
+            );
+        }
+
+        ConnectedComponents {
+            dimensions: self.dimensions(),
+            labels,
+            regions,
+        }
+    }
+
+    /// Checks whether every tile that can be entered with the given
+    /// [`MoveTypes`](MoveType) is reachable from every other one.
+    pub fn is_fully_connected<T: Into<Vec<MoveType>>>(&self, move_types: T) -> bool {
+        self.connected_components(move_types).is_connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::{Neighborhood, Tile};
+
+    #[test]
+    fn open_map_is_one_region() {
+        let map = Labyrinth2D::new_walled(6, 6);
+        let components = map.connected_components([MoveType::Walk]);
+
+        assert_eq!(components.count(), 1);
+        assert!(components.is_connected());
+        assert_eq!(components.regions()[0].len(), 16);
+        assert_eq!(components.region_of(Point::new(0, 0)), None);
+        assert_eq!(components.region_of(Point::new(3, 3)), Some(0));
+        assert_eq!(components.region_of(Point::new(-1, 3)), None);
+    }
+
+    #[test]
+    fn filled_map_is_not_connected() {
+        let map = Labyrinth2D::new(6, 6);
+        let components = map.connected_components([MoveType::Walk]);
+
+        assert_eq!(components.count(), 0);
+        assert!(!components.is_connected());
+        assert_eq!(components.largest(), None);
+    }
+
+    #[test]
+    fn regions_depend_on_move_types() {
+        let mut map = Labyrinth2D::new_empty(7, 5);
+        for y in 0..5 {
+            map.set_tile_at(Point::new(2, y), Tile::chasm());
+        }
+
+        let walking = map.connected_components([MoveType::Walk]);
+        assert_eq!(walking.count(), 2);
+        assert_eq!(walking.largest(), Some(1));
+        assert_eq!(walking.region_of(Point::new(1, 1)), Some(0));
+        assert_eq!(walking.region_of(Point::new(4, 1)), Some(1));
+
+        assert!(map.is_fully_connected([MoveType::Fly]));
+    }
+
+    #[test]
+    fn diagonals_join_regions() {
+        let mut map = Labyrinth2D::new(3, 3);
+        map.set_tile_at(Point::new(0, 0), Tile::floor());
+        map.set_tile_at(Point::new(1, 1), Tile::floor());
+
+        assert_eq!(map.connected_components([MoveType::Walk]).count(), 2);
+
+        map.set_neighborhood(Neighborhood::Diagonal);
+        assert!(map.is_fully_connected([MoveType::Walk]));
+
+        map.set_neighborhood(Neighborhood::DiagonalNoCornerCutting);
+        assert!(!map.is_fully_connected([MoveType::Walk]));
+    }
+}
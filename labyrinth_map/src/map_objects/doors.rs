@@ -0,0 +1,134 @@
+//! This module holds the door methods of [`Labyrinth2D`].
+//!
+//! Doors are ordinary tiles (see [`Tile::door()`]), so these methods only
+//! swap a door tile for the one of its next state.
+
+use bracket_pathfinding::prelude::*;
+
+use super::{DoorState, Labyrinth2D, MoveType, Tile};
+
+/// Implementation of door methods for Labyrinth2D
+impl Labyrinth2D {
+    /// Returns the state of the door at a given [`Point`], or `None` if there
+    /// is no door there.
+    pub fn door_state(&self, loc: Point) -> Option<DoorState> {
+        self.tile_at(loc).door_state()
+    }
+
+    /// Opens the closed door at a given [`Point`]. Returns whether the door
+    /// was opened; locked doors have to be unlocked first.
+    ///
+    /// # Example Usage
+    /// ```rust
+    /// use bracket_pathfinding::prelude::*;
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// let mut map = Labyrinth2D::new_walled(5, 5);
+    /// let door = Point::new(2, 2);
+    /// map.set_tile_at(door, Tile::locked_door(MoveType::custom("red_key")));
+    ///
+    /// assert!(!map.open_door(door));
+    /// assert!(map.unlock_door(door, &MoveType::custom("red_key")));
+    /// assert!(map.open_door(door));
+    /// assert_eq!(map.door_state(door), Some(DoorState::Open));
+    /// ```
+    pub fn open_door(&mut self, loc: Point) -> bool {
+        self.change_door(loc, DoorState::Closed, Tile::open_door())
+    }
+
+    /// Closes the open door at a given [`Point`]. Returns whether the door
+    /// was closed.
+    pub fn close_door(&mut self, loc: Point) -> bool {
+        self.change_door(loc, DoorState::Open, Tile::door())
+    }
+
+    /// Locks the closed door at a given [`Point`], so that it can only be
+    /// entered with the key. Returns whether the door was locked.
+    pub fn lock_door(&mut self, loc: Point, key: MoveType) -> bool {
+        self.change_door(loc, DoorState::Closed, Tile::locked_door(key))
+    }
+
+    /// Unlocks the locked door at a given [`Point`] with a key, leaving it
+    /// closed. Returns whether the door was unlocked, which only happens if
+    /// the key fits.
+    pub fn unlock_door(&mut self, loc: Point, key: &MoveType) -> bool {
+        if !self.tile_at(loc).can_enter([key]) {
+            return false;
+        }
+
+        self.change_door(loc, DoorState::Locked, Tile::door())
+    }
+
+    /// Replaces a door in a given state with a new door tile
+    fn change_door(&mut self, loc: Point, from: DoorState, to: Tile) -> bool {
+        if !self.in_bounds(loc) || self.door_state(loc) != Some(from) {
+            return false;
+        }
+
+        self.set_tile_at(loc, to);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map_objects::DOOR_COST;
+
+    #[test]
+    fn doors_open_and_close() {
+        let mut map = Labyrinth2D::new_empty(3, 1);
+        let door = Point::new(1, 0);
+        map.set_tile_at(door, Tile::door());
+
+        assert!(map.tile_at(door).is_opaque());
+        assert!(!map.close_door(door));
+
+        assert!(map.open_door(door));
+        assert!(!map.tile_at(door).is_opaque());
+        assert!(map.can_enter(door, &[MoveType::Walk]));
+
+        assert!(map.close_door(door));
+        assert_eq!(map.door_state(door), Some(DoorState::Closed));
+        assert!(!map.can_enter(door, &[MoveType::Walk]));
+    }
+
+    #[test]
+    fn locked_doors_need_their_key() {
+        let mut map = Labyrinth2D::new_empty(3, 1);
+        let door = Point::new(1, 0);
+        map.set_tile_at(door, Tile::door());
+
+        assert!(map.lock_door(door, MoveType::custom("red_key")));
+        assert!(!map.open_door(door));
+        assert!(!map.unlock_door(door, &MoveType::custom("blue_key")));
+        assert!(map.unlock_door(door, &MoveType::custom("Red_Key")));
+        assert_eq!(map.door_state(door), Some(DoorState::Closed));
+    }
+
+    #[test]
+    fn closed_doors_cost_more() {
+        let mut map = Labyrinth2D::new_empty(5, 3);
+        for y in 0..3 {
+            map.set_tile_at(Point::new(2, y), Tile::wall());
+        }
+        map.set_tile_at(Point::new(2, 1), Tile::door());
+
+        let movers = [MoveType::Walk, MoveType::opener()];
+        let dijkstra = map.dijkstra_map(&[Point::new(0, 1)], movers);
+
+        // 4 steps, one of them through the door
+        let distance = dijkstra.map[map.point2d_to_index(Point::new(4, 1))];
+        assert_eq!(distance, 3.0 + DOOR_COST);
+    }
+
+    #[test]
+    fn non_doors_are_left_alone() {
+        let mut map = Labyrinth2D::new_walled(3, 3);
+
+        assert!(!map.open_door(Point::new(1, 1)));
+        assert!(!map.open_door(Point::new(5, 5)));
+        assert_eq!(map.tile_kind(Point::new(1, 1)), "floor");
+    }
+}
@@ -0,0 +1,785 @@
+//! This module holds the [`Labyrinth3D`] struct, a stack of [`Labyrinth2D`]
+//! levels joined by [`Links`](Link) such as stairs, ladders and pits.
+//!
+//!
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+
+use bracket_pathfinding::prelude::*;
+
+use super::{Labyrinth2D, MoveType, MovementView};
+use crate::errors::LabyrinthError;
+
+/// Enum defining the kinds of links between levels
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum LinkKind {
+    /// variant for stairs leading up a level
+    StairsUp,
+
+    /// variant for stairs leading down a level
+    StairsDown,
+
+    /// variant for ladders
+    Ladder,
+
+    /// variant for pits, which can only be fallen down
+    Pit,
+
+    /// variant for a user-defined link kind
+    Custom(String),
+}
+
+/// One-way connection from a tile on one level of a [`Labyrinth3D`] to a tile
+/// on another level.
+///
+/// Like [`Tiles`](super::Tile), links are gated by [`MoveType`]: an entity can
+/// only use a link if one of its move types is in the link's access set.
+///
+/// # Construction
+/// Direct constructors are provided for some basic link types:
+/// - Stairs through [`Link::stairs_down()`] and [`Link::stairs_up()`]
+///     - Usable by walkers and flyers
+/// - Ladders through [`Link::ladder()`]
+///     - Usable by walkers
+/// - Pits through [`Link::pit()`]
+///     - Usable by walkers and flyers
+///
+/// Two-way connections are made by also adding the [`Link::reversed()`] link,
+/// see [`Labyrinth3D::add_two_way_link()`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Link {
+    /// The tile the link starts from.
+    from: Point3,
+
+    /// The tile the link leads to.
+    to: Point3,
+
+    /// The kind of link it is.
+    kind: LinkKind,
+
+    /// A hashset that defines the movement types that can use the link.
+    access: HashSet<MoveType>,
+
+    /// The cost of using the link.
+    #[cfg_attr(
+        feature = "serialization",
+        serde(deserialize_with = "deserialize_cost")
+    )]
+    cost: f32,
+}
+
+/// Deserializes the cost of a [`Link`], which must be finite and positive
+/// like the ones set through [`Link::with_cost()`].
+#[cfg(feature = "serialization")]
+fn deserialize_cost<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let cost = f32::deserialize(deserializer)?;
+    if cost.is_finite() && cost > 0.0 {
+        Ok(cost)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "Link costs must be finite and positive, got {}",
+            cost
+        )))
+    }
+}
+
+impl Link {
+    /// Explicit link constructor
+    pub fn new<T>(from: Point3, to: Point3, kind: LinkKind, access: T) -> Link
+    where
+        T: IntoIterator<Item = MoveType>,
+    {
+        Link {
+            from,
+            to,
+            kind: match kind {
+                LinkKind::Custom(str) => LinkKind::Custom(str.to_lowercase()),
+                _ => kind,
+            },
+            access: access
+                .into_iter()
+                .map(|movtype| match movtype {
+                    MoveType::Custom(str) => MoveType::custom(str),
+                    _ => movtype,
+                })
+                .collect(),
+            cost: 1.0,
+        }
+    }
+
+    /// Direct constructor for stairs leading down
+    pub fn stairs_down(from: Point3, to: Point3) -> Link {
+        Link::new(
+            from,
+            to,
+            LinkKind::StairsDown,
+            [MoveType::Walk, MoveType::Fly],
+        )
+    }
+
+    /// Direct constructor for stairs leading up
+    pub fn stairs_up(from: Point3, to: Point3) -> Link {
+        Link::new(
+            from,
+            to,
+            LinkKind::StairsUp,
+            [MoveType::Walk, MoveType::Fly],
+        )
+    }
+
+    /// Direct constructor for a ladder
+    pub fn ladder(from: Point3, to: Point3) -> Link {
+        Link::new(from, to, LinkKind::Ladder, [MoveType::Walk])
+    }
+
+    /// Direct constructor for a pit
+    pub fn pit(from: Point3, to: Point3) -> Link {
+        Link::new(from, to, LinkKind::Pit, [MoveType::Walk, MoveType::Fly])
+    }
+
+    /// Sets the cost of using the link.
+    ///
+    /// # Panics
+    /// Panics if the cost is not finite and positive.
+    pub fn with_cost(mut self, cost: f32) -> Link {
+        assert!(
+            cost.is_finite() && cost > 0.0,
+            "Link costs must be finite and positive, got {}",
+            cost
+        );
+        self.cost = cost;
+        self
+    }
+
+    /// Returns the same link, going the other way. Stairs going down become
+    /// stairs going up and vice versa.
+    pub fn reversed(&self) -> Link {
+        Link {
+            from: self.to,
+            to: self.from,
+            kind: match &self.kind {
+                LinkKind::StairsDown => LinkKind::StairsUp,
+                LinkKind::StairsUp => LinkKind::StairsDown,
+                kind => kind.clone(),
+            },
+            access: self.access.clone(),
+            cost: self.cost,
+        }
+    }
+
+    /// Getter for the start of the link
+    pub fn from(&self) -> Point3 {
+        self.from
+    }
+
+    /// Getter for the end of the link
+    pub fn to(&self) -> Point3 {
+        self.to
+    }
+
+    /// Getter for the link kind
+    pub fn kind(&self) -> &LinkKind {
+        &self.kind
+    }
+
+    /// Returns the accessibility of a link
+    pub fn access(&self) -> &HashSet<MoveType> {
+        &self.access
+    }
+
+    /// Getter for the cost of using the link
+    pub fn cost(&self) -> f32 {
+        self.cost
+    }
+
+    /// Checks if an entity with the given move types can use the link.
+    pub fn can_use<'a, T>(&self, move_types: T) -> bool
+    where
+        T: IntoIterator<Item = &'a MoveType>,
+    {
+        move_types
+            .into_iter()
+            .map(|move_type| match move_type {
+                MoveType::Custom(kind) => MoveType::custom(kind),
+                _ => move_type.clone(),
+            })
+            .any(|move_type| self.access.contains(&move_type))
+    }
+}
+
+/// Labyrinth3D struct, a stack of same-sized [`Labyrinth2D`] levels, joined by
+/// [`Links`](Link).
+///
+/// Positions on the map are given as [`Point3`], where `z` is the index of
+/// the level. Pathfinding and Dijkstra maps cross between levels through the
+/// links, as long as the entity's move types can use them.
+/// ```rust
+/// use bracket_pathfinding::prelude::*;
+/// use labyrinth_map::prelude::*;
+///
+/// let mut map = Labyrinth3D::new(10, 10, 2);
+/// map.level_mut(0).unwrap().set_tile_at(Point::new(1, 1), Tile::floor());
+/// map.level_mut(1).unwrap().set_tile_at(Point::new(1, 1), Tile::floor());
+///
+/// let stairs = Link::stairs_down(Point3::new(1, 1, 0), Point3::new(1, 1, 1));
+/// map.add_two_way_link(stairs).unwrap();
+///
+/// let path = map.find_path(Point3::new(1, 1, 1), Point3::new(1, 1, 0), [MoveType::Walk]);
+/// assert!(path.success);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Labyrinth3D {
+    // The levels of the map, from the top down
+    levels: Vec<Labyrinth2D>,
+    dimensions: Point,
+    links: Vec<Link>,
+}
+
+impl Labyrinth3D {
+    // ------------------ Constructors ---------------------------
+    /// Constructs a new Labyrinth3D with the passed width, height and number
+    /// of levels.
+    ///
+    /// Initial Tiles are all walls.
+    pub fn new(width: usize, height: usize, depth: usize) -> Labyrinth3D {
+        Labyrinth3D {
+            levels: vec![Labyrinth2D::new(width, height); depth],
+            dimensions: Point::new(width, height),
+            links: vec![],
+        }
+    }
+
+    /// Constructs a new Labyrinth3D from a stack of levels. All levels must
+    /// have the same dimensions.
+    pub fn from_levels(levels: Vec<Labyrinth2D>) -> Result<Labyrinth3D, LabyrinthError> {
+        let dimensions = match levels.first() {
+            Some(level) => level.dimensions(),
+            None => return Err(LabyrinthError::NoLevels),
+        };
+
+        if let Some(level) = levels.iter().find(|level| level.dimensions() != dimensions) {
+            return Err(LabyrinthError::LevelSizeMismatch {
+                expected: dimensions,
+                found: level.dimensions(),
+            });
+        }
+
+        Ok(Labyrinth3D {
+            levels,
+            dimensions,
+            links: vec![],
+        })
+    }
+
+    // ----------------- Level Methods --------------
+    /// Getter for the dimensions of every level
+    pub fn dimensions(&self) -> Point {
+        self.dimensions
+    }
+
+    /// Getter for the number of levels
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Getter for the total size of the [`Labyrinth3D`], in total number of
+    /// tiles.
+    pub fn size(&self) -> usize {
+        self.levels.iter().map(|level| level.size()).sum()
+    }
+
+    /// Gets a reference to the level at a given index
+    pub fn level(&self, z: usize) -> Option<&Labyrinth2D> {
+        self.levels.get(z)
+    }
+
+    /// Gets mutable access to the level at a given index.
+    ///
+    /// All levels must keep the same dimensions, so a level resized through
+    /// the returned [`LevelMut`] is resized back when it is dropped.
+    pub fn level_mut(&mut self, z: usize) -> Option<LevelMut<'_>> {
+        let dimensions = self.dimensions;
+        self.levels
+            .get_mut(z)
+            .map(|level| LevelMut { level, dimensions })
+    }
+
+    /// Getter for all levels, from the top down
+    pub fn levels(&self) -> &Vec<Labyrinth2D> {
+        &self.levels
+    }
+
+    /// Adds a level to the bottom of the map. It must have the same dimensions
+    /// as the other levels.
+    pub fn push_level(&mut self, level: Labyrinth2D) -> Result<(), LabyrinthError> {
+        if level.dimensions() != self.dimensions {
+            return Err(LabyrinthError::LevelSizeMismatch {
+                expected: self.dimensions,
+                found: level.dimensions(),
+            });
+        }
+
+        self.levels.push(level);
+        Ok(())
+    }
+
+    /// Checks if a [`Point3`] is within the bounds of the map
+    pub fn in_bounds(&self, loc: Point3) -> bool {
+        loc.z >= 0
+            && (loc.z as usize) < self.depth()
+            && self.levels[loc.z as usize].in_bounds(Point::new(loc.x, loc.y))
+    }
+
+    /// Converts a [`Point3`] to a global tile index, as used by
+    /// [`MovementView3D`] and [`Labyrinth3D::dijkstra_map()`]
+    pub fn point3d_to_index(&self, loc: Point3) -> usize {
+        let level_size = (self.dimensions.x * self.dimensions.y) as usize;
+        loc.z as usize * level_size + (loc.y * self.dimensions.x + loc.x) as usize
+    }
+
+    /// Converts a global tile index back to a [`Point3`]
+    pub fn index_to_point3d(&self, idx: usize) -> Point3 {
+        let level_size = (self.dimensions.x * self.dimensions.y) as usize;
+        let local = (idx % level_size) as i32;
+
+        Point3::new(
+            local % self.dimensions.x,
+            local / self.dimensions.x,
+            (idx / level_size) as i32,
+        )
+    }
+
+    // ----------------- Link Methods --------------
+    /// Adds a one-way [`Link`] between two tiles. Both ends must be in bounds.
+    pub fn add_link(&mut self, link: Link) -> Result<(), LabyrinthError> {
+        if let Some(&end) = [link.from(), link.to()]
+            .iter()
+            .find(|&&end| !self.in_bounds(end))
+        {
+            return Err(LabyrinthError::LinkOutOfBounds(end));
+        }
+
+        self.links.push(link);
+        Ok(())
+    }
+
+    /// Adds a [`Link`] and its [reverse](Link::reversed), so it can be used
+    /// both ways.
+    pub fn add_two_way_link(&mut self, link: Link) -> Result<(), LabyrinthError> {
+        let reversed = link.reversed();
+        self.add_link(link)?;
+        self.add_link(reversed)
+    }
+
+    /// Getter for all links in the map
+    pub fn links(&self) -> &Vec<Link> {
+        &self.links
+    }
+
+    /// Gets the links starting at a given [`Point3`]
+    pub fn links_from(&self, loc: Point3) -> Vec<&Link> {
+        self.links
+            .iter()
+            .filter(|link| link.from() == loc)
+            .collect()
+    }
+
+    /// Removes every link that starts or ends at a given [`Point3`]
+    pub fn remove_links_at(&mut self, loc: Point3) {
+        self.links
+            .retain(|link| link.from() != loc && link.to() != loc);
+    }
+
+    // -------------------- Pathfinding functions -----------------
+    /// Returns a [`MovementView3D`] of the [`Labyrinth3D`] for an entity with
+    /// the specified movement types.
+    pub fn movement_view<T>(&self, move_types: T) -> MovementView3D<'_>
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        MovementView3D::new(self, move_types)
+    }
+
+    /// Returns the neighbors of a [`Point3`], including the ends of any links
+    /// that can be used, given the usable move types.
+    pub fn get_neighbors<T>(&self, loc: Point3, move_types: T) -> Vec<Point3>
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        let view = self.movement_view(move_types);
+
+        view.get_available_exits(self.point3d_to_index(loc))
+            .iter()
+            .map(|(idx, _)| self.index_to_point3d(*idx))
+            .collect()
+    }
+
+    /// Find the path between two [`Point3s`](Point3) for an entity with
+    /// multiple movement types. The steps of the path are global indices, see
+    /// [`Labyrinth3D::index_to_point3d()`].
+    pub fn find_path<T>(&self, start: Point3, end: Point3, move_types: T) -> NavigationPath
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        let view = self.movement_view(move_types);
+
+        a_star_search(
+            self.point3d_to_index(start),
+            self.point3d_to_index(end),
+            &view,
+        )
+    }
+
+    /// Returns Dijkstra map for a set of starting [`Point3s`](Point3), given
+    /// the movement types of the entity. The map is indexed by global indices,
+    /// see [`Labyrinth3D::point3d_to_index()`].
+    pub fn dijkstra_map<T>(&self, starts: &[Point3], move_types: T) -> DijkstraMap
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        let view = self.movement_view(move_types);
+
+        let starts_idx: Vec<usize> = starts.iter().map(|&pt| self.point3d_to_index(pt)).collect();
+
+        let level_size = (self.dimensions.x * self.dimensions.y) as usize;
+
+        DijkstraMap::new(level_size, self.depth(), &starts_idx, &view, 1024.0)
+    }
+}
+
+/// Borrowed view of a [`Labyrinth3D`] together with the movement types of an
+/// entity moving through it. The 3D counterpart of [`MovementView`].
+///
+/// Implements [`BaseMap`] over global tile indices (see
+/// [`Labyrinth3D::point3d_to_index()`]), so that bracket-lib pathfinding can
+/// cross between levels through links.
+#[derive(Clone, Debug)]
+pub struct MovementView3D<'a> {
+    map: &'a Labyrinth3D,
+    levels: Vec<MovementView<'a>>,
+
+    // usable links, keyed by the global index they start from
+    links: HashMap<usize, Vec<(usize, f32)>>,
+
+    // whether every usable link stays on the same (x, y) position, in which
+    // case the 2D heuristic is still admissible within a level
+    aligned_links: bool,
+}
+
+impl<'a> MovementView3D<'a> {
+    /// Constructs a new [`MovementView3D`] over a [`Labyrinth3D`].
+    ///
+    /// An empty collection of move types is treated as [`MoveType::Walk`].
+    pub fn new<T>(map: &'a Labyrinth3D, move_types: T) -> MovementView3D<'a>
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        let move_types: Vec<MoveType> = move_types.into();
+
+        let levels: Vec<MovementView> = map
+            .levels
+            .iter()
+            .map(|level| level.movement_view(move_types.clone()))
+            .collect();
+
+        let usable: Vec<&Link> = match levels.first() {
+            Some(view) => map
+                .links
+                .iter()
+                .filter(|link| link.can_use(view.move_types()))
+                .collect(),
+            None => vec![],
+        };
+
+        let mut links: HashMap<usize, Vec<(usize, f32)>> = HashMap::new();
+        for link in usable.iter() {
+            links
+                .entry(map.point3d_to_index(link.from()))
+                .or_default()
+                .push((map.point3d_to_index(link.to()), link.cost()));
+        }
+
+        let aligned_links = usable
+            .iter()
+            .all(|link| link.from().x == link.to().x && link.from().y == link.to().y);
+
+        MovementView3D {
+            map,
+            levels,
+            links,
+            aligned_links,
+        }
+    }
+
+    /// Getter for the underlying [`Labyrinth3D`]
+    pub fn map(&self) -> &'a Labyrinth3D {
+        self.map
+    }
+
+    fn level_size(&self) -> usize {
+        let dims = self.map.dimensions();
+        (dims.x * dims.y) as usize
+    }
+}
+
+impl BaseMap for MovementView3D<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        let level_size = self.level_size();
+        self.levels[idx / level_size].is_opaque(idx % level_size)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let level_size = self.level_size();
+        let (z, local) = (idx / level_size, idx % level_size);
+
+        let mut exits: SmallVec<[(usize, f32); 10]> = self.levels[z]
+            .get_available_exits(local)
+            .iter()
+            .map(|&(pos, cost)| (z * level_size + pos, cost))
+            .collect();
+
+        // links can be taken as long as the destination can be entered
+        if let Some(links) = self.links.get(&idx) {
+            exits.extend(links.iter().copied().filter(|&(to, _)| {
+                let to_level = &self.levels[to / level_size];
+                let pt = to_level.index_to_point2d(to % level_size);
+                to_level
+                    .map()
+                    .tile_cost(pt, to_level.move_types())
+                    .is_some()
+            }));
+        }
+
+        exits
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        let level_size = self.level_size();
+
+        // any link could be a shortcut across levels, so only estimate within
+        // a level when links can't skip ahead
+        if idx1 / level_size == idx2 / level_size && self.aligned_links {
+            self.levels[idx1 / level_size]
+                .get_pathing_distance(idx1 % level_size, idx2 % level_size)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Mutable access to one level of a [`Labyrinth3D`], as returned by
+/// [`Labyrinth3D::level_mut()`].
+///
+/// Derefs to the [`Labyrinth2D`] of the level. If the level was resized while
+/// borrowed, it is resized back to the dimensions of the map when the guard
+/// is dropped, keeping the tiles both sizes share.
+/// ```rust
+/// use bracket_pathfinding::prelude::*;
+/// use labyrinth_map::prelude::*;
+///
+/// let mut map = Labyrinth3D::new(10, 10, 2);
+/// map.level_mut(0).unwrap().resize(5, 5);
+///
+/// assert_eq!(map.level(0).unwrap().dimensions(), Point::new(10, 10));
+/// ```
+#[derive(Debug)]
+pub struct LevelMut<'a> {
+    level: &'a mut Labyrinth2D,
+    dimensions: Point,
+}
+
+impl Deref for LevelMut<'_> {
+    type Target = Labyrinth2D;
+
+    fn deref(&self) -> &Labyrinth2D {
+        self.level
+    }
+}
+
+impl DerefMut for LevelMut<'_> {
+    fn deref_mut(&mut self) -> &mut Labyrinth2D {
+        self.level
+    }
+}
+
+impl Drop for LevelMut<'_> {
+    fn drop(&mut self) {
+        if self.level.dimensions() != self.dimensions {
+            self.level
+                .resize(self.dimensions.x as usize, self.dimensions.y as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::Tile;
+
+    fn prepare_two_floors() -> Labyrinth3D {
+        let mut map = Labyrinth3D::from_levels(vec![
+            Labyrinth2D::new_walled(5, 5),
+            Labyrinth2D::new_walled(5, 5),
+        ])
+        .unwrap();
+
+        // wall off the two halves of the top level
+        for y in 0..5 {
+            map.level_mut(0)
+                .unwrap()
+                .set_tile_at(Point::new(2, y), Tile::wall());
+        }
+
+        map
+    }
+
+    #[test]
+    fn levels_must_match() {
+        assert!(matches!(
+            Labyrinth3D::from_levels(vec![]),
+            Err(LabyrinthError::NoLevels)
+        ));
+        assert!(matches!(
+            Labyrinth3D::from_levels(vec![Labyrinth2D::new(3, 3), Labyrinth2D::new(4, 3)]),
+            Err(LabyrinthError::LevelSizeMismatch { .. })
+        ));
+
+        let mut map = Labyrinth3D::new(3, 3, 1);
+        assert!(map.push_level(Labyrinth2D::new(3, 4)).is_err());
+        assert!(map.push_level(Labyrinth2D::new(3, 3)).is_ok());
+        assert_eq!(map.depth(), 2);
+    }
+
+    #[test]
+    fn levels_keep_their_size() {
+        let mut map = prepare_two_floors();
+
+        {
+            let mut level = map.level_mut(0).unwrap();
+            level.resize(3, 8);
+            level.set_tile_at(Point::new(1, 7), Tile::floor());
+        }
+
+        let level = map.level(0).unwrap();
+        assert_eq!(level.dimensions(), Point::new(5, 5));
+        assert_eq!(level.tile_kind(Point::new(1, 1)), "floor");
+        assert_eq!(level.tile_kind(Point::new(2, 1)), "wall");
+        assert_eq!(level.tile_kind(Point::new(4, 1)), "wall");
+
+        // the index math still holds for every level
+        let loc = Point3::new(4, 4, 1);
+        assert_eq!(map.index_to_point3d(map.point3d_to_index(loc)), loc);
+        assert_eq!(map.size(), 50);
+    }
+
+    #[test]
+    fn links_must_be_in_bounds() {
+        let mut map = Labyrinth3D::new(3, 3, 2);
+
+        assert!(matches!(
+            map.add_link(Link::ladder(Point3::new(1, 1, 0), Point3::new(1, 1, 2))),
+            Err(LabyrinthError::LinkOutOfBounds(end)) if end == Point3::new(1, 1, 2)
+        ));
+        assert!(map
+            .add_link(Link::ladder(Point3::new(1, 1, 0), Point3::new(1, 1, 1)))
+            .is_ok());
+    }
+
+    #[test]
+    fn indices_are_reversible() {
+        let map = Labyrinth3D::new(4, 3, 3);
+
+        for idx in 0..map.size() {
+            assert_eq!(map.point3d_to_index(map.index_to_point3d(idx)), idx);
+        }
+        assert_eq!(map.point3d_to_index(Point3::new(1, 2, 1)), 21);
+    }
+
+    #[test]
+    fn stairs_connect_levels() {
+        let mut map = prepare_two_floors();
+        let start = Point3::new(1, 1, 0);
+        let end = Point3::new(3, 1, 0);
+
+        assert!(!map.find_path(start, end, [MoveType::Walk]).success);
+
+        map.add_two_way_link(Link::stairs_down(start, Point3::new(1, 1, 1)))
+            .unwrap();
+        map.add_two_way_link(Link::stairs_down(end, Point3::new(3, 1, 1)))
+            .unwrap();
+
+        let path = map.find_path(start, end, [MoveType::Walk]);
+        assert!(path.success);
+        assert!(path
+            .steps
+            .iter()
+            .any(|&idx| map.index_to_point3d(idx).z == 1));
+
+        let dmap = map.dijkstra_map(&[start], [MoveType::Walk]);
+        assert_eq!(dmap.map[map.point3d_to_index(end)], 4.0);
+    }
+
+    #[test]
+    fn links_are_gated_by_movetype() {
+        let mut map = prepare_two_floors();
+        let start = Point3::new(1, 1, 0);
+
+        map.add_two_way_link(Link::ladder(start, Point3::new(1, 1, 1)))
+            .unwrap();
+
+        assert!(map
+            .get_neighbors(start, [MoveType::Walk])
+            .contains(&Point3::new(1, 1, 1)));
+        assert!(!map
+            .get_neighbors(start, [MoveType::Fly])
+            .contains(&Point3::new(1, 1, 1)));
+    }
+
+    #[test]
+    fn pits_are_one_way() {
+        let mut map = prepare_two_floors();
+        let top = Point3::new(1, 1, 0);
+        let bottom = Point3::new(3, 3, 1);
+
+        map.add_link(Link::pit(top, bottom)).unwrap();
+
+        assert!(map.find_path(top, bottom, [MoveType::Walk]).success);
+        assert!(!map.find_path(bottom, top, [MoveType::Walk]).success);
+
+        map.remove_links_at(bottom);
+        assert!(map.links().is_empty());
+    }
+
+    #[test]
+    fn links_need_an_enterable_destination() {
+        let mut map = prepare_two_floors();
+        let start = Point3::new(1, 1, 0);
+
+        map.add_link(Link::pit(start, Point3::new(0, 0, 1)))
+            .unwrap();
+
+        assert_eq!(map.get_neighbors(start, [MoveType::Walk]).len(), 1);
+    }
+
+    #[test]
+    fn reversed_stairs_lead_up() {
+        let stairs = Link::stairs_down(Point3::new(1, 1, 0), Point3::new(2, 2, 1)).with_cost(2.0);
+        let reversed = stairs.reversed();
+
+        assert_eq!(reversed.kind(), &LinkKind::StairsUp);
+        assert_eq!(reversed.from(), stairs.to());
+        assert_eq!(reversed.to(), stairs.from());
+        assert_eq!(reversed.cost(), 2.0);
+        assert_eq!(reversed.reversed(), stairs);
+    }
+}
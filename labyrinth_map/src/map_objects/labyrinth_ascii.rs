@@ -0,0 +1,427 @@
+//! Module for the plain text representation of a [`Labyrinth2D`].
+//!
+//! A text map is a header that gives the tile for each glyph, a `---` line,
+//! and then the grid of glyphs, one line per row:
+//! ```text
+//! # = wall opaque
+//! . = floor transparent Walk Fly
+//! , = mud transparent Walk:3 Fly dig
+//! ---
+//! #####
+//! #.,.#
+//! #####
+//! ```
+//! Every header line is a glyph, `=`, the kind of the tile, whether it is
+//! `opaque` or `transparent`, and the move types that can enter it, each
+//! with an optional cost after a `:`. Whitespace, `:` and `%` in kinds and
+//! move types are written as `%` and their hex code, so `deep water` is
+//! `deep%20water`.
+//!
+//! The default tiles can be used without a header, as `#` (wall), `.` (floor),
+//! `~` (water), `!` (lava), `:` (chasm), `+` (closed door) and `'` (open door).
+//! In that case the `---` line can be left out too.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use bracket_pathfinding::prelude::*;
+
+use super::{Labyrinth2D, MoveType, Tile};
+use crate::errors::LabyrinthError;
+
+/// Line separating the header from the grid
+const SEPARATOR: &str = "---";
+
+/// Glyphs of the default tiles
+fn default_glyphs() -> [(char, Tile); 7] {
+    [
+        ('#', Tile::wall()),
+        ('.', Tile::floor()),
+        ('~', Tile::water()),
+        ('!', Tile::lava()),
+        (':', Tile::chasm()),
+        ('+', Tile::door()),
+        ('\'', Tile::open_door()),
+    ]
+}
+
+/// Characters of kinds and move types that are escaped in header lines
+fn needs_escape(c: char) -> bool {
+    c.is_whitespace() || c == ':' || c == '%'
+}
+
+/// Escapes a kind or move type for a header line
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if needs_escape(c) {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                escaped += &format!("%{:02X}", byte);
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape`], or returns None for a malformed escape
+fn unescape(word: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(word.len());
+    let mut rest = word.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Builds a syntax error for a position in the text, counted from 1
+fn syntax_error<T: Into<String>>(line: usize, column: usize, message: T) -> LabyrinthError {
+    LabyrinthError::Syntax {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+/// Reads a header line into its glyph and tile
+fn parse_header_line(line: &str, lineno: usize) -> Result<(char, Tile), LabyrinthError> {
+    let mut chars = line.chars();
+    let glyph = chars
+        .next()
+        .ok_or_else(|| syntax_error(lineno, 1, "expected a glyph"))?;
+
+    let definition = chars
+        .as_str()
+        .strip_prefix(" = ")
+        .ok_or_else(|| syntax_error(lineno, 2, "expected \" = \" after the glyph"))?;
+
+    // column of each word, to point errors at it
+    let mut words = definition
+        .split(' ')
+        .scan(5, |column, word| {
+            let start = *column;
+            *column += word.chars().count() + 1;
+            Some((start, word))
+        })
+        .filter(|(_, word)| !word.is_empty());
+
+    let (column, kind) = words
+        .next()
+        .ok_or_else(|| syntax_error(lineno, 5, "expected the kind of the tile"))?;
+    let kind = unescape(kind).ok_or_else(|| syntax_error(lineno, column, "invalid escape"))?;
+
+    let opaque = match words.next() {
+        Some((_, "opaque")) => true,
+        Some((_, "transparent")) => false,
+        Some((column, word)) => {
+            return Err(syntax_error(
+                lineno,
+                column,
+                format!("expected opaque or transparent, found {:?}", word),
+            ))
+        }
+        None => {
+            return Err(syntax_error(
+                lineno,
+                line.chars().count() + 1,
+                "expected opaque or transparent",
+            ))
+        }
+    };
+
+    let mut tile = Tile::new(&kind, opaque, []);
+
+    for (column, word) in words {
+        let (name, cost) = match word.split_once(':') {
+            Some((name, cost)) => match cost.parse::<f32>() {
+                Ok(cost) if cost.is_finite() && cost > 0.0 => (name, Some(cost)),
+                _ => {
+                    return Err(syntax_error(
+                        lineno,
+                        column + name.chars().count() + 1,
+                        format!("invalid cost {:?}", cost),
+                    ))
+                }
+            },
+            None => (word, None),
+        };
+
+        let name = unescape(name).ok_or_else(|| syntax_error(lineno, column, "invalid escape"))?;
+        let Ok(move_type) = name.parse::<MoveType>();
+        tile.add_movetype(move_type.clone());
+        if let Some(cost) = cost {
+            tile.set_cost(move_type, cost);
+        }
+    }
+
+    Ok((glyph, tile))
+}
+
+/// Writes the header line for a glyph and its tile
+fn header_line(glyph: char, tile: &Tile) -> String {
+    let mut move_types: Vec<&MoveType> = tile.access().iter().collect();
+    move_types.sort_unstable();
+
+    let mut line = format!(
+        "{} = {} {}",
+        glyph,
+        escape(tile.kind()),
+        if tile.is_opaque() {
+            "opaque"
+        } else {
+            "transparent"
+        }
+    );
+
+    for move_type in move_types {
+        match tile.costs().get(move_type) {
+            Some(cost) => line += &format!(" {}:{}", escape(&move_type.to_string()), cost),
+            None => line += &format!(" {}", escape(&move_type.to_string())),
+        }
+    }
+
+    line
+}
+
+/// Implementation of plain text methods for Labyrinth2D
+impl Labyrinth2D {
+    /// Reads a map from its plain text representation.
+    ///
+    /// # Example Usage
+    /// ```rust
+    /// use bracket_pathfinding::prelude::*;
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// let map = Labyrinth2D::from_ascii("+ = door opaque Walk\n---\n#####\n#.+.#\n#####").unwrap();
+    ///
+    /// assert_eq!(map.tile_kind(Point::new(2, 1)), "door");
+    /// assert_eq!(map.tile_kind(Point::new(1, 1)), "floor");
+    /// ```
+    pub fn from_ascii(raw: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        let lines: Vec<&str> = raw.lines().collect();
+        let mut tiledict: HashMap<char, Tile> = default_glyphs().into_iter().collect();
+
+        // the grid starts after the separator, if there is a header at all
+        let grid_start = match lines.iter().position(|line| line.trim_end() == SEPARATOR) {
+            Some(separator) => {
+                for (i, line) in lines[..separator].iter().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let (glyph, tile) = parse_header_line(line, i + 1)?;
+                    tiledict.insert(glyph, tile);
+                }
+                separator + 1
+            }
+            None => 0,
+        };
+
+        // blank lines around the grid are ignored
+        let first = (grid_start..lines.len()).find(|&i| !lines[i].is_empty());
+        let first = first.ok_or_else(|| {
+            syntax_error(lines.len().max(1), 1, "expected at least one row of tiles")
+        })?;
+        let last = (first..lines.len())
+            .rfind(|&i| !lines[i].is_empty())
+            .unwrap_or(first);
+
+        let width = lines[first].chars().count();
+        let mut tiles = Vec::with_capacity(width * (last - first + 1));
+
+        for (i, line) in lines.iter().enumerate().take(last + 1).skip(first) {
+            let length = line.chars().count();
+            if length != width {
+                return Err(syntax_error(
+                    i + 1,
+                    length.min(width) + 1,
+                    format!("row has length {}, expected {}", length, width),
+                ));
+            }
+
+            for (column, glyph) in line.chars().enumerate() {
+                match tiledict.get(&glyph) {
+                    Some(tile) => tiles.push(tile.clone()),
+                    None => {
+                        return Err(syntax_error(
+                            i + 1,
+                            column + 1,
+                            format!("glyph {:?} has no tile", glyph),
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(Labyrinth2D::from_tiles(
+            tiles,
+            Point::new(width, last - first + 1),
+        ))
+    }
+
+    /// Writes the map as plain text, with a header for every distinct tile.
+    /// [`Labyrinth2D::from_ascii`] reads it back into the same map.
+    pub fn to_ascii(&self) -> String {
+        let (palette, _) = self.palette();
+        let glyphs = Labyrinth2D::ascii_glyphs(&palette);
+
+        let mut out = String::new();
+        for (glyph, tile) in glyphs.iter().zip(palette) {
+            out += &header_line(*glyph, tile);
+            out.push('\n');
+        }
+        out += SEPARATOR;
+        out.push('\n');
+        out += &self.to_string();
+        out.push('\n');
+
+        out
+    }
+
+    /// Picks the glyph of every tile of a palette. The default tiles keep
+    /// their usual glyphs, and other tiles get a letter or digit.
+    fn ascii_glyphs(palette: &[&Tile]) -> Vec<char> {
+        let defaults = default_glyphs();
+
+        // 0..9, a..z and A..Z first, then other letters as needed
+        let mut spare = ('0'..='9')
+            .chain('a'..='z')
+            .chain('A'..='Z')
+            .chain(('\u{c0}'..=char::MAX).filter(|c| c.is_alphanumeric()));
+
+        palette
+            .iter()
+            .map(|&tile| {
+                defaults
+                    .iter()
+                    .find(|(_, default)| default == tile)
+                    .map(|&(glyph, _)| glyph)
+                    .or_else(|| spare.next())
+                    .expect("ran out of glyphs")
+            })
+            .collect()
+    }
+}
+
+/// Prints the grid of the map, using the same glyphs as
+/// [`Labyrinth2D::to_ascii`].
+impl fmt::Display for Labyrinth2D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (palette, indices) = self.palette();
+        let glyphs = Labyrinth2D::ascii_glyphs(&palette);
+
+        let width = self.dimensions.x.max(1) as usize;
+        for (y, row) in indices.chunks(width).enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            let row: String = row.iter().map(|&i| glyphs[i]).collect();
+            write!(f, "{}", row)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_line_column(err: LabyrinthError, expected: (usize, usize)) {
+        match err {
+            LabyrinthError::Syntax { line, column, .. } => assert_eq!((line, column), expected),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn ascii_is_reversible() {
+        let mut map = Labyrinth2D::new_walled(6, 5);
+        map.set_tile_at(Point::new(1, 1), Tile::chasm());
+        map.set_tile_at(Point::new(2, 2), Tile::new("glass", true, []));
+
+        let mut mud = Tile::new("mud", false, [MoveType::Walk, MoveType::custom("dig")]);
+        mud.set_cost(MoveType::Walk, 2.5);
+        map.set_tile_at(Point::new(3, 3), mud);
+
+        let raw = map.to_ascii();
+
+        assert_eq!(Labyrinth2D::from_ascii(&raw).unwrap(), map);
+    }
+
+    #[test]
+    fn names_with_spaces_are_reversible() {
+        let mut map = Labyrinth2D::new_walled(5, 3);
+
+        let mut bog = Tile::new(
+            "deep water",
+            false,
+            [
+                MoveType::Swim,
+                MoveType::custom("wade slowly"),
+                MoveType::custom("a:b%"),
+            ],
+        );
+        bog.set_cost(MoveType::custom("wade slowly"), 4.0);
+        map.set_tile_at(Point::new(1, 1), bog);
+        map.set_tile_at(Point::new(2, 1), Tile::chasm());
+
+        let raw = map.to_ascii();
+        assert!(raw.contains("deep%20water"));
+        assert!(raw.lines().all(|line| line == line.trim_end()));
+
+        assert_eq!(Labyrinth2D::from_ascii(&raw).unwrap(), map);
+    }
+
+    #[test]
+    fn header_is_optional() {
+        let map = Labyrinth2D::from_ascii("\n###\n#.#\n#~#\n#+#\n").unwrap();
+
+        assert_eq!(map.dimensions(), Point::new(3, 4));
+        assert_eq!(map.tile_kind(Point::new(1, 2)), "water");
+        assert_eq!(map.tile_kind(Point::new(1, 3)), "door");
+    }
+
+    #[test]
+    fn display_prints_the_grid() {
+        let mut map = Labyrinth2D::new(3, 2);
+        map.set_tile_at(Point::new(1, 1), Tile::floor());
+        map.set_tile_at(Point::new(2, 1), Tile::new("door", true, [MoveType::Walk]));
+
+        assert_eq!(map.to_string(), "###\n#.0");
+    }
+
+    #[test]
+    fn header_errors_have_a_position() {
+        let err = Labyrinth2D::from_ascii("# = wall solid\n---\n#").unwrap_err();
+        assert_line_column(err, (1, 10));
+
+        let err = Labyrinth2D::from_ascii("\n, = mud transparent Walk:0\n---\n,").unwrap_err();
+        assert_line_column(err, (2, 26));
+
+        let err = Labyrinth2D::from_ascii("#: wall opaque\n---\n#").unwrap_err();
+        assert_line_column(err, (1, 2));
+
+        let err = Labyrinth2D::from_ascii("# = wall opaque Walk dig%2\n---\n#").unwrap_err();
+        assert_line_column(err, (1, 22));
+    }
+
+    #[test]
+    fn grid_errors_have_a_position() {
+        let err = Labyrinth2D::from_ascii("###\n#?#\n###").unwrap_err();
+        assert_line_column(err, (2, 2));
+
+        let err = Labyrinth2D::from_ascii("+ = door opaque\n---\n###\n##\n###").unwrap_err();
+        assert_line_column(err, (4, 3));
+    }
+}
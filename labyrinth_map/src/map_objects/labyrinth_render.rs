@@ -0,0 +1,348 @@
+//! Module for rendering a [`Labyrinth2D`] to an image, without a window.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use bracket_pathfinding::prelude::*;
+
+use super::Labyrinth2D;
+use crate::errors::LabyrinthError;
+
+/// An RGB colour
+pub type Rgb = [u8; 3];
+
+/// How strongly a heatmap covers the tiles closest to its starts
+const HEATMAP_STRENGTH: f32 = 0.75;
+
+/// Renders maps to RGB images, with a flat colour for every kind of tile and
+/// optional overlays on top.
+///
+/// The default tiles have colours of their own. Other kinds of tiles get a
+/// colour from [`MapRenderer::with_colour`], or a fixed colour picked from
+/// their name so that they can still be told apart.
+///
+/// Overlays are drawn in order: the heatmap, then room outlines, then the path.
+///
+/// # Example Usage
+/// ```rust,no_run
+/// use bracket_pathfinding::prelude::*;
+/// use labyrinth_map::prelude::*;
+///
+/// let map = Labyrinth2D::new_walled(20, 20);
+/// let path = map.find_path(Point::new(1, 1), Point::new(18, 18), [MoveType::Walk]);
+///
+/// MapRenderer::new()
+///     .with_tile_size(4)
+///     .with_colour("floor", [40, 40, 40])
+///     .with_path(&path, [255, 255, 0])
+///     .save_png(&map, "map.png")
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct MapRenderer {
+    tile_size: u32,
+    colours: HashMap<String, Rgb>,
+    heatmap: Option<(Vec<f32>, Rgb)>,
+    outlines: Vec<(HashSet<Point>, Rgb)>,
+    path: Option<(Vec<usize>, Rgb)>,
+}
+
+impl Default for MapRenderer {
+    fn default() -> Self {
+        MapRenderer::new()
+    }
+}
+
+/// Picks a fixed colour for a kind of tile from its name
+fn colour_of_kind(kind: &str) -> Rgb {
+    // FNV-1a, so the colour is the same on every run and platform
+    let hash = kind.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    });
+    let [r, g, b, _] = hash.to_le_bytes();
+
+    // keep away from black, which is used for chasms
+    [r | 0x40, g | 0x40, b | 0x40]
+}
+
+/// Mixes two colours, `amount` of the way from `base` to `top`
+fn blend(base: Rgb, top: Rgb, amount: f32) -> Rgb {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    [
+        mix(base[0], top[0]),
+        mix(base[1], top[1]),
+        mix(base[2], top[2]),
+    ]
+}
+
+impl MapRenderer {
+    /// Constructs a renderer drawing every tile as a single pixel
+    pub fn new() -> MapRenderer {
+        let colours = [
+            ("wall", [136, 136, 136]),
+            ("floor", [34, 34, 34]),
+            ("water", [32, 64, 192]),
+            ("lava", [224, 96, 16]),
+            ("chasm", [0, 0, 0]),
+            ("door", [139, 90, 43]),
+            ("open_door", [96, 64, 32]),
+            ("locked_door", [192, 160, 32]),
+        ];
+
+        MapRenderer {
+            tile_size: 1,
+            colours: colours
+                .into_iter()
+                .map(|(kind, colour)| (String::from(kind), colour))
+                .collect(),
+            heatmap: None,
+            outlines: vec![],
+            path: None,
+        }
+    }
+
+    /// Method for setting the width and height of every tile, in pixels
+    pub fn with_tile_size(mut self, tile_size: u32) -> MapRenderer {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// Method for setting the colour of a kind of tile
+    pub fn with_colour<T>(mut self, kind: T, colour: Rgb) -> MapRenderer
+    where
+        T: Into<String>,
+    {
+        self.colours.insert(kind.into().to_lowercase(), colour);
+        self
+    }
+
+    /// Method for drawing a path over the map, as a dot in every step
+    pub fn with_path(mut self, path: &NavigationPath, colour: Rgb) -> MapRenderer {
+        self.path = Some((path.steps.clone(), colour));
+        self
+    }
+
+    /// Method for shading the map by distance in a [`DijkstraMap`]. Tiles at
+    /// the starts are shaded the most, and unreachable tiles not at all.
+    pub fn with_heatmap(mut self, dijkstra: &DijkstraMap, colour: Rgb) -> MapRenderer {
+        self.heatmap = Some((dijkstra.map.clone(), colour));
+        self
+    }
+
+    /// Method for outlining a region of the map, such as the floor of a room.
+    /// With the `render` feature, daedalus outlines every room of a level
+    /// with `GeneratedLevel::outline_rooms`.
+    pub fn with_outline<T>(mut self, points: T, colour: Rgb) -> MapRenderer
+    where
+        T: IntoIterator<Item = Point>,
+    {
+        self.outlines.push((points.into_iter().collect(), colour));
+        self
+    }
+
+    /// Colour of a kind of tile
+    fn colour(&self, kind: &str) -> Rgb {
+        self.colours
+            .get(kind)
+            .copied()
+            .unwrap_or_else(|| colour_of_kind(kind))
+    }
+
+    /// Renders the map to RGB pixels, row by row, 3 bytes per pixel.
+    /// The image is `tile_size` times the dimensions of the map.
+    pub fn render(&self, map: &Labyrinth2D) -> Vec<u8> {
+        let dims = map.dimensions();
+        let size = self.tile_size as usize;
+        let (width, height) = (dims.x as usize * size, dims.y as usize * size);
+
+        // colour of every tile, with the heatmap over it
+        let mut tile_colours: Vec<Rgb> = map
+            .tiles()
+            .iter()
+            .map(|tile| self.colour(tile.kind()))
+            .collect();
+
+        if let Some((distances, colour)) = &self.heatmap {
+            let reachable = |d: &f32| *d < f32::MAX;
+            let furthest = distances
+                .iter()
+                .copied()
+                .filter(reachable)
+                .fold(0.0, f32::max);
+
+            for (tile_colour, distance) in tile_colours.iter_mut().zip(distances) {
+                if reachable(distance) {
+                    let closeness = 1.0 - distance / furthest.max(1.0);
+                    *tile_colour = blend(*tile_colour, *colour, HEATMAP_STRENGTH * closeness);
+                }
+            }
+        }
+
+        let mut pixels = vec![0; width * height * 3];
+        let mut paint = |x: usize, y: usize, colour: Rgb| {
+            let i = (y * width + x) * 3;
+            pixels[i..i + 3].copy_from_slice(&colour);
+        };
+
+        for (idx, colour) in tile_colours.iter().enumerate() {
+            let (tx, ty) = (idx % dims.x as usize, idx / dims.x as usize);
+            for y in ty * size..(ty + 1) * size {
+                for x in tx * size..(tx + 1) * size {
+                    paint(x, y, *colour);
+                }
+            }
+        }
+
+        // outlines are drawn on the inner edges of the region, wherever it
+        // borders a tile outside of it
+        let thickness = (size / 8).max(1);
+        for (region, colour) in self.outlines.iter() {
+            for pt in region.iter().filter(|pt| map.in_bounds(**pt)) {
+                let (x0, y0) = (pt.x as usize * size, pt.y as usize * size);
+                let outside = |dx, dy| !region.contains(&(*pt + Point::new(dx, dy)));
+
+                for i in 0..size {
+                    for t in 0..thickness {
+                        if outside(0, -1) {
+                            paint(x0 + i, y0 + t, *colour);
+                        }
+                        if outside(0, 1) {
+                            paint(x0 + i, y0 + size - 1 - t, *colour);
+                        }
+                        if outside(-1, 0) {
+                            paint(x0 + t, y0 + i, *colour);
+                        }
+                        if outside(1, 0) {
+                            paint(x0 + size - 1 - t, y0 + i, *colour);
+                        }
+                    }
+                }
+            }
+        }
+
+        // path steps are a square dot in the middle of the tile
+        if let Some((steps, colour)) = &self.path {
+            let dot = (size / 2).max(1);
+            let margin = (size - dot) / 2;
+            for &idx in steps.iter().filter(|&&idx| idx < map.size()) {
+                let (tx, ty) = (idx % dims.x as usize, idx / dims.x as usize);
+                for y in 0..dot {
+                    for x in 0..dot {
+                        paint(tx * size + margin + x, ty * size + margin + y, *colour);
+                    }
+                }
+            }
+        }
+
+        pixels
+    }
+
+    /// Renders the map to the contents of a PNG file
+    pub fn render_png(&self, map: &Labyrinth2D) -> Result<Vec<u8>, LabyrinthError> {
+        let dims = map.dimensions();
+        let pixels = self.render(map);
+
+        let mut out = vec![];
+        let mut encoder = png::Encoder::new(
+            &mut out,
+            dims.x as u32 * self.tile_size,
+            dims.y as u32 * self.tile_size,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let encode_error = |err: png::EncodingError| LabyrinthError::Serialize(err.to_string());
+        let mut writer = encoder.write_header().map_err(encode_error)?;
+        writer.write_image_data(&pixels).map_err(encode_error)?;
+        writer.finish().map_err(encode_error)?;
+
+        Ok(out)
+    }
+
+    /// Renders the map to a PNG file
+    pub fn save_png(&self, map: &Labyrinth2D, fname: &str) -> Result<(), LabyrinthError> {
+        fs::write(fname, self.render_png(map)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::map_objects::{MoveType, Tile};
+
+    fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> Rgb {
+        let i = (y * width + x) * 3;
+        [pixels[i], pixels[i + 1], pixels[i + 2]]
+    }
+
+    #[test]
+    fn tiles_are_drawn_with_their_colour() {
+        let mut map = Labyrinth2D::new_walled(4, 4);
+        map.set_tile_at(Point::new(1, 1), Tile::new("moss", false, [MoveType::Walk]));
+
+        let pixels = MapRenderer::new()
+            .with_tile_size(2)
+            .with_colour("moss", [0, 255, 0])
+            .render(&map);
+
+        assert_eq!(pixels.len(), 8 * 8 * 3);
+        assert_eq!(pixel(&pixels, 8, 0, 0), [136, 136, 136]);
+        assert_eq!(pixel(&pixels, 8, 2, 2), [0, 255, 0]);
+        assert_eq!(pixel(&pixels, 8, 3, 3), [0, 255, 0]);
+        assert_eq!(pixel(&pixels, 8, 4, 4), [34, 34, 34]);
+    }
+
+    #[test]
+    fn unknown_kinds_get_a_fixed_colour() {
+        let renderer = MapRenderer::new();
+
+        assert_eq!(renderer.colour("moss"), renderer.colour("moss"));
+        assert_ne!(renderer.colour("moss"), renderer.colour("rubble"));
+    }
+
+    #[test]
+    fn overlays_are_drawn() {
+        let map = Labyrinth2D::new_empty(5, 5);
+        let path = map.find_path(Point::new(0, 0), Point::new(4, 0), [MoveType::Walk]);
+        let dijkstra = map.dijkstra_map(&[Point::new(4, 4)], [MoveType::Walk]);
+        let room = (1..4).flat_map(|x| (1..4).map(move |y| Point::new(x, y)));
+
+        let pixels = MapRenderer::new()
+            .with_tile_size(8)
+            .with_heatmap(&dijkstra, [255, 0, 0])
+            .with_outline(room, [0, 0, 255])
+            .with_path(&path, [255, 255, 0])
+            .render(&map);
+        let width = 5 * 8;
+
+        // the path goes along the top row
+        assert_eq!(pixel(&pixels, width, 3 * 8 + 4, 4), [255, 255, 0]);
+
+        // the room is outlined on its edges, but not inside
+        assert_eq!(pixel(&pixels, width, 8, 12), [0, 0, 255]);
+        assert_ne!(pixel(&pixels, width, 20, 20), [0, 0, 255]);
+
+        // tiles near the start are redder than far away ones
+        let near = pixel(&pixels, width, 4 * 8 + 4, 4 * 8 + 4);
+        let far = pixel(&pixels, width, 4, 4 * 8 + 4);
+        assert!(near[0] > far[0]);
+    }
+
+    #[test]
+    fn png_has_the_right_size() {
+        let map = Labyrinth2D::new_walled(6, 3);
+        let raw = MapRenderer::new()
+            .with_tile_size(3)
+            .render_png(&map)
+            .unwrap();
+
+        let reader = png::Decoder::new(std::io::Cursor::new(raw))
+            .read_info()
+            .unwrap();
+
+        assert_eq!(reader.info().width, 18);
+        assert_eq!(reader.info().height, 9);
+    }
+}
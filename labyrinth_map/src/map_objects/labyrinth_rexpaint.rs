@@ -0,0 +1,410 @@
+//! Module for reading and writing maps as REXPaint `.xp` images.
+//!
+//! An `.xp` file is a gzip-compressed list of layers, each holding a glyph,
+//! a foreground colour and a background colour for every cell, stored
+//! column by column.
+
+use std::fs;
+use std::io::{Read, Write};
+
+use bracket_pathfinding::prelude::*;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::{Labyrinth2D, Tile};
+use crate::errors::LabyrinthError;
+
+/// Format version written at the start of every `.xp` file by REXPaint
+const XP_VERSION: i32 = -1;
+
+/// Bytes taken by a single cell in an `.xp` file
+const CELL_SIZE: usize = 10;
+
+/// A single cell of a REXPaint image: a CP437 glyph code and its foreground
+/// and background colours, as `[r, g, b]`.
+///
+/// # Example Usage
+/// ```rust
+/// use labyrinth_map::prelude::*;
+///
+/// let wall = XpCell::new(b'#', [200, 200, 200], [0, 0, 0]);
+/// assert!(!wall.is_transparent());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct XpCell {
+    /// CP437 code of the glyph
+    pub glyph: u32,
+    /// Foreground colour
+    pub fg: [u8; 3],
+    /// Background colour
+    pub bg: [u8; 3],
+}
+
+impl XpCell {
+    /// Background colour REXPaint uses for empty cells of a layer
+    pub const TRANSPARENT_BG: [u8; 3] = [255, 0, 255];
+
+    /// Constructs a cell from a glyph and its colours
+    pub fn new<T: Into<u32>>(glyph: T, fg: [u8; 3], bg: [u8; 3]) -> XpCell {
+        XpCell {
+            glyph: glyph.into(),
+            fg,
+            bg,
+        }
+    }
+
+    /// Whether or not the cell is empty, letting lower layers show through
+    pub fn is_transparent(&self) -> bool {
+        self.bg == XpCell::TRANSPARENT_BG
+    }
+}
+
+/// Reads a little-endian i32 from an `.xp` file
+fn read_i32(bytes: &[u8], offset: &mut usize) -> Result<i32, LabyrinthError> {
+    let chunk = bytes
+        .get(*offset..*offset + 4)
+        .ok_or_else(|| LabyrinthError::Parse(String::from("Unexpected end of .xp data")))?;
+    *offset += 4;
+
+    Ok(i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+/// Reads a layer dimension, which must be positive
+fn read_dimension(bytes: &[u8], offset: &mut usize) -> Result<usize, LabyrinthError> {
+    match read_i32(bytes, offset)? {
+        dim if dim > 0 => Ok(dim as usize),
+        dim => Err(LabyrinthError::Parse(format!(
+            "Invalid .xp layer dimension {}",
+            dim
+        ))),
+    }
+}
+
+/// Decompresses an `.xp` file and flattens its layers into a single grid of
+/// cells, row by row. Every cell is taken from the topmost layer where it is
+/// not transparent, or from the bottom layer if it is transparent in all.
+fn parse_xp(compressed: &[u8]) -> Result<(Point, Vec<XpCell>), LabyrinthError> {
+    let mut bytes = vec![];
+    GzDecoder::new(compressed)
+        .read_to_end(&mut bytes)
+        .map_err(|err| LabyrinthError::Parse(err.to_string()))?;
+
+    let mut offset = 0;
+
+    // older files don't start with a version, just the layer count
+    let mut layer_count = read_i32(&bytes, &mut offset)?;
+    if layer_count < 0 {
+        layer_count = read_i32(&bytes, &mut offset)?;
+    }
+    if layer_count < 1 {
+        return Err(LabyrinthError::Parse(String::from(
+            ".xp image has no layers",
+        )));
+    }
+
+    let mut dimensions = Point::zero();
+    let mut cells = vec![];
+
+    for layer in 0..layer_count {
+        let width = read_dimension(&bytes, &mut offset)?;
+        let height = read_dimension(&bytes, &mut offset)?;
+
+        // the size is checked against the data left before anything is
+        // allocated, so a bad header can't ask for a huge map
+        let layer_size = width
+            .checked_mul(height)
+            .and_then(|cells| cells.checked_mul(CELL_SIZE))
+            .filter(|&size| size <= bytes.len() - offset)
+            .ok_or_else(|| {
+                LabyrinthError::Parse(format!(
+                    "Layer {} is {}x{}, more than the .xp data holds",
+                    layer, width, height
+                ))
+            })?;
+
+        if layer == 0 {
+            dimensions = Point::new(width, height);
+            cells = vec![XpCell::new(0u32, [0; 3], XpCell::TRANSPARENT_BG); width * height];
+        } else if Point::new(width, height) != dimensions {
+            return Err(LabyrinthError::Parse(format!(
+                "Layer {} is {}x{}, expected {}x{}",
+                layer, width, height, dimensions.x, dimensions.y
+            )));
+        }
+
+        let data = &bytes[offset..offset + layer_size];
+        offset += layer_size;
+
+        // cells are stored column by column
+        for (i, raw) in data.chunks(CELL_SIZE).enumerate() {
+            let cell = XpCell::new(
+                u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]),
+                [raw[4], raw[5], raw[6]],
+                [raw[7], raw[8], raw[9]],
+            );
+
+            if layer == 0 || !cell.is_transparent() {
+                let (x, y) = (i / height, i % height);
+                cells[y * width + x] = cell;
+            }
+        }
+    }
+
+    Ok((dimensions, cells))
+}
+
+/// Compresses a single layer of cells, given row by row, into an `.xp` file
+fn write_xp(dimensions: Point, cells: &[XpCell]) -> Result<Vec<u8>, LabyrinthError> {
+    let (width, height) = (dimensions.x as usize, dimensions.y as usize);
+
+    let mut bytes = Vec::with_capacity(16 + cells.len() * CELL_SIZE);
+    for header in [XP_VERSION, 1, dimensions.x, dimensions.y] {
+        bytes.extend_from_slice(&header.to_le_bytes());
+    }
+
+    for x in 0..width {
+        for y in 0..height {
+            let cell = &cells[y * width + x];
+            bytes.extend_from_slice(&cell.glyph.to_le_bytes());
+            bytes.extend_from_slice(&cell.fg);
+            bytes.extend_from_slice(&cell.bg);
+        }
+    }
+
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&bytes)?;
+    Ok(encoder.finish()?)
+}
+
+/// Implementation of REXPaint-related methods for Labyrinth2D
+impl Labyrinth2D {
+    /// Reads a map from a REXPaint `.xp` file, turning every cell into a
+    /// [`Tile`] through `tiles`.
+    ///
+    /// Layers are flattened, so a cell comes from the topmost layer where it
+    /// isn't [transparent](XpCell::is_transparent).
+    /// Cells for which `tiles` returns `None` are reported as a
+    /// [`LabyrinthError::UnknownGlyph`].
+    ///
+    /// # Example Usage
+    /// ```rust,no_run
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// // only the glyph matters here, not the colours
+    /// let map = Labyrinth2D::read_xp("prefab.xp", |cell| match cell.glyph as u8 {
+    ///     b'#' => Some(Tile::wall()),
+    ///     b'.' => Some(Tile::floor()),
+    ///     b'~' => Some(Tile::water()),
+    ///     _ => None,
+    /// });
+    /// ```
+    pub fn read_xp<F>(fname: &str, tiles: F) -> Result<Labyrinth2D, LabyrinthError>
+    where
+        F: FnMut(XpCell) -> Option<Tile>,
+    {
+        let raw_data = fs::read(fname)?;
+
+        Labyrinth2D::read_xp_from_bytes(&raw_data, tiles)
+    }
+
+    /// Reads a map from the contents of a REXPaint `.xp` file.
+    /// See [`Labyrinth2D::read_xp`].
+    pub fn read_xp_from_bytes<F>(raw: &[u8], mut tiles: F) -> Result<Labyrinth2D, LabyrinthError>
+    where
+        F: FnMut(XpCell) -> Option<Tile>,
+    {
+        let (dimensions, cells) = parse_xp(raw)?;
+        let width = dimensions.x as usize;
+
+        let tiles = cells
+            .into_iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                tiles(cell).ok_or_else(|| LabyrinthError::UnknownGlyph {
+                    glyph: cell.glyph.to_string(),
+                    position: Point::new(i % width, i / width),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Labyrinth2D::from_tiles(tiles, dimensions))
+    }
+
+    /// Writes the map to a single layer REXPaint `.xp` file, drawing every
+    /// [`Tile`] with the cell given by `cells`.
+    ///
+    /// Tiles for which `cells` returns `None` are reported as a
+    /// [`LabyrinthError::UnmappedTile`].
+    ///
+    /// # Example Usage
+    /// ```rust,no_run
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// let map = Labyrinth2D::new_walled(10, 10);
+    /// map.dump_xp("map.xp", |tile| match tile.kind().as_str() {
+    ///     "wall" => Some(XpCell::new(b'#', [160, 160, 160], [0, 0, 0])),
+    ///     "floor" => Some(XpCell::new(b'.', [80, 80, 80], [0, 0, 0])),
+    ///     _ => None,
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn dump_xp<F>(&self, fname: &str, cells: F) -> Result<(), LabyrinthError>
+    where
+        F: FnMut(&Tile) -> Option<XpCell>,
+    {
+        fs::write(fname, self.to_xp_bytes(cells)?)?;
+        Ok(())
+    }
+
+    /// Turns the map into the contents of a REXPaint `.xp` file.
+    /// See [`Labyrinth2D::dump_xp`].
+    pub fn to_xp_bytes<F>(&self, mut cells: F) -> Result<Vec<u8>, LabyrinthError>
+    where
+        F: FnMut(&Tile) -> Option<XpCell>,
+    {
+        let width = self.dimensions.x as usize;
+
+        let cells = self
+            .tiles
+            .iter()
+            .enumerate()
+            .map(|(i, tile)| {
+                cells(tile).ok_or_else(|| LabyrinthError::UnmappedTile {
+                    kind: tile.kind().to_owned(),
+                    position: Point::new(i % width, i / width),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        write_xp(self.dimensions, &cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell_of(tile: &Tile) -> Option<XpCell> {
+        match tile.kind().as_str() {
+            "wall" => Some(XpCell::new(b'#', [255, 255, 255], [0, 0, 0])),
+            "floor" => Some(XpCell::new(b'.', [128, 128, 128], [0, 0, 0])),
+            "water" => Some(XpCell::new(b'~', [0, 0, 255], [0, 0, 64])),
+            _ => None,
+        }
+    }
+
+    fn tile_of(cell: XpCell) -> Option<Tile> {
+        match cell.glyph as u8 {
+            b'#' => Some(Tile::wall()),
+            b'.' => Some(Tile::floor()),
+            b'~' => Some(Tile::water()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn xp_is_reversible() {
+        let mut map = Labyrinth2D::new_walled(6, 4);
+        map.set_tile_at(Point::new(1, 2), Tile::water());
+
+        let raw = map.to_xp_bytes(cell_of).unwrap();
+
+        assert_eq!(Labyrinth2D::read_xp_from_bytes(&raw, tile_of).unwrap(), map);
+    }
+
+    #[test]
+    fn layers_are_flattened() {
+        let floor = XpCell::new(b'.', [128, 128, 128], [0, 0, 0]);
+        let water = XpCell::new(b'~', [0, 0, 255], [0, 0, 64]);
+        let empty = XpCell::new(0u32, [0, 0, 0], XpCell::TRANSPARENT_BG);
+
+        // two layers of 2x1 cells, the top one only covering the right cell
+        let mut bytes = vec![];
+        for header in [XP_VERSION, 2] {
+            bytes.extend_from_slice(&header.to_le_bytes());
+        }
+        for layer in [[floor, floor], [empty, water]] {
+            bytes.extend_from_slice(&2i32.to_le_bytes());
+            bytes.extend_from_slice(&1i32.to_le_bytes());
+            for cell in layer {
+                bytes.extend_from_slice(&cell.glyph.to_le_bytes());
+                bytes.extend_from_slice(&cell.fg);
+                bytes.extend_from_slice(&cell.bg);
+            }
+        }
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        let raw = encoder.finish().unwrap();
+
+        let map = Labyrinth2D::read_xp_from_bytes(&raw, tile_of).unwrap();
+
+        assert_eq!(map.tile_kind(Point::new(0, 0)), "floor");
+        assert_eq!(map.tile_kind(Point::new(1, 0)), "water");
+    }
+
+    #[test]
+    fn unmapped_tiles_are_an_error() {
+        let mut map = Labyrinth2D::new_walled(4, 4);
+        map.set_tile_at(Point::new(2, 1), Tile::lava());
+
+        match map.to_xp_bytes(cell_of).unwrap_err() {
+            LabyrinthError::UnmappedTile { kind, position } => {
+                assert_eq!(kind, "lava");
+                assert_eq!(position, Point::new(2, 1));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn unknown_glyphs_are_an_error() {
+        let map = Labyrinth2D::new_walled(4, 4);
+        let raw = map.to_xp_bytes(cell_of).unwrap();
+
+        let err = Labyrinth2D::read_xp_from_bytes(&raw, |cell| {
+            (cell.glyph == u32::from(b'.')).then(Tile::floor)
+        })
+        .unwrap_err();
+
+        assert!(matches!(err, LabyrinthError::UnknownGlyph { .. }));
+    }
+
+    /// Compresses the header of a single layer .xp file, with some cells
+    fn xp_with_header(width: i32, height: i32, cells: usize) -> Vec<u8> {
+        let mut bytes = vec![];
+        for header in [XP_VERSION, 1, width, height] {
+            bytes.extend_from_slice(&header.to_le_bytes());
+        }
+        bytes.extend(std::iter::repeat_n(0, cells * CELL_SIZE));
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn bad_headers_are_a_parse_error() {
+        for (width, height, cells) in [
+            (i32::MAX, i32::MAX, 4),
+            (100_000, 100_000, 4),
+            (4, 4, 15),
+            (0, 4, 0),
+            (4, -1, 0),
+        ] {
+            let raw = xp_with_header(width, height, cells);
+            let err = Labyrinth2D::read_xp_from_bytes(&raw, tile_of).unwrap_err();
+
+            assert!(matches!(err, LabyrinthError::Parse(_)));
+        }
+    }
+
+    #[test]
+    fn garbage_is_a_parse_error() {
+        let err = Labyrinth2D::read_xp_from_bytes(b"not an xp file", tile_of).unwrap_err();
+
+        assert!(matches!(err, LabyrinthError::Parse(_)));
+    }
+}
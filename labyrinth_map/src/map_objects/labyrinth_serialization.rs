@@ -1,170 +1,393 @@
 //! Module for serialization-related code
 
-use super::{Labyrinth2D, MoveType, Point, Tile};
+use super::{Labyrinth2D, Labyrinth3D, Link, Neighborhood, Point, RawTile, Tile};
+use crate::errors::LabyrinthError;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::fmt;
+use std::fs;
+use std::io::Write;
 
-use serde::de::{Deserialize, Deserializer, Error, MapAccess, Visitor};
-use serde::ser::{Serialize, SerializeStruct};
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use ron::from_str;
 use ron::ser::{to_string_pretty, PrettyConfig};
 
+#[cfg(feature = "tiled")]
+mod tiled;
+
+/// Characters used for the tokens of custom tiles in a mapstring
+const TOKEN_CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Token standing for a tile in a mapstring.
+///
+/// Written as a char when it is a single character, which is the only kind
+/// of token older maps have, and as a string otherwise.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Token(String);
+
+impl Token {
+    /// Length of the token, in characters
+    fn len(&self) -> usize {
+        self.0.chars().count()
+    }
+}
+
+impl Serialize for Token {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut chars = self.0.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => serializer.serialize_char(c),
+            _ => serializer.serialize_str(&self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Token {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TokenVisitor;
+
+        impl de::Visitor<'_> for TokenVisitor {
+            type Value = Token;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a char or string symbol")
+            }
+
+            fn visit_char<E: de::Error>(self, value: char) -> Result<Token, E> {
+                Ok(Token(value.to_string()))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Token, E> {
+                Ok(Token(value.to_owned()))
+            }
+        }
+
+        deserializer.deserialize_any(TokenVisitor)
+    }
+}
+
+/// Serialized form of a [`Labyrinth2D`], before being checked and unpacked
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Labyrinth2D")]
+struct RawLabyrinth2D {
+    mapstring: Vec<String>,
+    tiledict: BTreeMap<Token, RawTile>,
+    // older maps don't store a neighborhood
+    #[serde(default)]
+    neighborhood: Neighborhood,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    zones: Vec<RawZone>,
+}
+
+/// Serialized form of a zone of a [`Labyrinth2D`]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Zone")]
+struct RawZone {
+    label: String,
+    // horizontal runs of tiles in the zone, as (x, y, length)
+    runs: Vec<(usize, usize, usize)>,
+}
+
+/// Serialized form of a [`Labyrinth3D`], before being checked and unpacked
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Labyrinth3D")]
+struct RawLabyrinth3D {
+    levels: Vec<RawLabyrinth2D>,
+    #[serde(default)]
+    links: Vec<Link>,
+}
+
+/// Writes a serialized map to a file
+fn write_ron<T: Serialize>(raw: &T, fname: &str) -> Result<(), LabyrinthError> {
+    let repr = to_string_pretty(raw, PrettyConfig::new())
+        .map_err(|err| LabyrinthError::Serialize(err.to_string()))?;
+    let mut file = fs::File::create(fname)?;
+    file.write_all(repr.as_bytes())?;
+    Ok(())
+}
+
+/// Parses a serialized map from a string
+fn parse_ron<T: DeserializeOwned>(raw: &str) -> Result<T, LabyrinthError> {
+    from_str(raw).map_err(|err| LabyrinthError::Parse(err.to_string()))
+}
+
 // /// Implemenation of serialization-related methods for Labyrinth2D
 impl Labyrinth2D {
     // ------------------ Serialization API --------------------------
-    // Don't know if this is even necessary
-    // TODO: proper error handling
-    pub fn dump_ron(&self, fname: &str) -> Result<(), String> {
-        use std::fs;
-        use std::io::Write;
-
-        let repr = to_string_pretty(&self, PrettyConfig::new())
-            .map_err(|_| "Unable to serialize".to_string())?;
-        let mut file = fs::File::create(fname).map_err(|_| "Unable to create file")?;
-        file.write(repr.as_bytes())
-            .map_err(|_| "Unable to write to file")?;
-        Ok(())
+    /// Writes the map to a RON file.
+    pub fn dump_ron(&self, fname: &str) -> Result<(), LabyrinthError> {
+        write_ron(&self.compress(), fname)
     }
 
-    pub fn read_ron(fname: &str) -> Result<Labyrinth2D, String> {
-        use std::fs;
-
-        let raw_data = &fs::read_to_string(fname)
-            .map_err(|_| format!("Could not open file {:?}", fname))
-            .unwrap();
+    /// Reads a map from a RON file.
+    pub fn read_ron(fname: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        let raw_data = fs::read_to_string(fname)?;
 
-        from_str(raw_data).map_err(|msg| format!("Deserialize failed!: {}", msg))
+        Labyrinth2D::read_ron_from_str(&raw_data)
     }
 
-    // TODO: figure out the serialization interface? Do I even need one?
-    pub fn read_ron_from_str(raw: &str) -> Result<Labyrinth2D, String> {
-        from_str(raw).map_err(|msg| format!("Deserialize failed!: {}", msg))
+    /// Reads a map from a RON string.
+    pub fn read_ron_from_str(raw: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        Labyrinth2D::unpack(parse_ron(raw)?)
     }
 
     /// Constructs a mapstring and tiledict representation of the internal tiles
-    fn compress(&self) -> (Vec<String>, HashMap<char, Tile>) {
-        let mut mapstr = vec![];
-        let mut tiledict = HashMap::new();
-
-        // add default values
-        tiledict.insert(String::from("wall"), ('#', Tile::wall()));
-        tiledict.insert(String::from("floor"), ('.', Tile::floor()));
-        tiledict.insert(String::from("water"), ('~', Tile::water()));
-        tiledict.insert(String::from("lava"), ('!', Tile::lava()));
-        tiledict.insert(String::from("chasm"), (' ', Tile::chasm()));
-
-        // These will be the possible keys/representations for the mapstring
-        // goes from 0..9, a..z, A..Z. Hopefully that should be enough
-        let mut key_iter = (b'0'..=b'9')
-            .chain(b'a'..b'z')
-            .chain(b'A'..=b'Z')
-            .map(|c| c as char);
-
-        // TODO: Make this better maybe?
-        let mut tiles_copy: Vec<Tile> = self.tiles.clone();
-        tiles_copy.dedup();
-
-        for tile in tiles_copy.iter() {
-            if !tiledict.values().any(|(_, val): &(char, Tile)| val == tile) {
-                // find key to use
-                let newkey = match key_iter.next() {
-                    Some(key) => key,
-                    None => {
-                        // TODO: proper error handling here
-                        panic!("too many custom tiles!")
-                    }
-                };
+    fn compress(&self) -> RawLabyrinth2D {
+        let (palette, indices) = self.palette();
+        let tokens = Labyrinth2D::palette_tokens(&palette);
+
+        let width = self.dimensions.x.max(1) as usize;
+        let mapstring = indices
+            .chunks(width)
+            .map(|row| row.iter().map(|&i| tokens[i].as_str()).collect())
+            .collect();
+
+        let tiledict = tokens
+            .into_iter()
+            .map(Token)
+            .zip(palette.into_iter().map(RawTile::from))
+            .collect();
+
+        RawLabyrinth2D {
+            mapstring,
+            tiledict,
+            neighborhood: self.neighborhood,
+            zones: self.compress_zones(),
+        }
+    }
 
-                let newtile = tile.clone();
-                // add tile to tiledict with the key
-                tiledict.insert(newtile.kind().to_owned(), (newkey, newtile));
+    /// Constructs the runs of tiles of every zone, row by row
+    fn compress_zones(&self) -> Vec<RawZone> {
+        let mut zones: Vec<RawZone> = self
+            .zone_labels
+            .iter()
+            .map(|label| RawZone {
+                label: label.clone(),
+                runs: vec![],
+            })
+            .collect();
+
+        let width = self.dimensions.x.max(1) as usize;
+        for (y, row) in self.zones.chunks(width).enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let length = row[x..].iter().take_while(|&&zone| zone == row[x]).count();
+                if let Some(zone) = row[x] {
+                    zones[zone].runs.push((x, y, length));
+                }
+                x += length;
             }
         }
 
-        for row in self.rows() {
-            // TODO: check if row is complete here
-
-            let mut mapstrrow = String::new();
-            for tile in row {
-                // get the representation of the tile
-                let kind: &str = &tile.kind();
+        zones
+    }
 
-                let to_push = match tiledict.get(kind) {
-                    Some((c, _)) => *c,
-                    None => {
-                        panic!("tile not found!")
-                    }
-                };
+    /// Picks the tokens that represent each tile of a palette in the mapstring.
+    ///
+    /// The default tiles keep their usual symbols, and other tiles get one of
+    /// 0..9, a..z, A..Z. If that is not enough, every tile gets a fixed-width
+    /// token of several of those characters instead.
+    fn palette_tokens(palette: &[&Tile]) -> Vec<String> {
+        let defaults = [
+            (Tile::wall(), '#'),
+            (Tile::floor(), '.'),
+            (Tile::water(), '~'),
+            (Tile::lava(), '!'),
+            (Tile::chasm(), ' '),
+            (Tile::door(), '+'),
+            (Tile::open_door(), '\''),
+        ];
+        let default_glyph = |tile: &Tile| {
+            defaults
+                .iter()
+                .find(|(default, _)| default == tile)
+                .map(|&(_, glyph)| glyph)
+        };
 
-                mapstrrow.push(to_push);
-            }
-            mapstr.push(mapstrrow);
+        let custom = palette
+            .iter()
+            .filter(|tile| default_glyph(tile).is_none())
+            .count();
+
+        if custom <= TOKEN_CHARS.len() {
+            let mut glyphs = TOKEN_CHARS.iter().map(|&c| c as char);
+            return palette
+                .iter()
+                .map(|tile| {
+                    default_glyph(tile)
+                        .or_else(|| glyphs.next())
+                        .unwrap()
+                        .to_string()
+                })
+                .collect();
         }
 
-        // generate the final char:tile dictionary
-        let mut finaltiledict = HashMap::new();
-        for (c, tile) in tiledict.into_values() {
-            finaltiledict.insert(c, tile);
+        // enough digits to number every tile in base 62
+        let mut width = 1;
+        while TOKEN_CHARS.len().pow(width) < palette.len() {
+            width += 1;
         }
 
-        (mapstr, finaltiledict)
+        (0..palette.len())
+            .map(|mut i| {
+                let mut token = vec![0; width as usize];
+                for digit in token.iter_mut().rev() {
+                    *digit = TOKEN_CHARS[i % TOKEN_CHARS.len()];
+                    i /= TOKEN_CHARS.len();
+                }
+                String::from_utf8(token).unwrap()
+            })
+            .collect()
     }
 
     /// Constructs a Labyrinth2D from a mapstring and tiledict representation
-    fn unpack(
-        mapstring: Vec<String>,
-        tiledict: HashMap<char, Tile>,
-    ) -> Result<Labyrinth2D, String> {
+    fn unpack(raw: RawLabyrinth2D) -> Result<Labyrinth2D, LabyrinthError> {
+        let RawLabyrinth2D {
+            mapstring,
+            tiledict,
+            neighborhood,
+            zones,
+        } = raw;
+
+        // every token must have the same number of characters.
+        // Older maps only have single character tokens.
+        let tiledict = tiledict
+            .into_iter()
+            .map(|(token, tile)| Ok((token, Tile::try_from(tile)?)))
+            .collect::<Result<BTreeMap<Token, Tile>, LabyrinthError>>()?;
+
+        let token_width = tiledict.keys().next().map_or(1, |token| token.len());
+        if token_width == 0 || tiledict.keys().any(|token| token.len() != token_width) {
+            return Err(LabyrinthError::Parse(String::from(
+                "Tiledict symbols must all have the same, non-zero length",
+            )));
+        }
+
         // check if mapstring was valid
         // All rows must have same length
-        if mapstring.iter().map(|str| str.len()).min()
-            != mapstring.iter().map(|str| str.len()).max()
+        let rows: Vec<Vec<char>> = mapstring.iter().map(|row| row.chars().collect()).collect();
+
+        let width = rows.first().map_or(0, |row| row.len());
+        if let Some((row, found)) = rows
+            .iter()
+            .map(|row| row.len())
+            .enumerate()
+            .find(|&(_, len)| len != width)
         {
-            return Err(String::from("Row lengths do not match!"));
+            return Err(LabyrinthError::RaggedRows {
+                row,
+                expected: width,
+                found,
+            });
+        }
+
+        if !width.is_multiple_of(token_width) {
+            return Err(LabyrinthError::Parse(format!(
+                "Row length {} is not a multiple of the symbol length {}",
+                width, token_width
+            )));
         }
 
-        // then construct the Vec<Tiles> from the joined mapstr and the dict
-        let width = mapstring[1].len() as i32;
-        let height = mapstring.len() as i32;
+        // then construct the Vec<Tiles> from the mapstr and the dict
         let dimensions = Point {
-            x: width,
-            y: height,
+            x: (width / token_width) as i32,
+            y: rows.len() as i32,
         };
 
-        let joinedstr = mapstring.join("");
+        let mut tiles = Vec::with_capacity((dimensions.x * dimensions.y) as usize);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, token) in row.chunks(token_width).enumerate() {
+                let token = Token(token.iter().collect());
+                match tiledict.get(&token) {
+                    Some(tile) => tiles.push(tile.clone()),
+                    None => {
+                        return Err(LabyrinthError::UnknownGlyph {
+                            glyph: token.0,
+                            position: Point::new(x, y),
+                        })
+                    }
+                }
+            }
+        }
+
+        let mut map = Labyrinth2D::from_tiles(tiles, dimensions);
+        map.neighborhood = neighborhood;
+
+        for RawZone { label, runs } in zones {
+            let zone = map.add_zone(label);
+            for (x, y, length) in runs {
+                if y >= rows.len() || x + length > dimensions.x as usize {
+                    return Err(LabyrinthError::Parse(format!(
+                        "Zone {} has tiles outside of the map",
+                        zone
+                    )));
+                }
+                map.add_to_zone(zone, (x..x + length).map(|x| Point::new(x, y)));
+            }
+        }
 
-        let tiles = joinedstr
-            .chars()
-            .map(|c| tiledict.get(&c).cloned())
-            .collect::<Option<Vec<Tile>>>();
+        Ok(map)
+    }
+}
+
+/// Implementation of serialization-related methods for Labyrinth3D
+impl Labyrinth3D {
+    /// Writes the map, with every level and link, to a RON file.
+    pub fn dump_ron(&self, fname: &str) -> Result<(), LabyrinthError> {
+        write_ron(&self.compress(), fname)
+    }
+
+    /// Reads a map from a RON file.
+    pub fn read_ron(fname: &str) -> Result<Labyrinth3D, LabyrinthError> {
+        let raw_data = fs::read_to_string(fname)?;
+
+        Labyrinth3D::read_ron_from_str(&raw_data)
+    }
 
-        if tiles.is_none() {
-            return Err(String::from("Tiledict incomplete, could not construct map"));
+    /// Reads a map from a RON string.
+    pub fn read_ron_from_str(raw: &str) -> Result<Labyrinth3D, LabyrinthError> {
+        Labyrinth3D::unpack(parse_ron(raw)?)
+    }
+
+    /// Constructs the serialized form of every level and link
+    fn compress(&self) -> RawLabyrinth3D {
+        RawLabyrinth3D {
+            levels: self.levels().iter().map(Labyrinth2D::compress).collect(),
+            links: self.links().clone(),
+        }
+    }
+
+    /// Rebuilds a Labyrinth3D, validating its levels and links
+    fn unpack(raw: RawLabyrinth3D) -> Result<Labyrinth3D, LabyrinthError> {
+        let levels = raw
+            .levels
+            .into_iter()
+            .map(Labyrinth2D::unpack)
+            .collect::<Result<_, _>>()?;
+
+        let mut labyrinth = Labyrinth3D::from_levels(levels)?;
+        for link in raw.links {
+            labyrinth.add_link(link)?;
         }
 
-        Ok(Labyrinth2D {
-            tiles: tiles.unwrap(),
-            dimensions,
-            _filter: vec![],
-        })
+        Ok(labyrinth)
     }
 }
 
 impl Serialize for Labyrinth2D {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
-        S: serde::Serializer,
+        S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Labyrinth2D", 2)?;
-
-        let (mapstring, dict) = self.compress();
-
-        state.serialize_field("mapstring", &mapstring)?;
-        state.serialize_field("tiledict", &dict)?;
-
-        state.end()
+        self.compress().serialize(serializer)
     }
 }
 
@@ -173,68 +396,36 @@ impl<'de> Deserialize<'de> for Labyrinth2D {
     where
         D: Deserializer<'de>,
     {
-        #[derive(serde::Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
-        enum Field {
-            Mapstring,
-            Tiledict,
-            // _Filter
-        }
-
-        struct Labyrinth2DVisitor;
-
-        impl<'de> Visitor<'de> for Labyrinth2DVisitor {
-            type Value = Labyrinth2D;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("struct Labyrinth2D")
-            }
-
-            fn visit_map<V>(self, mut map: V) -> Result<Labyrinth2D, V::Error>
-            where
-                V: MapAccess<'de>,
-            {
-                let mut mapstr: Option<Vec<String>> = None;
-                let mut tiledict: Option<HashMap<char, Tile>> = None;
-
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::Mapstring => {
-                            if mapstr.is_some() {
-                                return Err(Error::duplicate_field("mapstring"));
-                            }
-                            mapstr = Some(map.next_value()?);
-                        }
-                        Field::Tiledict => {
-                            if tiledict.is_some() {
-                                return Err(Error::duplicate_field("tiledict"));
-                            }
-                            tiledict = Some(map.next_value()?);
-                        }
-                    }
-                }
-                let mapstr = mapstr.ok_or_else(|| Error::missing_field("mapstring"))?;
-                let tiledict = tiledict.ok_or_else(|| Error::missing_field("tiledict"))?;
+        Labyrinth2D::unpack(RawLabyrinth2D::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
 
-                Ok(Labyrinth2D::unpack(mapstr, tiledict)
-                    .map_err(|msg| Error::custom(format!("Unpack: {}", msg)))?)
-            }
-        }
+impl Serialize for Labyrinth3D {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.compress().serialize(serializer)
+    }
+}
 
-        const FIELDS: &'static [&'static str] = &["mapstring", "tiledict"];
-        deserializer.deserialize_struct("Labyrinth2D", FIELDS, Labyrinth2DVisitor)
+impl<'de> Deserialize<'de> for Labyrinth3D {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Labyrinth3D::unpack(RawLabyrinth3D::deserialize(deserializer)?).map_err(de::Error::custom)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    use ron;
+    use crate::prelude::MoveType;
 
     fn serialize_then_deserialize(map: &Labyrinth2D) -> Result<Labyrinth2D, ron::Error> {
         let mapstr = ron::to_string(map)?;
-        ron::from_str(&mapstr)
+        ron::from_str(&mapstr).map_err(|err| err.code)
     }
 
     fn assert_reversible(map: &Labyrinth2D) {
@@ -248,6 +439,7 @@ mod tests {
         assert_reversible(&Labyrinth2D::new(3, 3));
         assert_reversible(&Labyrinth2D::new_empty(3, 3));
         assert_reversible(&Labyrinth2D::new_walled(3, 3));
+        assert_reversible(&Labyrinth2D::new_empty(5, 1));
 
         //customized maps
         let center = Point { x: 1, y: 1 };
@@ -261,5 +453,238 @@ mod tests {
         map_with_custom_movetype.set_tile_at(center, phasewall);
 
         assert_reversible(&map_with_custom_movetype);
+
+        let mut map_with_diagonals = Labyrinth2D::new_walled(3, 3);
+        map_with_diagonals.set_neighborhood(Neighborhood::DiagonalNoCornerCutting);
+
+        assert_reversible(&map_with_diagonals);
+    }
+
+    #[test]
+    fn zones_are_reversible() {
+        let mut map = Labyrinth2D::new_walled(6, 4);
+        let hall = map.add_zone("hall");
+        let vault = map.add_zone("vault");
+        map.add_to_zone(hall, (1..5).map(|x| Point::new(x, 1)));
+        map.add_to_zone(vault, [Point::new(2, 2), Point::new(4, 2)]);
+        map.add_zone("empty");
+
+        assert_eq!(map.compress_zones()[hall].runs, vec![(1, 1, 4)]);
+        assert_reversible(&map);
+    }
+
+    #[test]
+    fn serialize_3d_is_reversible() {
+        use bracket_pathfinding::prelude::Point3;
+
+        let mut map = Labyrinth3D::from_levels(vec![
+            Labyrinth2D::new_walled(4, 4),
+            Labyrinth2D::new_empty(4, 4),
+            Labyrinth2D::new(4, 4),
+        ])
+        .unwrap();
+
+        map.add_two_way_link(Link::stairs_down(
+            Point3::new(1, 1, 0),
+            Point3::new(1, 1, 1),
+        ))
+        .unwrap();
+        map.add_link(Link::pit(Point3::new(2, 2, 0), Point3::new(3, 3, 1)).with_cost(0.5))
+            .unwrap();
+
+        let raw = ron::to_string(&map).unwrap();
+        let map2 = Labyrinth3D::read_ron_from_str(&raw).unwrap();
+
+        assert_eq!(map, map2);
+        assert_eq!(map2.links().len(), 3);
+    }
+
+    #[test]
+    fn bad_link_costs_are_rejected() {
+        use bracket_pathfinding::prelude::Point3;
+
+        let mut map = Labyrinth3D::from_levels(vec![
+            Labyrinth2D::new_empty(4, 4),
+            Labyrinth2D::new_empty(4, 4),
+        ])
+        .unwrap();
+        map.add_link(Link::pit(Point3::new(2, 2, 0), Point3::new(3, 3, 1)).with_cost(0.5))
+            .unwrap();
+        let raw = ron::to_string(&map).unwrap();
+        assert!(raw.contains("cost:0.5"));
+
+        for cost in ["-3.0", "0.0", "NaN"] {
+            let bad = raw.replace("cost:0.5", &format!("cost:{}", cost));
+            let err = Labyrinth3D::read_ron_from_str(&bad).unwrap_err();
+            assert!(matches!(err, LabyrinthError::Parse(_)));
+        }
+    }
+
+    #[test]
+    fn mismatched_3d_levels_are_rejected() {
+        let levels = vec![Labyrinth2D::new(3, 3), Labyrinth2D::new(4, 4)];
+        let raw = format!("(levels: {})", ron::to_string(&levels).unwrap());
+
+        assert!(Labyrinth3D::read_ron_from_str(&raw).is_err());
+    }
+
+    #[test]
+    fn neighborhood_is_optional() {
+        let raw = r##"(
+            mapstring: ["#.#", "#.#"],
+            tiledict: {
+                '#': (kind: "wall", opaque: true, access: []),
+                '.': (kind: "floor", opaque: false, access: [Walk, Fly]),
+            },
+        )"##;
+
+        let map = Labyrinth2D::read_ron_from_str(raw).unwrap();
+
+        assert_eq!(map.neighborhood(), Neighborhood::Orthogonal);
+        assert_eq!(map.tile_kind(Point::new(1, 0)), "floor");
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        let err = Labyrinth2D::read_ron("does/not/exist.ron").unwrap_err();
+
+        assert!(matches!(err, LabyrinthError::Io(_)));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn invalid_ron_is_a_parse_error() {
+        let err = Labyrinth2D::read_ron_from_str("(mapstring: [").unwrap_err();
+
+        assert!(matches!(err, LabyrinthError::Parse(_)));
+    }
+
+    #[test]
+    fn bad_costs_are_rejected() {
+        for cost in ["-3.0", "0.0", "NaN", "inf"] {
+            let raw = format!(
+                r##"(
+                mapstring: ["#.#"],
+                tiledict: {{
+                    '#': (kind: "wall", opaque: true, access: []),
+                    '.': (kind: "mud", opaque: false, access: [Walk], costs: {{Walk: {}}}),
+                }},
+            )"##,
+                cost
+            );
+
+            let err = Labyrinth2D::read_ron_from_str(&raw).unwrap_err();
+            assert!(matches!(
+                err,
+                LabyrinthError::InvalidCost {
+                    move_type: MoveType::Walk,
+                    ..
+                }
+            ));
+        }
+
+        // tiles read on their own are checked too
+        let raw = r#"(kind: "mud", opaque: false, access: [Walk], costs: {Walk: -3.0})"#;
+        assert!(ron::from_str::<Tile>(raw).is_err());
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected() {
+        let raw = r##"(
+            mapstring: ["#.#", "#.", "#.#"],
+            tiledict: {
+                '#': (kind: "wall", opaque: true, access: []),
+                '.': (kind: "floor", opaque: false, access: [Walk, Fly]),
+            },
+        )"##;
+
+        let err = Labyrinth2D::read_ron_from_str(raw).unwrap_err();
+
+        assert!(matches!(
+            err,
+            LabyrinthError::RaggedRows {
+                row: 1,
+                expected: 3,
+                found: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn unknown_glyphs_are_rejected() {
+        let raw = r##"(
+            mapstring: ["#.#", "#?#"],
+            tiledict: {
+                '#': (kind: "wall", opaque: true, access: []),
+                '.': (kind: "floor", opaque: false, access: [Walk, Fly]),
+            },
+        )"##;
+
+        let err = Labyrinth2D::read_ron_from_str(raw).unwrap_err();
+
+        match err {
+            LabyrinthError::UnknownGlyph { glyph, position } => {
+                assert_eq!(glyph, "?");
+                assert_eq!(position, Point::new(1, 1));
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn many_custom_tiles_are_reversible() {
+        let mut map = Labyrinth2D::new(10, 10);
+        for i in 0..70 {
+            let tile = Tile::new(format!("custom{}", i), false, [MoveType::Walk]);
+            map.set_tile_at(Point::new(i % 10, i / 10), tile);
+        }
+
+        let raw = map.compress();
+        assert_eq!(raw.tiledict.len(), 71);
+        assert!(raw.mapstring.iter().all(|row| row.chars().count() == 20));
+
+        assert_reversible(&map);
+    }
+
+    #[test]
+    fn tiles_with_the_same_kind_are_kept_apart() {
+        let mut map = Labyrinth2D::new_empty(3, 1);
+        map.set_tile_at(Point::new(0, 0), Tile::new("glass", false, []));
+        map.set_tile_at(Point::new(1, 0), Tile::new("glass", true, []));
+
+        assert_eq!(map.compress().tiledict.len(), 3);
+        assert_reversible(&map);
+    }
+
+    #[test]
+    fn multi_character_symbols_are_read() {
+        let raw = r##"(
+            mapstring: ["0a0b0a", "0b0b0b"],
+            tiledict: {
+                "0a": (kind: "wall", opaque: true, access: []),
+                "0b": (kind: "floor", opaque: false, access: [Walk, Fly]),
+            },
+        )"##;
+
+        let map = Labyrinth2D::read_ron_from_str(raw).unwrap();
+
+        assert_eq!(map.dimensions, Point::new(3, 2));
+        assert_eq!(map.tile_kind(Point::new(0, 0)), "wall");
+        assert_eq!(map.tile_kind(Point::new(1, 0)), "floor");
+    }
+
+    #[test]
+    fn mixed_symbol_lengths_are_rejected() {
+        let raw = r##"(
+            mapstring: ["#.."],
+            tiledict: {
+                '#': (kind: "wall", opaque: true, access: []),
+                "..": (kind: "floor", opaque: false, access: [Walk, Fly]),
+            },
+        )"##;
+
+        let err = Labyrinth2D::read_ron_from_str(raw).unwrap_err();
+
+        assert!(matches!(err, LabyrinthError::Parse(_)));
     }
 }
@@ -0,0 +1,853 @@
+//! Module for reading and writing maps in the formats of the Tiled editor.
+//!
+//! Both the JSON (`.tmj`) and the XML (`.tmx`) map formats are supported.
+//! Every distinct tile of the map becomes a tile of a single embedded tileset,
+//! whose type is the kind of the tile, and whose custom properties hold the
+//! rest of it:
+//! - `opaque`, a bool
+//! - `access`, a comma separated list of move types (e.g. `"Walk,Fly,phase"`)
+//! - `cost:<move type>`, a float, for every movement cost that isn't the default
+//!
+//! Maps made in Tiled are read from their first tile layer, which must be
+//! stored as CSV. Missing properties are read as a non-opaque tile that can't
+//! be entered, and empty cells become walls.
+
+use super::super::{Labyrinth2D, MoveType, Point, Tile};
+use crate::errors::LabyrinthError;
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// Size in pixels of the tiles of exported maps
+const TILE_SIZE: u32 = 16;
+
+/// Name of the tileset and tile layer of exported maps
+const LAYER_NAME: &str = "labyrinth";
+
+/// Bits of a gid used by Tiled to flip and rotate tiles
+const GID_FLAGS: u32 = 0xF000_0000;
+
+/// A Tiled map, as far as labyrinth_map cares about it
+#[derive(Serialize, Deserialize)]
+struct TiledMap {
+    #[serde(rename = "type", default)]
+    map_type: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    orientation: String,
+    #[serde(default)]
+    renderorder: String,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    nextlayerid: u32,
+    #[serde(default)]
+    nextobjectid: u32,
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+}
+
+/// A layer of a Tiled map. Only tile layers are used.
+#[derive(Serialize, Deserialize)]
+struct TiledLayer {
+    #[serde(rename = "type")]
+    layer_type: String,
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    #[serde(default)]
+    x: i32,
+    #[serde(default)]
+    y: i32,
+    #[serde(default = "full_opacity")]
+    opacity: f32,
+    #[serde(default = "visible")]
+    visible: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<TiledData>,
+}
+
+/// Contents of a tile layer
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TiledData {
+    /// Global tile ids, row by row
+    Gids(Vec<u32>),
+    /// Tile ids in an encoding other than CSV
+    Encoded(String),
+}
+
+/// A tileset of a Tiled map
+#[derive(Serialize, Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(default)]
+    tiles: Vec<TiledTile>,
+}
+
+/// A tile of a tileset that has a type or properties
+#[derive(Serialize, Deserialize)]
+struct TiledTile {
+    id: u32,
+    // Tiled 1.9 calls this the class of the tile
+    #[serde(rename = "type", alias = "class", default)]
+    tile_type: String,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+/// A custom property of a tile
+#[derive(Serialize, Deserialize)]
+struct TiledProperty {
+    name: String,
+    #[serde(rename = "type", default = "string_type")]
+    property_type: String,
+    value: Value,
+}
+
+fn full_opacity() -> f32 {
+    1.0
+}
+
+fn visible() -> bool {
+    true
+}
+
+fn string_type() -> String {
+    String::from("string")
+}
+
+impl TiledProperty {
+    fn new<T: Into<Value>>(name: &str, property_type: &str, value: T) -> TiledProperty {
+        TiledProperty {
+            name: name.to_owned(),
+            property_type: property_type.to_owned(),
+            value: value.into(),
+        }
+    }
+}
+
+impl TiledTile {
+    /// Describes a tile of a map as a tile of a tileset
+    fn pack(id: u32, tile: &Tile) -> TiledTile {
+        let mut access: Vec<String> = tile.access().iter().map(MoveType::to_string).collect();
+        access.sort_unstable();
+
+        let mut properties = vec![
+            TiledProperty::new("opaque", "bool", tile.is_opaque()),
+            TiledProperty::new("access", "string", access.join(",")),
+        ];
+
+        let mut costs: Vec<(String, f32)> = tile
+            .costs()
+            .iter()
+            .map(|(move_type, &cost)| (move_type.to_string(), cost))
+            .collect();
+        costs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        properties.extend(costs.into_iter().map(|(name, cost)| {
+            TiledProperty::new(&format!("cost:{}", name), "float", cost as f64)
+        }));
+
+        TiledTile {
+            id,
+            tile_type: tile.kind().to_owned(),
+            properties,
+        }
+    }
+
+    /// Builds the tile of a map described by a tile of a tileset
+    fn unpack(&self) -> Result<Tile, LabyrinthError> {
+        let mut kind = self.tile_type.clone();
+        let mut tile = Tile::new("", false, []);
+
+        for property in self.properties.iter() {
+            let invalid = || {
+                LabyrinthError::Parse(format!(
+                    "Invalid value {} for property {:?} of tile {}",
+                    property.value, property.name, self.id
+                ))
+            };
+
+            match property.name.as_str() {
+                "kind" if kind.is_empty() => {
+                    kind = property.value.as_str().ok_or_else(invalid)?.to_owned();
+                }
+                "opaque" => tile.set_opacity(property.value.as_bool().ok_or_else(invalid)?),
+                "access" => tile.add_movetypes(
+                    property
+                        .value
+                        .as_str()
+                        .ok_or_else(invalid)?
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .flat_map(str::parse::<MoveType>),
+                ),
+                name => {
+                    if let Some(move_type) = name.strip_prefix("cost:") {
+                        let cost = property
+                            .value
+                            .as_f64()
+                            .filter(|cost| cost.is_finite() && *cost > 0.0)
+                            .ok_or_else(invalid)?;
+                        let Ok(move_type) = move_type.parse();
+                        tile.set_cost(move_type, cost as f32);
+                    }
+                }
+            }
+        }
+
+        if kind.is_empty() {
+            return Err(LabyrinthError::Parse(format!(
+                "Tile {} has no type or kind property",
+                self.id
+            )));
+        }
+        tile.set_kind(kind);
+
+        Ok(tile)
+    }
+}
+
+/// Implementation of Tiled-related methods for Labyrinth2D
+impl Labyrinth2D {
+    // ------------------ Tiled JSON API --------------------------
+    /// Writes the map to a Tiled JSON (`.tmj`) file.
+    pub fn dump_tmj(&self, fname: &str) -> Result<(), LabyrinthError> {
+        fs::write(fname, self.to_tmj_string()?)?;
+        Ok(())
+    }
+
+    /// Turns the map into a Tiled JSON map.
+    pub fn to_tmj_string(&self) -> Result<String, LabyrinthError> {
+        serde_json::to_string_pretty(&self.to_tiled())
+            .map_err(|err| LabyrinthError::Serialize(err.to_string()))
+    }
+
+    /// Reads a map from a Tiled JSON (`.tmj`) file.
+    pub fn read_tmj(fname: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        let raw_data = fs::read_to_string(fname)?;
+
+        Labyrinth2D::read_tmj_from_str(&raw_data)
+    }
+
+    /// Reads a map from a Tiled JSON string.
+    pub fn read_tmj_from_str(raw: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        let tiled =
+            serde_json::from_str(raw).map_err(|err| LabyrinthError::Parse(err.to_string()))?;
+
+        Labyrinth2D::from_tiled(tiled)
+    }
+
+    // ------------------ Tiled XML API --------------------------
+    /// Writes the map to a Tiled XML (`.tmx`) file.
+    pub fn dump_tmx(&self, fname: &str) -> Result<(), LabyrinthError> {
+        fs::write(fname, self.to_tmx_string())?;
+        Ok(())
+    }
+
+    /// Turns the map into a Tiled XML map.
+    pub fn to_tmx_string(&self) -> String {
+        write_tmx(&self.to_tiled())
+    }
+
+    /// Reads a map from a Tiled XML (`.tmx`) file.
+    pub fn read_tmx(fname: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        let raw_data = fs::read_to_string(fname)?;
+
+        Labyrinth2D::read_tmx_from_str(&raw_data)
+    }
+
+    /// Reads a map from a Tiled XML string.
+    pub fn read_tmx_from_str(raw: &str) -> Result<Labyrinth2D, LabyrinthError> {
+        Labyrinth2D::from_tiled(parse_tmx(raw)?)
+    }
+
+    /// Constructs a Tiled map with a tileset holding every distinct tile
+    fn to_tiled(&self) -> TiledMap {
+        let (palette, indices) = self.palette();
+        let width = self.dimensions.x as u32;
+        let height = self.dimensions.y as u32;
+
+        let tileset = TiledTileset {
+            firstgid: 1,
+            name: LAYER_NAME.to_owned(),
+            tilewidth: TILE_SIZE,
+            tileheight: TILE_SIZE,
+            tilecount: palette.len() as u32,
+            columns: 0,
+            source: None,
+            tiles: palette
+                .iter()
+                .enumerate()
+                .map(|(id, tile)| TiledTile::pack(id as u32, tile))
+                .collect(),
+        };
+
+        let layer = TiledLayer {
+            layer_type: String::from("tilelayer"),
+            id: 1,
+            name: LAYER_NAME.to_owned(),
+            width,
+            height,
+            x: 0,
+            y: 0,
+            opacity: 1.0,
+            visible: true,
+            encoding: None,
+            data: Some(TiledData::Gids(
+                indices.into_iter().map(|i| i as u32 + 1).collect(),
+            )),
+        };
+
+        TiledMap {
+            map_type: String::from("map"),
+            version: String::from("1.10"),
+            orientation: String::from("orthogonal"),
+            renderorder: String::from("right-down"),
+            width,
+            height,
+            tilewidth: TILE_SIZE,
+            tileheight: TILE_SIZE,
+            infinite: false,
+            nextlayerid: 2,
+            nextobjectid: 1,
+            layers: vec![layer],
+            tilesets: vec![tileset],
+        }
+    }
+
+    /// Constructs a Labyrinth2D from the first tile layer of a Tiled map
+    fn from_tiled(tiled: TiledMap) -> Result<Labyrinth2D, LabyrinthError> {
+        if tiled.infinite {
+            return Err(LabyrinthError::Parse(String::from(
+                "Infinite Tiled maps are not supported",
+            )));
+        }
+
+        if let Some(source) = tiled.tilesets.iter().find_map(|ts| ts.source.as_ref()) {
+            return Err(LabyrinthError::Parse(format!(
+                "External tileset {:?} is not supported, embed it in the map",
+                source
+            )));
+        }
+
+        let layer = tiled
+            .layers
+            .iter()
+            .find(|layer| layer.layer_type == "tilelayer")
+            .ok_or_else(|| LabyrinthError::Parse(String::from("Map has no tile layer")))?;
+
+        let gids = match &layer.data {
+            Some(TiledData::Gids(gids)) => gids,
+            _ => {
+                return Err(LabyrinthError::Parse(format!(
+                    "Layer encoding {:?} is not supported, use CSV",
+                    layer.encoding.as_deref().unwrap_or("none")
+                )))
+            }
+        };
+
+        let (width, height) = (tiled.width as usize, tiled.height as usize);
+        if gids.len() != width * height {
+            return Err(LabyrinthError::Parse(format!(
+                "Layer has {} tiles, expected {}x{}",
+                gids.len(),
+                width,
+                height
+            )));
+        }
+
+        // tilesets sorted by descending firstgid, so the first one at or
+        // below a gid is the one it belongs to
+        let mut tilesets: Vec<&TiledTileset> = tiled.tilesets.iter().collect();
+        tilesets.sort_unstable_by_key(|tileset| std::cmp::Reverse(tileset.firstgid));
+
+        // tiles are unpacked once per gid, not once per cell
+        let mut unpacked: Vec<(u32, Tile)> = vec![];
+
+        let mut tiles = Vec::with_capacity(gids.len());
+        for (i, &gid) in gids.iter().enumerate() {
+            let gid = gid & !GID_FLAGS;
+            if gid == 0 {
+                tiles.push(Tile::default());
+                continue;
+            }
+
+            if let Some((_, tile)) = unpacked.iter().find(|(known, _)| *known == gid) {
+                tiles.push(tile.clone());
+                continue;
+            }
+
+            let tile = tilesets
+                .iter()
+                .find(|tileset| tileset.firstgid <= gid)
+                .and_then(|tileset| {
+                    let id = gid - tileset.firstgid;
+                    tileset.tiles.iter().find(|tile| tile.id == id)
+                })
+                .ok_or_else(|| LabyrinthError::UnknownGlyph {
+                    glyph: gid.to_string(),
+                    position: Point::new(i % width, i / width),
+                })?
+                .unpack()?;
+
+            unpacked.push((gid, tile.clone()));
+            tiles.push(tile);
+        }
+
+        Ok(Labyrinth2D::from_tiles(tiles, Point::new(width, height)))
+    }
+}
+
+/// Escapes a string for use in an XML attribute
+fn escape(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes a Tiled map as TMX
+fn write_tmx(tiled: &TiledMap) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    out += &format!(
+        "<map version=\"{}\" orientation=\"{}\" renderorder=\"{}\" width=\"{}\" height=\"{}\" \
+         tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" nextlayerid=\"{}\" nextobjectid=\"{}\">\n",
+        tiled.version,
+        tiled.orientation,
+        tiled.renderorder,
+        tiled.width,
+        tiled.height,
+        tiled.tilewidth,
+        tiled.tileheight,
+        tiled.nextlayerid,
+        tiled.nextobjectid
+    );
+
+    for tileset in tiled.tilesets.iter() {
+        out += &format!(
+            " <tileset firstgid=\"{}\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" \
+             tilecount=\"{}\" columns=\"{}\">\n",
+            tileset.firstgid,
+            escape(&tileset.name),
+            tileset.tilewidth,
+            tileset.tileheight,
+            tileset.tilecount,
+            tileset.columns
+        );
+
+        for tile in tileset.tiles.iter() {
+            out += &format!(
+                "  <tile id=\"{}\" type=\"{}\">\n   <properties>\n",
+                tile.id,
+                escape(&tile.tile_type)
+            );
+
+            for property in tile.properties.iter() {
+                let value = match &property.value {
+                    Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                out += &format!(
+                    "    <property name=\"{}\" type=\"{}\" value=\"{}\"/>\n",
+                    escape(&property.name),
+                    property.property_type,
+                    escape(&value)
+                );
+            }
+
+            out += "   </properties>\n  </tile>\n";
+        }
+
+        out += " </tileset>\n";
+    }
+
+    for layer in tiled.layers.iter() {
+        out += &format!(
+            " <layer id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\">\n  <data encoding=\"csv\">\n",
+            layer.id,
+            escape(&layer.name),
+            layer.width,
+            layer.height
+        );
+
+        if let Some(TiledData::Gids(gids)) = &layer.data {
+            let rows: Vec<String> = gids
+                .chunks(layer.width.max(1) as usize)
+                .map(|row| {
+                    row.iter()
+                        .map(|gid| gid.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .collect();
+            out += &rows.join(",\n");
+            out += "\n";
+        }
+
+        out += "</data>\n </layer>\n";
+    }
+
+    out += "</map>\n";
+    out
+}
+
+/// Finds the value of an attribute of an XML element
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attr| attr.name.local_name == name)
+        .map(|attr| attr.value.as_str())
+}
+
+/// Finds the value of a numeric attribute of an XML element
+fn number_attribute<T: std::str::FromStr + Default>(
+    attributes: &[OwnedAttribute],
+    name: &str,
+) -> Result<T, LabyrinthError> {
+    match attribute(attributes, name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| LabyrinthError::Parse(format!("Invalid value {:?} for {}", value, name))),
+        None => Ok(T::default()),
+    }
+}
+
+/// Reads the value of a TMX property, which is always written as a string
+fn property_value(property_type: &str, value: &str) -> Value {
+    match property_type {
+        "bool" => value.parse::<bool>().map_or(Value::Null, Value::from),
+        "int" | "float" => value.parse::<f64>().map_or(Value::Null, Value::from),
+        _ => Value::from(value),
+    }
+}
+
+/// Parses the parts of a TMX map labyrinth_map cares about
+fn parse_tmx(raw: &str) -> Result<TiledMap, LabyrinthError> {
+    let parse_error = |err: xml::reader::Error| LabyrinthError::Parse(err.to_string());
+
+    let mut tiled: Option<TiledMap> = None;
+    // the elements the reader is inside of, from the outermost one
+    let mut elements: Vec<String> = vec![];
+
+    for event in EventReader::from_str(raw) {
+        match event.map_err(parse_error)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let attrs = attributes.as_slice();
+                let parent = elements.last().cloned().unwrap_or_default();
+                let in_tile = elements.len() >= 3
+                    && elements[elements.len() - 3..] == ["tileset", "tile", "properties"];
+                elements.push(name.local_name.clone());
+
+                if name.local_name == "map" {
+                    tiled = Some(TiledMap {
+                        map_type: String::from("map"),
+                        version: attribute(attrs, "version").unwrap_or("").to_owned(),
+                        orientation: attribute(attrs, "orientation").unwrap_or("").to_owned(),
+                        renderorder: attribute(attrs, "renderorder").unwrap_or("").to_owned(),
+                        width: number_attribute(attrs, "width")?,
+                        height: number_attribute(attrs, "height")?,
+                        tilewidth: number_attribute(attrs, "tilewidth")?,
+                        tileheight: number_attribute(attrs, "tileheight")?,
+                        infinite: attribute(attrs, "infinite") == Some("1"),
+                        nextlayerid: number_attribute(attrs, "nextlayerid")?,
+                        nextobjectid: number_attribute(attrs, "nextobjectid")?,
+                        layers: vec![],
+                        tilesets: vec![],
+                    });
+                    continue;
+                }
+
+                let tiled = tiled.as_mut().ok_or_else(|| {
+                    LabyrinthError::Parse(format!("Unexpected <{}> outside of <map>", name))
+                })?;
+
+                match name.local_name.as_str() {
+                    "tileset" => tiled.tilesets.push(TiledTileset {
+                        firstgid: number_attribute(attrs, "firstgid")?,
+                        name: attribute(attrs, "name").unwrap_or("").to_owned(),
+                        tilewidth: number_attribute(attrs, "tilewidth")?,
+                        tileheight: number_attribute(attrs, "tileheight")?,
+                        tilecount: number_attribute(attrs, "tilecount")?,
+                        columns: number_attribute(attrs, "columns")?,
+                        source: attribute(attrs, "source").map(str::to_owned),
+                        tiles: vec![],
+                    }),
+                    "tile" => {
+                        // <tile> is both a tile of a tileset and a cell of an
+                        // XML encoded layer
+                        if parent == "data" {
+                            if let Some(TiledData::Gids(gids)) = tiled
+                                .layers
+                                .last_mut()
+                                .and_then(|layer| layer.data.as_mut())
+                            {
+                                gids.push(number_attribute(attrs, "gid")?);
+                            }
+                        } else if let Some(tileset) =
+                            tiled.tilesets.last_mut().filter(|_| parent == "tileset")
+                        {
+                            tileset.tiles.push(TiledTile {
+                                id: number_attribute(attrs, "id")?,
+                                tile_type: attribute(attrs, "type")
+                                    .or_else(|| attribute(attrs, "class"))
+                                    .unwrap_or("")
+                                    .to_owned(),
+                                properties: vec![],
+                            });
+                        }
+                    }
+                    // only the properties of tileset tiles are read, not
+                    // those of the map, layers or objects
+                    "property" if in_tile => {
+                        let tile = tiled
+                            .tilesets
+                            .last_mut()
+                            .and_then(|tileset| tileset.tiles.last_mut());
+                        if let Some(tile) = tile {
+                            let property_type = attribute(attrs, "type").unwrap_or("string");
+                            let value = attribute(attrs, "value").unwrap_or("");
+                            tile.properties.push(TiledProperty::new(
+                                attribute(attrs, "name").unwrap_or(""),
+                                property_type,
+                                property_value(property_type, value),
+                            ));
+                        }
+                    }
+                    "layer" => tiled.layers.push(TiledLayer {
+                        layer_type: String::from("tilelayer"),
+                        id: number_attribute(attrs, "id")?,
+                        name: attribute(attrs, "name").unwrap_or("").to_owned(),
+                        width: number_attribute(attrs, "width")?,
+                        height: number_attribute(attrs, "height")?,
+                        x: 0,
+                        y: 0,
+                        opacity: 1.0,
+                        visible: true,
+                        encoding: None,
+                        data: None,
+                    }),
+                    "data" => {
+                        if let Some(layer) = tiled.layers.last_mut() {
+                            layer.encoding = attribute(attrs, "encoding").map(str::to_owned);
+                            layer.data = match layer.encoding.as_deref() {
+                                // csv gids are filled in from the text, and
+                                // XML ones from the <tile> elements
+                                Some("csv") | None => Some(TiledData::Gids(vec![])),
+                                Some(_) => Some(TiledData::Encoded(String::new())),
+                            };
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                elements.pop();
+            }
+            XmlEvent::Characters(text) if elements.last().is_some_and(|name| name == "data") => {
+                let layer = tiled.as_mut().and_then(|tiled| tiled.layers.last_mut());
+                if let Some(layer) = layer {
+                    if let Some(TiledData::Gids(gids)) = &mut layer.data {
+                        for gid in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                            gids.push(gid.parse().map_err(|_| {
+                                LabyrinthError::Parse(format!("Invalid tile id {:?}", gid))
+                            })?);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tiled.ok_or_else(|| LabyrinthError::Parse(String::from("Document has no <map> element")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepare_testmap() -> Labyrinth2D {
+        let mut map = Labyrinth2D::new_walled(5, 4);
+
+        let mut mud = Tile::new("mud", false, [MoveType::Walk, MoveType::custom("dig")]);
+        mud.set_cost(MoveType::Walk, 3.0);
+
+        map.set_tile_at(Point::new(1, 1), mud);
+        map.set_tile_at(Point::new(2, 1), Tile::water());
+        map.set_tile_at(Point::new(3, 2), Tile::new("glass", false, []));
+        map.set_tile_at(Point::new(1, 2), Tile::new("glass", true, []));
+
+        map
+    }
+
+    #[test]
+    fn tmj_is_reversible() {
+        let map = prepare_testmap();
+        let raw = map.to_tmj_string().unwrap();
+
+        assert_eq!(Labyrinth2D::read_tmj_from_str(&raw).unwrap(), map);
+    }
+
+    #[test]
+    fn tmx_is_reversible() {
+        let map = prepare_testmap();
+        let raw = map.to_tmx_string();
+
+        assert_eq!(Labyrinth2D::read_tmx_from_str(&raw).unwrap(), map);
+    }
+
+    #[test]
+    fn hand_authored_tmx_is_read() {
+        let raw = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="8" tileheight="8" infinite="0">
+ <tileset firstgid="1" name="walls" tilewidth="8" tileheight="8" tilecount="4" columns="2">
+  <image source="walls.png" width="16" height="16"/>
+  <tile id="2" class="wall">
+   <properties>
+    <property name="opaque" type="bool" value="true"/>
+   </properties>
+  </tile>
+ </tileset>
+ <tileset firstgid="5" name="ground" tilewidth="8" tileheight="8" tilecount="1" columns="1">
+  <tile id="0">
+   <properties>
+    <property name="kind" value="bog"/>
+    <property name="access" value="Walk, Swim, wade"/>
+    <property name="cost:Walk" type="float" value="2.5"/>
+   </properties>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  <data>
+   <tile gid="3"/><tile gid="5"/><tile gid="3"/>
+   <tile gid="2147483653"/><tile/><tile gid="3"/>
+  </data>
+ </layer>
+</map>"#;
+
+        let map = Labyrinth2D::read_tmx_from_str(raw).unwrap();
+
+        assert_eq!(map.tile_kind(Point::new(0, 0)), "wall");
+        assert!(map.tiles()[0].is_opaque());
+
+        // flipped tiles are the same tile
+        let bog = Point::new(0, 1);
+        assert_eq!(map.tile_kind(bog), "bog");
+        assert!(map.can_enter(bog, &[MoveType::custom("Wade")]));
+        assert_eq!(map.tile_cost(bog, &[MoveType::Walk]), Some(2.5));
+
+        // empty cells are walls
+        assert_eq!(map.tiles()[4], Tile::wall());
+    }
+
+    #[test]
+    fn only_tile_properties_are_read() {
+        let raw = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="1" tilewidth="8" tileheight="8" infinite="0">
+ <properties>
+  <property name="opaque" type="bool" value="true"/>
+ </properties>
+ <tileset firstgid="1" name="ground" tilewidth="8" tileheight="8" tilecount="1" columns="1">
+  <tile id="0" type="floor">
+   <properties>
+    <property name="access" value="Walk"/>
+   </properties>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="2" height="1">
+  <properties>
+   <property name="kind" value="lava"/>
+   <property name="access" value="Fly"/>
+   <property name="cost:Walk" type="float" value="9"/>
+  </properties>
+  <data encoding="csv">1,1</data>
+ </layer>
+ <objectgroup id="2" name="things">
+  <object id="1" x="0" y="0">
+   <properties>
+    <property name="opaque" type="bool" value="true"/>
+   </properties>
+  </object>
+ </objectgroup>
+</map>"#;
+
+        let map = Labyrinth2D::read_tmx_from_str(raw).unwrap();
+        let floor = &map.tiles()[0];
+
+        assert_eq!(floor.kind(), "floor");
+        assert!(!floor.is_opaque());
+        assert!(floor.can_enter(&[MoveType::Walk]));
+        assert!(!floor.can_enter(&[MoveType::Fly]));
+        assert_eq!(floor.cost(&[MoveType::Walk]), Some(1.0));
+    }
+
+    #[test]
+    fn encoded_layers_are_rejected() {
+        let raw = r#"{
+            "width": 1, "height": 1, "infinite": false,
+            "layers": [{"type": "tilelayer", "encoding": "base64", "data": "AQAAAA=="}],
+            "tilesets": [{"firstgid": 1, "tiles": [{"id": 0, "type": "floor"}]}]
+        }"#;
+
+        let err = Labyrinth2D::read_tmj_from_str(raw).unwrap_err();
+
+        assert!(matches!(err, LabyrinthError::Parse(_)));
+    }
+
+    #[test]
+    fn unknown_tiles_are_rejected() {
+        let raw = r#"{
+            "width": 2, "height": 1, "infinite": false,
+            "layers": [{"type": "tilelayer", "data": [1, 2]}],
+            "tilesets": [{"firstgid": 1, "tiles": [{"id": 0, "type": "floor"}]}]
+        }"#;
+
+        match Labyrinth2D::read_tmj_from_str(raw).unwrap_err() {
+            LabyrinthError::UnknownGlyph { glyph, position } => {
+                assert_eq!(glyph, "2");
+                assert_eq!(position, Point::new(1, 0));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! This module holds the [`MovementView`] struct, a borrowed view of a
+//! [`Labyrinth2D`] for an entity with a specific set of [`MoveType`]s.
+//!
+//!
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use bracket_pathfinding::prelude::*;
+
+use super::{Labyrinth2D, MoveType};
+
+/// Default cost multiplier for a diagonal step.
+pub const DIAGONAL_COST: f32 = std::f32::consts::SQRT_2;
+
+/// Movement types of a plain walker
+const WALK: &[MoveType] = &[MoveType::Walk];
+
+/// Enum defining which neighboring tiles can be reached in a single step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum Neighborhood {
+    /// 4-way movement, orthogonal steps only
+    #[default]
+    Orthogonal,
+
+    /// 8-way movement, orthogonal and diagonal steps
+    Diagonal,
+
+    /// 8-way movement, but diagonal steps can't cut past a corner. Both
+    /// orthogonal tiles next to the step must be enterable and not opaque.
+    DiagonalNoCornerCutting,
+}
+
+impl Neighborhood {
+    /// Whether or not diagonal steps are allowed at all
+    pub fn allows_diagonals(&self) -> bool {
+        !matches!(self, Neighborhood::Orthogonal)
+    }
+
+    /// Returns the unit step offsets that are part of the neighborhood
+    pub fn deltas(&self) -> &'static [Point] {
+        const DELTAS: [Point; 8] = [
+            Point { x: -1, y: 0 },
+            Point { x: 0, y: -1 },
+            Point { x: 1, y: 0 },
+            Point { x: 0, y: 1 },
+            Point { x: -1, y: -1 },
+            Point { x: 1, y: -1 },
+            Point { x: 1, y: 1 },
+            Point { x: -1, y: 1 },
+        ];
+
+        if self.allows_diagonals() {
+            &DELTAS
+        } else {
+            &DELTAS[..4]
+        }
+    }
+
+    /// Shortest possible distance between two [`Points`](Point) on an open
+    /// map, given the cost of a diagonal step. Used as the A* heuristic.
+    pub fn distance(&self, start: Point, end: Point, diagonal_cost: f32) -> f32 {
+        let dx = (start.x - end.x).abs() as f32;
+        let dy = (start.y - end.y).abs() as f32;
+
+        if self.allows_diagonals() {
+            let (long, short) = (dx.max(dy), dx.min(dy));
+            (long - short) + short * diagonal_cost.min(2.0)
+        } else {
+            dx + dy
+        }
+    }
+}
+
+/// Borrowed view of a [`Labyrinth2D`] together with the movement types of an
+/// entity moving through it.
+///
+/// Implements [`Algorithm2D`] and [`BaseMap`] traits from bracket-pathfinding,
+/// so any bracket-lib pathfinding algorithm can be run on it without needing
+/// mutable access to the map. Since it only holds a shared reference, many
+/// views can be used at the same time (i.e. across threads).
+///
+/// The view uses the [`Neighborhood`] of the map unless it's overridden with
+/// [`MovementView::with_neighborhood()`].
+/// ```rust
+/// use bracket_pathfinding::prelude::*;
+/// use labyrinth_map::prelude::*;
+///
+/// let map = Labyrinth2D::new_empty(10, 10);
+/// let view = map.movement_view([MoveType::Fly]);
+///
+/// let path = a_star_search(
+///     view.point2d_to_index(Point::new(0, 0)),
+///     view.point2d_to_index(Point::new(9, 9)),
+///     &view,
+/// );
+/// assert!(path.success);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MovementView<'a> {
+    map: &'a Labyrinth2D,
+    move_types: Cow<'a, [MoveType]>,
+    neighborhood: Neighborhood,
+    diagonal_cost: f32,
+
+    // Cheapest step on the map, used to keep the A* heuristic admissible.
+    // Only computed when the heuristic is first needed.
+    min_cost: OnceLock<f32>,
+}
+
+impl<'a> MovementView<'a> {
+    /// Constructs a new [`MovementView`] over a [`Labyrinth2D`].
+    ///
+    /// An empty collection of move types is treated as [`MoveType::Walk`].
+    pub fn new<T>(map: &'a Labyrinth2D, move_types: T) -> MovementView<'a>
+    where
+        T: Into<Vec<MoveType>>,
+    {
+        let mut move_types: Vec<MoveType> = move_types
+            .into()
+            .into_iter()
+            .map(|move_type| match move_type {
+                MoveType::Custom(kind) => MoveType::custom(kind),
+                _ => move_type,
+            })
+            .collect();
+
+        if move_types.is_empty() {
+            move_types.push(MoveType::Walk);
+        }
+
+        move_types.sort();
+        move_types.dedup();
+
+        if move_types == WALK {
+            return MovementView::walking(map);
+        }
+
+        MovementView {
+            map,
+            move_types: Cow::Owned(move_types),
+            neighborhood: map.neighborhood(),
+            diagonal_cost: DIAGONAL_COST,
+            min_cost: OnceLock::new(),
+        }
+    }
+
+    /// Constructs a walking view, which allocates nothing and shares the
+    /// cheapest walking cost the map keeps
+    pub(crate) fn walking(map: &'a Labyrinth2D) -> MovementView<'a> {
+        MovementView {
+            map,
+            move_types: Cow::Borrowed(WALK),
+            neighborhood: map.neighborhood(),
+            diagonal_cost: DIAGONAL_COST,
+            min_cost: OnceLock::new(),
+        }
+    }
+
+    /// Overrides the [`Neighborhood`] of the map for this view
+    pub fn with_neighborhood(mut self, neighborhood: Neighborhood) -> MovementView<'a> {
+        self.neighborhood = neighborhood;
+        self
+    }
+
+    /// Sets the cost multiplier of a diagonal step. Defaults to [`DIAGONAL_COST`].
+    ///
+    /// Costs below 1.0 are raised to 1.0, since a diagonal step that's
+    /// cheaper than an orthogonal one would make the A* heuristic overestimate.
+    pub fn with_diagonal_cost(mut self, diagonal_cost: f32) -> MovementView<'a> {
+        self.diagonal_cost = diagonal_cost.max(1.0);
+        self
+    }
+
+    /// Getter for the underlying [`Labyrinth2D`]
+    pub fn map(&self) -> &'a Labyrinth2D {
+        self.map
+    }
+
+    /// Getter for the movement types used by the view
+    pub fn move_types(&self) -> &[MoveType] {
+        &self.move_types
+    }
+
+    /// Getter for the [`Neighborhood`] used by the view
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    fn min_cost(&self) -> f32 {
+        *self.min_cost.get_or_init(|| {
+            if self.move_types() == WALK {
+                return self.map.walk_min_cost();
+            }
+
+            self.map
+                .iter()
+                .filter_map(|tile| tile.cost(self.move_types()))
+                .fold(1.0, f32::min)
+        })
+    }
+
+    /// Whether or not a diagonal step from a [`Point`] squeezes past a corner
+    fn cuts_corner(&self, start: Point, delta: Point) -> bool {
+        [Point::new(delta.x, 0), Point::new(0, delta.y)]
+            .iter()
+            .map(|&diff| start + diff)
+            .any(|pt| {
+                self.map.tile_at(pt).is_opaque() || !self.map.can_enter(pt, self.move_types())
+            })
+    }
+}
+
+impl Algorithm2D for MovementView<'_> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+impl BaseMap for MovementView<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let start = self.index_to_point2d(idx);
+
+        self.neighborhood
+            .deltas()
+            .iter()
+            // filter to only points in map bounds
+            .filter(|&&diff| self.in_bounds(start + diff))
+            // diagonal steps can't squeeze past corners, if disallowed
+            .filter(|&&diff| {
+                diff.x == 0
+                    || diff.y == 0
+                    || self.neighborhood != Neighborhood::DiagonalNoCornerCutting
+                    || !self.cuts_corner(start, diff)
+            })
+            // filter to only tiles that are enterable, along with their cost
+            .filter_map(|&diff| {
+                let step = if diff.x == 0 || diff.y == 0 {
+                    1.0
+                } else {
+                    self.diagonal_cost
+                };
+
+                self.map
+                    .tile_cost(start + diff, self.move_types())
+                    .map(|cost| (start + diff, cost * step))
+            })
+            // map points -> vector indices, and package into final struct
+            .map(|(pt, cost)| (self.point2d_to_index(pt), cost))
+            // finally, collect into the final SmallVec
+            .collect::<SmallVec<[(_, _); 10]>>()
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        // scaled down so that it never overestimates on maps with cheap tiles
+        self.neighborhood.distance(
+            self.index_to_point2d(idx1),
+            self.index_to_point2d(idx2),
+            self.diagonal_cost,
+        ) * self.min_cost()
+    }
+}
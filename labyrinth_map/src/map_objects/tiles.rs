@@ -5,7 +5,16 @@
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::LabyrinthError;
+
+/// Default cost of entering a closed or locked door, which has to be opened
+/// first.
+pub const DOOR_COST: f32 = 2.0;
 
 /// Enum defining possible movement methods
 #[derive(PartialEq, Eq, Ord, PartialOrd, Hash, Debug, Clone)]
@@ -35,6 +44,54 @@ impl MoveType {
 
         MoveType::Custom(lcase)
     }
+
+    /// Convenience function for the move type of entities that can open
+    /// doors. Closed doors can only be entered with it, at [`DOOR_COST`].
+    pub fn opener() -> MoveType {
+        MoveType::custom("open")
+    }
+}
+
+/// Enum defining the states of the door tiles
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum DoorState {
+    /// An open door, as built by [`Tile::open_door()`]
+    Open,
+
+    /// A closed door, as built by [`Tile::door()`]
+    Closed,
+
+    /// A locked door, as built by [`Tile::locked_door()`]
+    Locked,
+}
+
+/// Writes the name of the move type, as used by the text map formats:
+/// `Walk`, `Fly`, `Swim`, or the name of a custom move type.
+impl fmt::Display for MoveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveType::Walk => write!(f, "Walk"),
+            MoveType::Fly => write!(f, "Fly"),
+            MoveType::Swim => write!(f, "Swim"),
+            MoveType::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Reads a move type from its name. Anything other than `Walk`, `Fly` or
+/// `Swim` is a custom move type.
+impl FromStr for MoveType {
+    type Err = Infallible;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "Walk" => MoveType::Walk,
+            "Fly" => MoveType::Fly,
+            "Swim" => MoveType::Swim,
+            _ => MoveType::custom(name),
+        })
+    }
 }
 
 /// Tile struct that contains its name (for differentiation purposes),
@@ -58,8 +115,50 @@ impl MoveType {
 /// - Chasm through [`Tile::chasm()`]
 ///     - Doesn't block vision
 ///     - Passable for flyers
-#[derive(Clone, Debug, Eq, PartialEq)]
-#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+///
+/// ## Doors
+/// Doors are tiles too, and change from one kind to another as they are
+/// opened, closed or unlocked:
+/// - Closed door through [`Tile::door()`]
+///     - Blocks vision
+///     - Passable only for [`MoveType::opener()`], at [`DOOR_COST`]
+/// - Open door through [`Tile::open_door()`]
+///     - Doesn't block vision
+///     - Passable for walkers and flyers, like floor
+/// - Locked door through [`Tile::locked_door()`]
+///     - Blocks vision
+///     - Passable only for the custom move type of its key, at [`DOOR_COST`]
+///
+/// To find paths through doors that still have to be opened, pathfind with
+/// [`MoveType::opener()`] (and any keys) next to the usual move types:
+/// ```rust
+/// use bracket_pathfinding::prelude::*;
+/// use labyrinth_map::prelude::*;
+///
+/// let mut map = Labyrinth2D::new_empty(3, 1);
+/// map.set_tile_at(Point::new(1, 0), Tile::door());
+///
+/// let blocked = map.find_path(Point::new(0, 0), Point::new(2, 0), [MoveType::Walk]);
+/// assert!(!blocked.success);
+///
+/// let path = map.find_path(
+///     Point::new(0, 0),
+///     Point::new(2, 0),
+///     [MoveType::Walk, MoveType::opener()],
+/// );
+/// assert!(path.success);
+/// ```
+///
+/// # Movement Costs
+/// Entering a tile costs 1.0 by default. A different cost can be set for
+/// each [`MoveType`] with [`Tile::set_cost()`], and an entity with several
+/// move types uses the cheapest one that can enter the tile.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialization",
+    derive(Serialize, Deserialize),
+    serde(try_from = "RawTile")
+)]
 pub struct Tile {
     /// The kind of tile it is.
     kind: String,
@@ -69,6 +168,67 @@ pub struct Tile {
 
     /// A hashset that defines the movement types that can enter the Tile.
     access: HashSet<MoveType>,
+
+    /// Costs of entering the Tile for each movement type, if not the default.
+    costs: HashMap<MoveType, f32>,
+}
+
+// Costs are always finite (enforced in Tile::set_cost, and checked when
+// deserializing), so equality is total.
+impl Eq for Tile {}
+
+/// Serialized form of a [`Tile`], before its costs are checked
+#[cfg(feature = "serialization")]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename = "Tile")]
+pub(crate) struct RawTile {
+    kind: String,
+    opaque: bool,
+    access: HashSet<MoveType>,
+    #[serde(default)]
+    costs: HashMap<MoveType, f32>,
+}
+
+#[cfg(feature = "serialization")]
+impl From<&Tile> for RawTile {
+    fn from(tile: &Tile) -> RawTile {
+        RawTile {
+            kind: tile.kind.clone(),
+            opaque: tile.opaque,
+            access: tile.access.clone(),
+            costs: tile.costs.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl TryFrom<RawTile> for Tile {
+    type Error = LabyrinthError;
+
+    fn try_from(raw: RawTile) -> Result<Tile, LabyrinthError> {
+        for (move_type, &cost) in raw.costs.iter() {
+            check_cost(move_type, cost)?;
+        }
+
+        Ok(Tile {
+            kind: raw.kind,
+            opaque: raw.opaque,
+            access: raw.access,
+            costs: raw.costs,
+        })
+    }
+}
+
+/// Checks that a movement cost is finite and positive
+fn check_cost(move_type: &MoveType, cost: f32) -> Result<(), LabyrinthError> {
+    if cost.is_finite() && cost > 0.0 {
+        Ok(())
+    } else {
+        Err(LabyrinthError::InvalidCost {
+            move_type: move_type.clone(),
+            cost,
+        })
+    }
 }
 
 impl Default for Tile {
@@ -96,6 +256,7 @@ impl Tile {
             kind: kind.into().to_lowercase(),
             access: access_map,
             opaque,
+            costs: HashMap::new(),
         }
     }
 
@@ -124,6 +285,36 @@ impl Tile {
         Tile::new("chasm", false, [MoveType::Fly])
     }
 
+    /// Direct constructor for a closed door tile
+    pub fn door() -> Tile {
+        let mut door = Tile::new("door", true, [MoveType::opener()]);
+        door.set_cost(MoveType::opener(), DOOR_COST);
+        door
+    }
+
+    /// Direct constructor for an open door tile
+    pub fn open_door() -> Tile {
+        Tile::new("open_door", false, [MoveType::Walk, MoveType::Fly])
+    }
+
+    /// Direct constructor for a locked door tile, which can only be entered
+    /// with a key: a custom [`MoveType`].
+    pub fn locked_door(key: MoveType) -> Tile {
+        let mut door = Tile::new("locked_door", true, [key.clone()]);
+        door.set_cost(key, DOOR_COST);
+        door
+    }
+
+    /// Returns the state of the tile if it is a door
+    pub fn door_state(&self) -> Option<DoorState> {
+        match self.kind.as_str() {
+            "open_door" => Some(DoorState::Open),
+            "door" => Some(DoorState::Closed),
+            "locked_door" => Some(DoorState::Locked),
+            _ => None,
+        }
+    }
+
     /// Getter for the Tile kind
     pub fn kind(&self) -> &String {
         &self.kind
@@ -189,7 +380,7 @@ impl Tile {
         move_types
             .into_iter()
             .map(|move_type| match move_type {
-                MoveType::Custom(kind) => MoveType::custom(&kind.clone()),
+                MoveType::Custom(kind) => MoveType::custom(kind.clone()),
                 _ => move_type.clone(),
             })
             .any(|move_type| self.access.contains(&move_type))
@@ -199,6 +390,55 @@ impl Tile {
     pub fn access(&self) -> &HashSet<MoveType> {
         &self.access
     }
+
+    /// Sets the cost of entering the tile with a given movement type.
+    ///
+    /// # Panics
+    /// Panics if the cost is not finite and positive.
+    pub fn set_cost(&mut self, movtype: MoveType, cost: f32) {
+        assert!(
+            cost.is_finite() && cost > 0.0,
+            "Tile costs must be finite and positive, got {}",
+            cost
+        );
+
+        match movtype {
+            MoveType::Custom(str) => self.costs.insert(MoveType::custom(str), cost),
+            _ => self.costs.insert(movtype, cost),
+        };
+    }
+
+    /// Resets the cost of entering the tile with a given movement type back to
+    /// the default.
+    pub fn remove_cost(&mut self, movtype: &MoveType) -> Option<f32> {
+        match movtype {
+            MoveType::Custom(str) => self.costs.remove(&MoveType::custom(str)),
+            _ => self.costs.remove(movtype),
+        }
+    }
+
+    /// Returns the non-default movement costs of a tile
+    pub fn costs(&self) -> &HashMap<MoveType, f32> {
+        &self.costs
+    }
+
+    /// Returns the cost for an entity with the given move types to enter the
+    /// tile, using the cheapest move type that can enter it. Returns `None` if
+    /// the tile can't be entered at all.
+    pub fn cost<'a, T>(&self, move_types: T) -> Option<f32>
+    where
+        T: IntoIterator<Item = &'a MoveType>,
+    {
+        move_types
+            .into_iter()
+            .map(|move_type| match move_type {
+                MoveType::Custom(kind) => MoveType::custom(kind),
+                _ => move_type.clone(),
+            })
+            .filter(|move_type| self.access.contains(move_type))
+            .map(|move_type| self.costs.get(&move_type).copied().unwrap_or(1.0))
+            .reduce(f32::min)
+    }
 }
 
 /// Builder Struct for Tiles
@@ -206,6 +446,13 @@ pub struct TileBuilder {
     kind: Option<String>,
     opaque: Option<bool>,
     access: Vec<MoveType>,
+    costs: Vec<(MoveType, f32)>,
+}
+
+impl Default for TileBuilder {
+    fn default() -> Self {
+        TileBuilder::new()
+    }
 }
 
 impl TileBuilder {
@@ -215,6 +462,7 @@ impl TileBuilder {
             kind: None,
             opaque: None,
             access: vec![],
+            costs: vec![],
         }
     }
 
@@ -224,6 +472,7 @@ impl TileBuilder {
             kind: Some(String::from("wall")),
             opaque: Some(true),
             access: vec![],
+            costs: vec![],
         }
     }
 
@@ -233,6 +482,7 @@ impl TileBuilder {
             kind: Some(String::from("floor")),
             opaque: Some(false),
             access: vec![MoveType::Walk, MoveType::Fly],
+            costs: vec![],
         }
     }
 
@@ -242,6 +492,7 @@ impl TileBuilder {
             kind: Some(String::from("water")),
             opaque: Some(false),
             access: vec![MoveType::Swim, MoveType::Fly],
+            costs: vec![],
         }
     }
 
@@ -251,6 +502,7 @@ impl TileBuilder {
             kind: Some(String::from("lava")),
             opaque: Some(false),
             access: vec![MoveType::Fly],
+            costs: vec![],
         }
     }
 
@@ -260,6 +512,17 @@ impl TileBuilder {
             kind: Some(String::from("chasm")),
             opaque: Some(false),
             access: vec![MoveType::Fly],
+            costs: vec![],
+        }
+    }
+
+    /// Closed door Template constructor for TileBuilder
+    pub fn door() -> TileBuilder {
+        TileBuilder {
+            kind: Some(String::from("door")),
+            opaque: Some(true),
+            access: vec![MoveType::opener()],
+            costs: vec![(MoveType::opener(), DOOR_COST)],
         }
     }
 
@@ -285,18 +548,29 @@ impl TileBuilder {
         self
     }
 
+    /// Method for setting the cost of entering the tile with a movement type.
+    /// The cost must be finite and positive, or building fails.
+    pub fn with_cost(mut self, movtype: MoveType, cost: f32) -> TileBuilder {
+        self.costs.push((movtype, cost));
+        self
+    }
+
     /// Terminal method for TileBuilder. Returns a Result with either the Tile
     /// or an error.
-    pub fn build(self) -> Result<Tile, String> {
-        if self.opaque.is_none() && self.kind.is_none() {
-            return Err(String::from("Builder not fully initialized!"));
+    pub fn build(self) -> Result<Tile, LabyrinthError> {
+        let kind = self.kind.ok_or(LabyrinthError::BuilderIncomplete("kind"))?;
+        let opaque = self
+            .opaque
+            .ok_or(LabyrinthError::BuilderIncomplete("opaque"))?;
+
+        let mut tile = Tile::new(&kind, opaque, self.access);
+
+        for (movtype, cost) in self.costs {
+            check_cost(&movtype, cost)?;
+            tile.set_cost(movtype, cost);
         }
 
-        Ok(Tile::new(
-            &self.kind.unwrap(),
-            self.opaque.unwrap(),
-            self.access,
-        ))
+        Ok(tile)
     }
 }
 
@@ -307,6 +581,7 @@ mod tests {
     macro_rules! set {
     ( $( $x:expr ),* ) => {  // Match zero or more comma delimited items
         {
+            #[allow(unused_mut)]
             let mut temp_set = HashSet::new();  // Create a mutable HashSet
             $(
                 temp_set.insert($x); // Insert each item matched into the HashSet
@@ -377,6 +652,33 @@ mod tests {
         is_chasm(tile);
     }
 
+    #[test]
+    fn door_templates() {
+        let key = MoveType::custom("red_key");
+
+        assert_eq!(Tile::door().door_state(), Some(DoorState::Closed));
+        assert_eq!(Tile::open_door().door_state(), Some(DoorState::Open));
+        assert_eq!(
+            Tile::locked_door(key.clone()).door_state(),
+            Some(DoorState::Locked)
+        );
+        assert_eq!(Tile::floor().door_state(), None);
+
+        // closed doors are opened on the way, at a higher cost
+        assert_eq!(Tile::door().cost(&[MoveType::Walk]), None);
+        assert_eq!(
+            Tile::door().cost(&[MoveType::Walk, MoveType::opener()]),
+            Some(DOOR_COST)
+        );
+        assert!(Tile::open_door().can_enter(&[MoveType::Walk]));
+        assert!(Tile::open_door().can_enter(&[MoveType::Fly]));
+        assert_eq!(Tile::open_door().access(), Tile::floor().access());
+
+        // locked doors need their key
+        assert!(!Tile::locked_door(key.clone()).can_enter(&[MoveType::opener()]));
+        assert_eq!(Tile::locked_door(key.clone()).cost(&[key]), Some(DOOR_COST));
+    }
+
     #[test]
     fn tile_enterable_with_one_matching_movtype() {
         let custom_tile = Tile::floor();
@@ -399,7 +701,7 @@ mod tests {
     }
 
     #[test]
-    fn movetypes_can_be_directly_added_to_tiles() -> Result<(), String> {
+    fn movetypes_can_be_directly_added_to_tiles() -> Result<(), LabyrinthError> {
         let mut tile = Tile::wall();
         tile.add_movetype(MoveType::custom("dig"));
 
@@ -453,6 +755,44 @@ mod tests {
         assert!(!res);
     }
 
+    #[test]
+    fn default_cost_is_one() {
+        let tile = Tile::floor();
+
+        assert_eq!(tile.cost(&[MoveType::Walk]), Some(1.0));
+        assert_eq!(tile.cost(&[MoveType::Swim]), None);
+    }
+
+    #[test]
+    fn cheapest_movetype_cost_is_used() {
+        let mut tile = Tile::water();
+        tile.set_cost(MoveType::Swim, 3.0);
+        tile.set_cost(MoveType::Fly, 0.5);
+
+        assert_eq!(tile.cost(&[MoveType::Swim]), Some(3.0));
+        assert_eq!(tile.cost(&[MoveType::Swim, MoveType::Fly]), Some(0.5));
+
+        // costs for movetypes without access are ignored
+        tile.set_cost(MoveType::Walk, 0.1);
+        assert_eq!(tile.cost(&[MoveType::Walk, MoveType::Swim]), Some(3.0));
+    }
+
+    #[test]
+    fn custom_movetype_costs_are_case_insensitive() {
+        let mut tile = Tile::new("mud", false, [MoveType::custom("wade")]);
+        tile.set_cost(MoveType::Custom(String::from("Wade")), 2.0);
+
+        assert_eq!(tile.cost(&[MoveType::custom("wade")]), Some(2.0));
+        assert_eq!(tile.remove_cost(&MoveType::custom("WADE")), Some(2.0));
+        assert_eq!(tile.cost(&[MoveType::custom("wade")]), Some(1.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn costs_must_be_positive() {
+        Tile::floor().set_cost(MoveType::Walk, 0.0);
+    }
+
     // TileBuilder tests
     #[test]
     #[should_panic]
@@ -461,17 +801,50 @@ mod tests {
     }
 
     #[test]
-    fn builder_templates() -> Result<(), String> {
+    fn builder_reports_missing_field() {
+        let err = TileBuilder::new().with_opacity(true).build().unwrap_err();
+        assert!(matches!(err, LabyrinthError::BuilderIncomplete("kind")));
+
+        let err = TileBuilder::new().with_kind("ice").build().unwrap_err();
+        assert!(matches!(err, LabyrinthError::BuilderIncomplete("opaque")));
+    }
+
+    #[test]
+    fn builder_rejects_bad_costs() {
+        for cost in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let err = TileBuilder::floor()
+                .with_cost(MoveType::Walk, cost)
+                .build()
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                LabyrinthError::InvalidCost {
+                    move_type: MoveType::Walk,
+                    ..
+                }
+            ));
+        }
+
+        let tile = TileBuilder::floor()
+            .with_cost(MoveType::Walk, 0.5)
+            .build()
+            .unwrap();
+        assert_eq!(tile.cost(&[MoveType::Walk]), Some(0.5));
+    }
+
+    #[test]
+    fn builder_templates() -> Result<(), LabyrinthError> {
         assert_eq!(Tile::wall(), TileBuilder::wall().build()?);
         assert_eq!(Tile::floor(), TileBuilder::floor().build()?);
         assert_eq!(Tile::water(), TileBuilder::water().build()?);
         assert_eq!(Tile::lava(), TileBuilder::lava().build()?);
         assert_eq!(Tile::chasm(), TileBuilder::chasm().build()?);
+        assert_eq!(Tile::door(), TileBuilder::door().build()?);
         Ok(())
     }
 
     #[test]
-    fn tile_config_works() -> Result<(), String> {
+    fn tile_config_works() -> Result<(), LabyrinthError> {
         let newtile: Tile = TileBuilder::new()
             .with_kind("slime")
             .with_opacity(false)
@@ -483,7 +856,7 @@ mod tests {
             .build()?;
 
         assert_eq!(newtile.kind, String::from("slime"));
-        assert_eq!(newtile.opaque, false);
+        assert!(!newtile.opaque);
 
         let mut expected_access = HashSet::new();
         expected_access.insert(MoveType::Fly);
@@ -493,4 +866,17 @@ mod tests {
         assert_eq!(newtile.access(), &expected_access);
         Ok(())
     }
+
+    #[test]
+    fn builder_sets_costs() -> Result<(), LabyrinthError> {
+        let shallows = TileBuilder::water()
+            .with_kind("shallows")
+            .with_access(&[MoveType::Walk])
+            .with_cost(MoveType::Walk, 2.5)
+            .build()?;
+
+        assert_eq!(shallows.cost(&[MoveType::Walk]), Some(2.5));
+        assert_eq!(shallows.cost(&[MoveType::Fly]), Some(1.0));
+        Ok(())
+    }
 }
@@ -0,0 +1,268 @@
+//! This module holds the field of view computation for [`Labyrinth2D`],
+//! using symmetric shadowcasting.
+//!
+//! Based on Albert Ford's description of the algorithm, with exact rational
+//! slopes so that the results don't depend on floating point rounding.
+
+use std::collections::HashSet;
+
+use bracket_pathfinding::prelude::*;
+
+use super::Labyrinth2D;
+
+/// Slope of a line from the origin, stored as an exact fraction.
+/// The denominator is always positive.
+#[derive(Clone, Copy, Debug)]
+struct Slope {
+    num: i32,
+    den: i32,
+}
+
+impl Slope {
+    fn new(num: i32, den: i32) -> Slope {
+        Slope { num, den }
+    }
+
+    /// Slope of the line from the origin to the near corner of a tile
+    fn of_tile(depth: i32, col: i32) -> Slope {
+        Slope::new(2 * col - 1, 2 * depth)
+    }
+}
+
+/// A row of tiles in a quadrant, at a given depth from the origin
+#[derive(Clone, Copy, Debug)]
+struct Row {
+    depth: i32,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// Lowest column in the row, rounding half-way values up
+    fn min_col(&self) -> i32 {
+        let Slope { num, den } = self.start;
+        (2 * self.depth * num + den).div_euclid(2 * den)
+    }
+
+    /// Highest column in the row, rounding half-way values down
+    fn max_col(&self) -> i32 {
+        let Slope { num, den } = self.end;
+        -(-(2 * self.depth * num - den)).div_euclid(2 * den)
+    }
+
+    /// Whether or not a tile in the row lies inside the sector of the row.
+    /// Only such tiles are revealed, which keeps the results symmetric.
+    fn is_symmetric(&self, col: i32) -> bool {
+        col * self.start.den >= self.depth * self.start.num
+            && col * self.end.den <= self.depth * self.end.num
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+/// The four cardinal quadrants scanned around the origin
+#[derive(Clone, Copy, Debug)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    /// Transforms a (depth, col) position in the quadrant to a map [`Point`]
+    fn transform(&self, origin: Point, depth: i32, col: i32) -> Point {
+        match self {
+            Quadrant::North => Point::new(origin.x + col, origin.y - depth),
+            Quadrant::South => Point::new(origin.x + col, origin.y + depth),
+            Quadrant::East => Point::new(origin.x + depth, origin.y + col),
+            Quadrant::West => Point::new(origin.x - depth, origin.y + col),
+        }
+    }
+}
+
+impl Labyrinth2D {
+    /// Computes the set of [`Points`](Point) visible from an origin, up to a
+    /// given radius, using symmetric shadowcasting on the opacity of the tiles.
+    ///
+    /// The origin is always visible. Opaque tiles are visible, but block the
+    /// vision of anything behind them. Visibility is symmetric: if `b` is in
+    /// the field of view of `a`, then `a` is in the field of view of `b`.
+    /// ```rust
+    /// use bracket_pathfinding::prelude::*;
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// let mut map = Labyrinth2D::new_walled(10, 10);
+    /// map.set_tile_at(Point::new(5, 4), Tile::wall());
+    ///
+    /// let fov = map.field_of_view(Point::new(5, 5), 8);
+    /// assert!(fov.contains(&Point::new(5, 4)));
+    /// assert!(!fov.contains(&Point::new(5, 2)));
+    /// ```
+    pub fn field_of_view(&self, origin: Point, radius: i32) -> HashSet<Point> {
+        let mut visible = HashSet::new();
+
+        if !self.in_bounds(origin) {
+            return visible;
+        }
+        visible.insert(origin);
+
+        let is_wall = |pt: Point| !self.in_bounds(pt) || self.tile_at(pt).is_opaque();
+        let in_radius = |pt: Point| {
+            let (dx, dy) = (pt.x - origin.x, pt.y - origin.y);
+            dx * dx + dy * dy <= radius * radius
+        };
+
+        for quadrant in [
+            Quadrant::North,
+            Quadrant::East,
+            Quadrant::South,
+            Quadrant::West,
+        ] {
+            let mut rows = vec![Row {
+                depth: 1,
+                start: Slope::new(-1, 1),
+                end: Slope::new(1, 1),
+            }];
+
+            while let Some(mut row) = rows.pop() {
+                if row.depth > radius {
+                    continue;
+                }
+
+                let mut prev_wall: Option<bool> = None;
+
+                for col in row.min_col()..=row.max_col() {
+                    let pt = quadrant.transform(origin, row.depth, col);
+                    let wall = is_wall(pt);
+
+                    if (wall || row.is_symmetric(col)) && self.in_bounds(pt) && in_radius(pt) {
+                        visible.insert(pt);
+                    }
+
+                    match (prev_wall, wall) {
+                        // coming out of a wall, narrow the start of the row
+                        (Some(true), false) => row.start = Slope::of_tile(row.depth, col),
+                        // running into a wall, scan the lit part of the next row
+                        (Some(false), true) => {
+                            let mut next = row.next();
+                            next.end = Slope::of_tile(row.depth, col);
+                            rows.push(next);
+                        }
+                        _ => {}
+                    }
+
+                    prev_wall = Some(wall);
+                }
+
+                if prev_wall == Some(false) {
+                    rows.push(row.next());
+                }
+            }
+        }
+
+        visible
+    }
+
+    /// Checks whether a target [`Point`] is visible from an origin, within a
+    /// given radius.
+    pub fn is_visible(&self, origin: Point, target: Point, radius: i32) -> bool {
+        self.field_of_view(origin, radius).contains(&target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prelude::Tile;
+
+    #[test]
+    fn open_map_is_visible_within_radius() {
+        let map = Labyrinth2D::new_empty(11, 11);
+        let origin = Point::new(5, 5);
+
+        let fov = map.field_of_view(origin, 3);
+
+        assert!(fov.contains(&origin));
+        assert!(fov.contains(&Point::new(8, 5)));
+        assert!(fov.contains(&Point::new(7, 7)));
+        assert!(!fov.contains(&Point::new(9, 5)));
+        assert!(!fov.contains(&Point::new(8, 8)));
+
+        let expected = (0..11)
+            .flat_map(|x| (0..11).map(move |y| Point::new(x, y)))
+            .filter(|pt| (pt.x - 5).pow(2) + (pt.y - 5).pow(2) <= 9)
+            .count();
+        assert_eq!(fov.len(), expected);
+    }
+
+    #[test]
+    fn walls_block_vision() {
+        let mut map = Labyrinth2D::new_empty(9, 9);
+        let origin = Point::new(4, 4);
+        for y in 0..9 {
+            map.set_tile_at(Point::new(6, y), Tile::wall());
+        }
+
+        let fov = map.field_of_view(origin, 10);
+
+        // the wall itself is visible, but not past it
+        assert!(fov.contains(&Point::new(6, 4)));
+        assert!(!fov.contains(&Point::new(7, 4)));
+        assert!((0..9).all(|y| !fov.contains(&Point::new(8, y))));
+        assert!(map.is_visible(origin, Point::new(0, 0), 10));
+        assert!(!map.is_visible(origin, Point::new(8, 0), 10));
+    }
+
+    #[test]
+    fn transparent_tiles_do_not_block_vision() {
+        let mut map = Labyrinth2D::new_empty(9, 1);
+        map.set_tile_at(Point::new(4, 0), Tile::water());
+        map.set_tile_at(Point::new(5, 0), Tile::chasm());
+
+        assert!(map.is_visible(Point::new(0, 0), Point::new(8, 0), 10));
+
+        map.set_tile_opacity(Point::new(4, 0), true);
+        assert!(!map.is_visible(Point::new(0, 0), Point::new(8, 0), 10));
+    }
+
+    #[test]
+    fn field_of_view_is_symmetric() {
+        let mut map = Labyrinth2D::new_walled(12, 12);
+        for pt in [
+            Point::new(3, 3),
+            Point::new(4, 3),
+            Point::new(7, 2),
+            Point::new(5, 6),
+            Point::new(8, 8),
+            Point::new(2, 8),
+            Point::new(9, 4),
+        ] {
+            map.set_tile_at(pt, Tile::wall());
+        }
+
+        let floors: Vec<Point> = (0..map.size())
+            .map(|idx| map.index_to_point2d(idx))
+            .filter(|&pt| !map.is_opaque(map.point2d_to_index(pt)))
+            .collect();
+
+        for &a in floors.iter() {
+            let fov = map.field_of_view(a, 20);
+            for &b in floors.iter() {
+                assert_eq!(
+                    fov.contains(&b),
+                    map.field_of_view(b, 20).contains(&a),
+                    "asymmetric between {:?} and {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+}
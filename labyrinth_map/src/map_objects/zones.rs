@@ -0,0 +1,183 @@
+//! This module holds the zones of a [`Labyrinth2D`]: labelled areas of the
+//! map, such as rooms, arenas or shops.
+//!
+//! Zones are numbered in the order they are added. Every tile belongs to at
+//! most one zone, and a zone doesn't need to be contiguous.
+
+use bracket_pathfinding::prelude::*;
+
+use super::Labyrinth2D;
+
+/// Implementation of the zone layer of Labyrinth2D
+impl Labyrinth2D {
+    /// Adds a new zone with a label, without any tiles in it yet, and returns
+    /// its number.
+    ///
+    /// # Example Usage
+    /// ```rust
+    /// use bracket_pathfinding::prelude::*;
+    /// use labyrinth_map::prelude::*;
+    ///
+    /// let mut map = Labyrinth2D::new_walled(10, 10);
+    ///
+    /// let shop = map.add_zone("shop");
+    /// map.add_to_zone(shop, [Point::new(1, 1), Point::new(2, 1)]);
+    ///
+    /// assert_eq!(map.zone_at(Point::new(2, 1)), Some(shop));
+    /// assert_eq!(map.find_zone("shop"), Some(shop));
+    /// assert_eq!(map.zone_points(shop).len(), 2);
+    /// ```
+    pub fn add_zone<T: Into<String>>(&mut self, label: T) -> usize {
+        self.zone_labels.push(label.into());
+        self.zone_labels.len() - 1
+    }
+
+    /// Number of zones in the map
+    pub fn zone_count(&self) -> usize {
+        self.zone_labels.len()
+    }
+
+    /// Getter for the label of a zone
+    pub fn zone_label(&self, zone: usize) -> Option<&str> {
+        self.zone_labels.get(zone).map(String::as_str)
+    }
+
+    /// Finds the first zone with a given label
+    pub fn find_zone(&self, label: &str) -> Option<usize> {
+        self.zone_labels.iter().position(|other| other == label)
+    }
+
+    /// The zone a [`Point`] belongs to, if any
+    pub fn zone_at(&self, loc: Point) -> Option<usize> {
+        if self.in_bounds(loc) {
+            self.zones[self.point2d_to_index(loc)]
+        } else {
+            None
+        }
+    }
+
+    /// Sets the zone of the tile at a given [`Point`], or takes it out of its
+    /// zone with `None`.
+    ///
+    /// # Panics
+    /// Panics if the zone was never added to the map.
+    pub fn set_zone_at(&mut self, loc: Point, zone: Option<usize>) {
+        if let Some(zone) = zone {
+            assert!(zone < self.zone_count(), "Zone {} doesn't exist", zone);
+        }
+
+        let idx = self.point2d_to_index(loc);
+        self.zones[idx] = zone;
+    }
+
+    /// Puts every given [`Point`] inside the map into a zone.
+    ///
+    /// # Panics
+    /// Panics if the zone was never added to the map.
+    pub fn add_to_zone<T>(&mut self, zone: usize, points: T)
+    where
+        T: IntoIterator<Item = Point>,
+    {
+        for loc in points {
+            if self.in_bounds(loc) {
+                self.set_zone_at(loc, Some(zone));
+            }
+        }
+    }
+
+    /// The [`Points`](Point) of a zone, row by row
+    pub fn zone_points(&self, zone: usize) -> Vec<Point> {
+        self.zones
+            .iter()
+            .enumerate()
+            .filter(|&(_, &other)| other == Some(zone))
+            .map(|(idx, _)| self.index_to_point2d(idx))
+            .collect()
+    }
+
+    /// The zones with a tile orthogonally next to a tile of the given zone,
+    /// in order.
+    pub fn zone_neighbors(&self, zone: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self
+            .zone_points(zone)
+            .into_iter()
+            .flat_map(|pt| {
+                [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                    .into_iter()
+                    .map(move |(dx, dy)| pt + Point::new(dx, dy))
+            })
+            .filter_map(|pt| self.zone_at(pt))
+            .filter(|&other| other != zone)
+            .collect();
+
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    /// Removes every zone from the map
+    pub fn clear_zones(&mut self) {
+        self.zones.iter_mut().for_each(|zone| *zone = None);
+        self.zone_labels.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepare_zoned_map() -> (Labyrinth2D, [usize; 3]) {
+        let mut map = Labyrinth2D::new_empty(6, 3);
+
+        let west = map.add_zone("west");
+        let east = map.add_zone("east");
+        let far = map.add_zone("far");
+
+        map.add_to_zone(west, (0..3).map(|y| Point::new(0, y)));
+        map.add_to_zone(east, (0..3).map(|y| Point::new(1, y)));
+        map.add_to_zone(far, [Point::new(5, 2)]);
+
+        (map, [west, east, far])
+    }
+
+    #[test]
+    fn zones_are_queried() {
+        let (map, [west, east, far]) = prepare_zoned_map();
+
+        assert_eq!(map.zone_count(), 3);
+        assert_eq!(map.zone_label(east), Some("east"));
+        assert_eq!(map.zone_at(Point::new(0, 1)), Some(west));
+        assert_eq!(map.zone_at(Point::new(3, 1)), None);
+        assert_eq!(map.zone_at(Point::new(-1, 1)), None);
+        assert_eq!(map.zone_points(far), vec![Point::new(5, 2)]);
+    }
+
+    #[test]
+    fn neighbors_touch_orthogonally() {
+        let (map, [west, east, far]) = prepare_zoned_map();
+
+        assert_eq!(map.zone_neighbors(west), vec![east]);
+        assert_eq!(map.zone_neighbors(east), vec![west]);
+        assert!(map.zone_neighbors(far).is_empty());
+    }
+
+    #[test]
+    fn zones_survive_resizing() {
+        let (mut map, [west, _, far]) = prepare_zoned_map();
+
+        map.resize(3, 4);
+
+        assert_eq!(map.zone_at(Point::new(0, 2)), Some(west));
+        assert_eq!(map.zone_at(Point::new(0, 3)), None);
+        assert!(map.zone_points(far).is_empty());
+        assert_eq!(map.tile_kind(Point::new(2, 3)), "wall");
+        assert_eq!(map.tile_kind(Point::new(2, 2)), "floor");
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_zones_panic() {
+        let mut map = Labyrinth2D::new(3, 3);
+        map.set_zone_at(Point::new(1, 1), Some(0));
+    }
+}
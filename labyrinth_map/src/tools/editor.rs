@@ -33,7 +33,7 @@ impl GameState for State {
             TileType::Water => "water",
             TileType::Lava => "lava",
             TileType::Chasm => "chasm",
-            TileType::Custom(str) => &str,
+            TileType::Custom(str) => str,
         };
 
         ctx.print(52, 2, format!("Click to set tile to {}", tilename));
@@ -88,12 +88,9 @@ fn process_character(gs: &mut State, c: char) {
 }
 
 fn export(gs: &State) {
-    match gs.map.dump_ron("map.ron") {
-        Err(e) => {
-            println!("{}", e)
-        }
-        _ => {}
-    };
+    if let Err(e) = gs.map.dump_ron("map.ron") {
+        println!("{}", e)
+    }
 }
 
 fn import(gs: &mut State) {